@@ -7,8 +7,23 @@ use syn::{Fields, Ident};
 pub fn component_derive(input: TokenStream) -> TokenStream {
   let ast: syn::DeriveInput = syn::parse(input).unwrap();
   let name = &ast.ident;
+  let (impl_generics, type_generics, where_clause) = ast.generics.split_for_impl();
   quote! {
-    impl Component for #name {}
+    impl #impl_generics Component for #name #type_generics #where_clause {
+      fn component_info() -> crate::ecs::ComponentInfo {
+        #[allow(unused_imports)]
+        use crate::ecs::{
+          DebugFallback, DebugProbe, DefaultFallback, DefaultProbe, MapEntitiesFallback,
+          MapEntitiesProbe, SerdeFallback, SerdeProbe,
+        };
+        let probe = crate::ecs::Probe::<Self>::new();
+        crate::ecs::ComponentInfo::of::<Self>()
+          .with_serde((&&probe).serde())
+          .with_debug((&&probe).debug())
+          .with_default((&&probe).default_fn())
+          .with_map_entities((&&probe).map_entities())
+      }
+    }
   }
   .into()
 }
@@ -19,9 +34,13 @@ pub fn event_data_derive(input: TokenStream) -> TokenStream {
   let name = &ast.ident;
   quote! {
     impl EventData for #name {
-      type Item = TimeDelta;
+      type Item = Self;
 
-      fn get_item(tick: &Tick/* , command_managers: _ */) -> Self::Item { tick.delta(&Tick::new()) }
+      fn get_item(tick: &Tick, event_manager: &EventManager) -> Result<Self::Item, EventError> {
+        event_manager
+          .latest::<Self>()
+          .ok_or(EventError::CannotFetchEventData(TypeId::of::<Self>(), *tick))
+      }
     }
   }
   .into()
@@ -0,0 +1,130 @@
+use super::Handle;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Weak;
+
+// Shared by every asset type so an id never points at two assets
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn next_id() -> u64 {
+  NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+struct Entry<T> {
+  asset: T,
+  // None when only weak handles were ever given out, those assets stay until removed
+  token: Option<Weak<()>>,
+}
+
+// Resource holding every loaded asset of one type
+pub struct Assets<T> {
+  assets: HashMap<u64, Entry<T>>,
+}
+
+impl<T> Assets<T> {
+  pub fn new() -> Self {
+    Self {
+      assets: HashMap::new(),
+    }
+  }
+
+  pub fn add(&mut self, asset: T) -> Handle<T> {
+    let (handle, token) = Handle::strong(next_id());
+    self.assets.insert(
+      handle.id(),
+      Entry {
+        asset,
+        token: Some(token),
+      },
+    );
+    handle
+  }
+
+  // Returns the asset that was replaced
+  pub fn insert(&mut self, handle: &Handle<T>, asset: T) -> Option<T> {
+    let token = match self.assets.remove(&handle.id()) {
+      Some(entry) if entry.token.is_some() => entry.token,
+      _ => handle.token(),
+    };
+    let entry = Entry { asset, token };
+    self
+      .assets
+      .insert(handle.id(), entry)
+      .map(|entry| entry.asset)
+  }
+
+  pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+    self.get_by_id(handle.id())
+  }
+  pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+    self
+      .assets
+      .get_mut(&handle.id())
+      .map(|entry| &mut entry.asset)
+  }
+
+  pub fn remove(&mut self, handle: &Handle<T>) -> Option<T> {
+    self.assets.remove(&handle.id()).map(|entry| entry.asset)
+  }
+
+  pub fn contains(&self, handle: &Handle<T>) -> bool {
+    self.assets.contains_key(&handle.id())
+  }
+
+  pub fn len(&self) -> usize {
+    self.assets.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.assets.is_empty()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+    self
+      .assets
+      .iter()
+      .map(|(id, entry)| (Handle::weak(*id), &entry.asset))
+  }
+
+  pub(crate) fn get_by_id(&self, id: u64) -> Option<&T> {
+    self.assets.get(&id).map(|entry| &entry.asset)
+  }
+
+  pub(crate) fn insert_by_id(&mut self, id: u64, token: Weak<()>, asset: T) -> Option<T> {
+    self
+      .assets
+      .insert(
+        id,
+        Entry {
+          asset,
+          token: Some(token),
+        },
+      )
+      .map(|entry| entry.asset)
+  }
+
+  // Drops the assets no strong handle points to anymore, returns their ids
+  pub(crate) fn collect_unused(&mut self) -> Vec<u64> {
+    let unused: Vec<_> = self
+      .assets
+      .iter()
+      .filter(|(_, entry)| {
+        entry
+          .token
+          .as_ref()
+          .is_some_and(|token| token.strong_count() == 0)
+      })
+      .map(|(id, _)| *id)
+      .collect();
+    for id in unused.iter() {
+      self.assets.remove(id);
+    }
+    unused
+  }
+}
+
+impl<T> Default for Assets<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -0,0 +1,92 @@
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::{Arc, Weak};
+
+use crate::ecs::Component;
+
+// Points at an asset stored in Assets<T>, cheap to clone and usable as a component
+// Strong handles keep the asset loaded, it is dropped with the last of them
+pub struct Handle<T> {
+  id: u64,
+  token: Option<Arc<()>>,
+  marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+  // Returns the handle along with what the owner of the asset uses to tell when it's unused
+  pub(crate) fn strong(id: u64) -> (Self, Weak<()>) {
+    let token = Arc::new(());
+    let weak = Arc::downgrade(&token);
+    (
+      Self {
+        id,
+        token: Some(token),
+        marker: PhantomData,
+      },
+      weak,
+    )
+  }
+
+  pub(crate) fn from_token(id: u64, token: Arc<()>) -> Self {
+    Self {
+      id,
+      token: Some(token),
+      marker: PhantomData,
+    }
+  }
+
+  // Doesn't keep the asset alive
+  pub fn weak(id: u64) -> Self {
+    Self {
+      id,
+      token: None,
+      marker: PhantomData,
+    }
+  }
+
+  pub fn id(&self) -> u64 {
+    self.id
+  }
+
+  pub fn is_strong(&self) -> bool {
+    self.token.is_some()
+  }
+
+  pub fn as_weak(&self) -> Self {
+    Self::weak(self.id)
+  }
+
+  pub(crate) fn token(&self) -> Option<Weak<()>> {
+    self.token.as_ref().map(Arc::downgrade)
+  }
+}
+
+impl<T: Send + Sync + 'static> Component for Handle<T> {}
+
+// Derives would require T to implement the traits as well
+impl<T> Clone for Handle<T> {
+  fn clone(&self) -> Self {
+    Self {
+      id: self.id,
+      token: self.token.clone(),
+      marker: PhantomData,
+    }
+  }
+}
+impl<T> PartialEq for Handle<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+impl<T> Eq for Handle<T> {}
+impl<T> Hash for Handle<T> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.id.hash(state);
+  }
+}
+impl<T> Debug for Handle<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.id)
+  }
+}
@@ -0,0 +1,142 @@
+use crate::error::AssetError;
+use crate::rendering::Image;
+
+use std::any::{Any, TypeId};
+use std::path::{Path, PathBuf};
+
+// Turns the bytes of a file into an asset, runs on the loading thread
+pub trait AssetLoader: Send + Sync + 'static {
+  type Asset: Send + Sync + Any;
+
+  // Lowercase, without the dot
+  fn extensions(&self) -> &[&str];
+  fn load(&self, bytes: &[u8], path: &Path) -> Result<Self::Asset, AssetError>;
+}
+
+pub(crate) type ErasedAsset = Box<dyn Any + Send + Sync>;
+
+// Lets the server store loaders of any asset type side by side
+pub(crate) trait ErasedLoader: Send + Sync {
+  fn asset_type(&self) -> TypeId;
+  fn load(&self, bytes: &[u8], path: &Path) -> Result<ErasedAsset, AssetError>;
+}
+
+impl<L: AssetLoader> ErasedLoader for L {
+  fn asset_type(&self) -> TypeId {
+    TypeId::of::<L::Asset>()
+  }
+
+  fn load(&self, bytes: &[u8], path: &Path) -> Result<ErasedAsset, AssetError> {
+    AssetLoader::load(self, bytes, path).map(|asset| Box::new(asset) as ErasedAsset)
+  }
+}
+
+// Decodes PNGs into RGBA8
+pub struct ImageLoader;
+
+impl AssetLoader for ImageLoader {
+  type Asset = Image;
+
+  fn extensions(&self) -> &[&str] {
+    &["png"]
+  }
+
+  fn load(&self, bytes: &[u8], _: &Path) -> Result<Image, AssetError> {
+    Image::from_png(bytes)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shader {
+  pub path: PathBuf,
+  pub source: String,
+}
+
+pub struct ShaderLoader;
+
+impl AssetLoader for ShaderLoader {
+  type Asset = Shader;
+
+  fn extensions(&self) -> &[&str] {
+    &["wgsl"]
+  }
+
+  fn load(&self, bytes: &[u8], path: &Path) -> Result<Shader, AssetError> {
+    Ok(Shader {
+      path: path.to_owned(),
+      source: String::from_utf8(bytes.to_vec())?,
+    })
+  }
+}
+
+// Encoded audio, decoding is left to whatever plays it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AudioSource {
+  pub extension: String,
+  pub bytes: Vec<u8>,
+}
+
+pub struct AudioLoader;
+
+impl AssetLoader for AudioLoader {
+  type Asset = AudioSource;
+
+  fn extensions(&self) -> &[&str] {
+    &["wav", "ogg", "mp3", "flac"]
+  }
+
+  fn load(&self, bytes: &[u8], path: &Path) -> Result<AudioSource, AssetError> {
+    Ok(AudioSource {
+      extension: extension(path).unwrap_or_default(),
+      bytes: bytes.to_vec(),
+    })
+  }
+}
+
+// Source of a won script, parsed by the interpreter when it runs since its AST borrows the source
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Script {
+  pub path: PathBuf,
+  pub source: String,
+}
+
+pub struct ScriptLoader;
+
+impl AssetLoader for ScriptLoader {
+  type Asset = Script;
+
+  fn extensions(&self) -> &[&str] {
+    &["won"]
+  }
+
+  fn load(&self, bytes: &[u8], path: &Path) -> Result<Script, AssetError> {
+    Ok(Script {
+      path: path.to_owned(),
+      source: String::from_utf8(bytes.to_vec())?,
+    })
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneFile(pub serde_json::Value);
+
+pub struct SceneLoader;
+
+impl AssetLoader for SceneLoader {
+  type Asset = SceneFile;
+
+  fn extensions(&self) -> &[&str] {
+    &["scene"]
+  }
+
+  fn load(&self, bytes: &[u8], _: &Path) -> Result<SceneFile, AssetError> {
+    Ok(SceneFile(serde_json::from_slice(bytes)?))
+  }
+}
+
+pub(crate) fn extension(path: &Path) -> Option<String> {
+  path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .map(|extension| extension.to_lowercase())
+}
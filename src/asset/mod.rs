@@ -0,0 +1,189 @@
+mod assets;
+mod handle;
+mod loader;
+mod server;
+
+pub use assets::Assets;
+pub use handle::Handle;
+pub use loader::{
+  AssetLoader, AudioLoader, AudioSource, ImageLoader, SceneFile, SceneLoader, Script, ScriptLoader,
+  Shader, ShaderLoader,
+};
+pub use server::{AssetServer, LoadState};
+
+#[cfg(test)]
+mod tests {
+  use super::{AssetLoader, AssetServer, Assets, Handle, LoadState, Script, Shader};
+  use crate::error::AssetError;
+  use crate::event::builtin::{AssetFailed, AssetModified};
+  use crate::event::Clock;
+  use crate::p1::P1;
+  use crate::rendering::Image;
+  use crate::testing::{temp_dir, update_until};
+
+  use std::fs;
+  use std::path::{Path, PathBuf};
+  use std::time::Duration;
+
+  fn write_png(path: PathBuf, width: u32, height: u32, data: &[u8]) {
+    let mut encoder = png::Encoder::new(fs::File::create(path).unwrap(), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+      .write_header()
+      .unwrap()
+      .write_image_data(data)
+      .unwrap();
+  }
+
+  fn state<T>(engine: &P1, handle: &Handle<T>) -> Option<LoadState> {
+    engine.resource::<AssetServer>().unwrap().load_state(handle)
+  }
+
+  #[test]
+  fn assets_load_in_the_background() {
+    let dir = temp_dir("assets");
+    write_png(dir.join("pixels.png"), 2, 1, &[255, 0, 0, 0, 255, 0]);
+    fs::write(dir.join("broken.png"), b"not a png").unwrap();
+    fs::write(dir.join("flat.wgsl"), "@fragment fn main() {}").unwrap();
+
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.insert_resource(AssetServer::new(&dir));
+
+    let (image, same, broken, shader) = {
+      let mut server = engine.resource_mut::<AssetServer>().unwrap();
+      assert!(matches!(
+        server.load::<Image>("sound.xyz"),
+        Err(AssetError::NoLoader(_))
+      ));
+      assert!(matches!(
+        server.load::<Shader>("pixels.png"),
+        Err(AssetError::WrongAssetType { .. })
+      ));
+      (
+        server.load::<Image>("pixels.png").unwrap(),
+        server.load::<Image>("pixels.png").unwrap(),
+        server.load::<Image>("broken.png").unwrap(),
+        server.load::<Shader>("flat.wgsl").unwrap(),
+      )
+    };
+    assert_eq!(image, same);
+
+    update_until(&mut engine, |engine| {
+      [image.id(), broken.id(), shader.id()]
+        .iter()
+        .all(|id| state(engine, &Handle::<()>::weak(*id)) != Some(LoadState::Loading))
+    });
+    assert_eq!(state(&engine, &broken), Some(LoadState::Failed));
+    assert_eq!(
+      engine.latest_event::<AssetFailed>().unwrap().id,
+      broken.id()
+    );
+    assert_eq!(
+      engine
+        .resource::<Assets<Image>>()
+        .unwrap()
+        .get(&image)
+        .unwrap()
+        .pixel(1, 0),
+      Some([0, 255, 0, 255])
+    );
+    assert_eq!(
+      engine
+        .resource::<Assets<Shader>>()
+        .unwrap()
+        .get(&shader)
+        .unwrap()
+        .source,
+      "@fragment fn main() {}"
+    );
+
+    // The asset goes away with its last strong handle
+    drop(image);
+    engine.update().unwrap();
+    assert!(engine.resource::<Assets<Image>>().unwrap().contains(&same));
+    let id = same.id();
+    drop(same);
+    engine.update().unwrap();
+    assert!(engine
+      .resource::<Assets<Image>>()
+      .unwrap()
+      .get(&Handle::weak(id))
+      .is_none());
+    assert_eq!(state(&engine, &Handle::<Image>::weak(id)), None);
+
+    let _ = fs::remove_dir_all(dir);
+  }
+
+  #[test]
+  fn modified_files_reload() {
+    let dir = temp_dir("hot-reload");
+    fs::write(dir.join("main.won"), "let a = 1;").unwrap();
+
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.insert_resource(AssetServer::new(&dir));
+    let script = engine
+      .resource_mut::<AssetServer>()
+      .unwrap()
+      .load::<Script>("main.won")
+      .unwrap();
+    update_until(&mut engine, |engine| {
+      state(engine, &script) == Some(LoadState::Loaded)
+    });
+    engine
+      .resource_mut::<AssetServer>()
+      .unwrap()
+      .watch_for_changes(Duration::ZERO);
+    let source = |engine: &P1| {
+      let scripts = engine.resource::<Assets<Script>>().unwrap();
+      scripts.get(&script).unwrap().source.clone()
+    };
+
+    // Lengths differ so the change shows even where modification times are coarse
+    fs::write(dir.join("main.won"), [0xff, 0xfe, 0xfd]).unwrap();
+    update_until(&mut engine, |engine| {
+      engine.latest_event::<AssetFailed>().is_some()
+    });
+    assert_eq!(source(&engine), "let a = 1;");
+    assert_eq!(state(&engine, &script), Some(LoadState::Loaded));
+
+    fs::write(dir.join("main.won"), "let a = 2;\nlet b = a;").unwrap();
+    update_until(&mut engine, |engine| {
+      engine.latest_event::<AssetModified>().is_some()
+    });
+    assert_eq!(
+      engine.latest_event::<AssetModified>().unwrap().id,
+      script.id()
+    );
+    assert_eq!(source(&engine), "let a = 2;\nlet b = a;");
+
+    // A loader for another type taking the extension over doesn't replace the script
+    engine
+      .resource_mut::<AssetServer>()
+      .unwrap()
+      .register_loader(TextLoader);
+    fs::write(dir.join("main.won"), "let a = 3;").unwrap();
+    update_until(&mut engine, |engine| {
+      engine
+        .latest_event::<AssetFailed>()
+        .is_some_and(|failed| failed.error.contains("does not load into"))
+    });
+    assert_eq!(source(&engine), "let a = 2;\nlet b = a;");
+    assert_eq!(state(&engine, &script), Some(LoadState::Loaded));
+
+    let _ = fs::remove_dir_all(dir);
+  }
+
+  struct TextLoader;
+
+  impl AssetLoader for TextLoader {
+    type Asset = String;
+
+    fn extensions(&self) -> &[&str] {
+      &["won"]
+    }
+    fn load(&self, bytes: &[u8], _path: &Path) -> Result<String, AssetError> {
+      Ok(String::from_utf8(bytes.to_vec())?)
+    }
+  }
+}
@@ -0,0 +1,371 @@
+use super::assets::next_id;
+use super::loader::{extension, ErasedAsset, ErasedLoader};
+use super::{
+  AssetLoader, Assets, AudioLoader, Handle, ImageLoader, SceneLoader, ScriptLoader, ShaderLoader,
+};
+use crate::ecs::ResourceManager;
+use crate::error::AssetError;
+use crate::event::builtin::{AssetFailed, AssetLoaded, AssetModified};
+use crate::scene::PrefabLoader;
+use crate::text::FontLoader;
+
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use parking_lot::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadState {
+  Loading,
+  Loaded,
+  Failed,
+}
+
+struct Finished {
+  id: u64,
+  path: PathBuf,
+  result: Result<ErasedAsset, AssetError>,
+}
+
+struct PathEntry {
+  id: u64,
+  asset_type: TypeId,
+  token: Weak<()>,
+  // Modification time and length of the file when it was last read, what hot reloading compares against
+  fingerprint: Option<(SystemTime, u64)>,
+}
+
+fn fingerprint(path: &Path) -> Option<(SystemTime, u64)> {
+  let metadata = fs::metadata(path).ok()?;
+  Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// Filled by AssetServer::update, the engine turns them into events
+#[derive(Default)]
+pub(crate) struct AssetChanges {
+  pub loaded: Vec<AssetLoaded>,
+  pub modified: Vec<AssetModified>,
+  pub failed: Vec<AssetFailed>,
+}
+
+// What the server needs to do with an asset type it only knows the TypeId of
+struct AssetType {
+  name: &'static str,
+  insert: fn(&ResourceManager, &Path, &PathEntry, ErasedAsset) -> Result<(), AssetError>,
+  collect_unused: fn(&ResourceManager) -> Vec<u64>,
+}
+
+impl AssetType {
+  fn of<T: Send + Sync + Any>() -> Self {
+    Self {
+      name: std::any::type_name::<T>(),
+      insert: |resources, path, entry, asset| {
+        let asset = *asset
+          .downcast::<T>()
+          .map_err(|_| AssetError::WrongAssetType {
+            path: path.to_owned(),
+            expected: std::any::type_name::<T>(),
+          })?;
+        if !resources.contains::<Assets<T>>() {
+          resources.insert(Assets::<T>::new());
+        }
+        resources.get_mut::<Assets<T>>().unwrap().insert_by_id(
+          entry.id,
+          entry.token.clone(),
+          asset,
+        );
+        Ok(())
+      },
+      collect_unused: |resources| {
+        resources
+          .get_mut::<Assets<T>>()
+          .map(|mut assets| assets.collect_unused())
+          .unwrap_or_default()
+      },
+    }
+  }
+}
+
+// Resource loading files relative to its root on background threads
+// Loaded assets land in their Assets<T> resource during P1::update
+pub struct AssetServer {
+  root: PathBuf,
+  loaders: HashMap<String, Arc<dyn ErasedLoader>>,
+  types: HashMap<TypeId, AssetType>,
+  paths: HashMap<PathBuf, PathEntry>,
+  states: HashMap<u64, LoadState>,
+  // Loaded assets being read again after their file changed
+  reloading: HashSet<u64>,
+  // How often files are checked for changes, None when hot reloading is off
+  watch_interval: Option<Duration>,
+  last_poll: Option<Instant>,
+  sender: Sender<Finished>,
+  // Receivers aren't Sync, resources have to be
+  receiver: Mutex<Receiver<Finished>>,
+}
+
+impl AssetServer {
+  pub fn new(root: impl Into<PathBuf>) -> Self {
+    let (sender, receiver) = mpsc::channel();
+    let mut server = Self {
+      root: root.into(),
+      loaders: HashMap::new(),
+      types: HashMap::new(),
+      paths: HashMap::new(),
+      states: HashMap::new(),
+      reloading: HashSet::new(),
+      watch_interval: None,
+      last_poll: None,
+      sender,
+      receiver: Mutex::new(receiver),
+    };
+    server.register_loader(ImageLoader);
+    server.register_loader(ShaderLoader);
+    server.register_loader(AudioLoader);
+    server.register_loader(SceneLoader);
+    server.register_loader(ScriptLoader);
+    server.register_loader(FontLoader);
+    server.register_loader(PrefabLoader);
+    server
+  }
+
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  // Polls the files of loaded assets and reloads the ones that changed
+  // Polling works everywhere, including containers and network mounts where file events don't
+  pub fn watch_for_changes(&mut self, interval: Duration) {
+    self.watch_interval = Some(interval);
+  }
+  pub fn stop_watching(&mut self) {
+    self.watch_interval = None;
+  }
+  pub fn is_watching(&self) -> bool {
+    self.watch_interval.is_some()
+  }
+
+  // Replaces the loaders previously registered for the same extensions
+  pub fn register_loader<L: AssetLoader>(&mut self, loader: L) {
+    self
+      .types
+      .entry(TypeId::of::<L::Asset>())
+      .or_insert_with(AssetType::of::<L::Asset>);
+
+    let extensions: Vec<_> = loader
+      .extensions()
+      .iter()
+      .map(|extension| extension.to_lowercase())
+      .collect();
+    let loader: Arc<dyn ErasedLoader> = Arc::new(loader);
+    for extension in extensions {
+      self.loaders.insert(extension, loader.clone());
+    }
+  }
+
+  // Loading a path that is already loaded or loading gives back the same asset
+  pub fn load<T: Send + Sync + Any>(
+    &mut self,
+    path: impl AsRef<Path>,
+  ) -> Result<Handle<T>, AssetError> {
+    let path = path.as_ref().to_owned();
+    let loader = self.loader_for::<T>(&path)?;
+
+    if let Some(handle) = self.get_handle::<T>(&path) {
+      return Ok(handle);
+    }
+
+    let (handle, token) = Handle::strong(next_id());
+    self.paths.insert(
+      path.clone(),
+      PathEntry {
+        id: handle.id(),
+        asset_type: TypeId::of::<T>(),
+        token,
+        fingerprint: None,
+      },
+    );
+    self.spawn_load(handle.id(), path, loader);
+    Ok(handle)
+  }
+
+  fn loader_for<T: Any>(&self, path: &Path) -> Result<Arc<dyn ErasedLoader>, AssetError> {
+    let extension = extension(path).unwrap_or_default();
+    let loader = self
+      .loaders
+      .get(&extension)
+      .ok_or(AssetError::NoLoader(extension))?;
+    if loader.asset_type() != TypeId::of::<T>() {
+      return Err(AssetError::WrongAssetType {
+        path: path.to_owned(),
+        expected: std::any::type_name::<T>(),
+      });
+    }
+    Ok(loader.clone())
+  }
+
+  fn spawn_load(&mut self, id: u64, path: PathBuf, loader: Arc<dyn ErasedLoader>) {
+    let full_path = self.root.join(&path);
+    // Taken before reading so a write during the load is picked up by the next poll
+    if let Some(entry) = self.paths.get_mut(&path) {
+      entry.fingerprint = fingerprint(&full_path);
+    }
+    if self.states.get(&id) == Some(&LoadState::Loaded) {
+      self.reloading.insert(id);
+    } else {
+      self.states.insert(id, LoadState::Loading);
+    }
+
+    let sender = self.sender.clone();
+    thread::spawn(move || {
+      let result = fs::read(&full_path)
+        .map_err(AssetError::from)
+        .and_then(|bytes| loader.load(&bytes, &path));
+      // The server is gone if this fails, nobody is waiting for the asset anymore
+      let _ = sender.send(Finished { id, path, result });
+    });
+  }
+
+  pub fn get_handle<T: Any>(&self, path: impl AsRef<Path>) -> Option<Handle<T>> {
+    let entry = self.paths.get(path.as_ref())?;
+    if entry.asset_type != TypeId::of::<T>() {
+      return None;
+    }
+    entry
+      .token
+      .upgrade()
+      .map(|token| Handle::from_token(entry.id, token))
+  }
+
+  pub fn load_state<T>(&self, handle: &Handle<T>) -> Option<LoadState> {
+    self.states.get(&handle.id()).copied()
+  }
+
+  pub fn path<T>(&self, handle: &Handle<T>) -> Option<&Path> {
+    self
+      .paths
+      .iter()
+      .find(|(_, entry)| entry.id == handle.id())
+      .map(|(path, _)| path.as_path())
+  }
+
+  fn poll_changes(&mut self, changes: &mut AssetChanges) {
+    let Some(interval) = self.watch_interval else {
+      return;
+    };
+    let now = Instant::now();
+    if self
+      .last_poll
+      .is_some_and(|last_poll| now.duration_since(last_poll) < interval)
+    {
+      return;
+    }
+    self.last_poll = Some(now);
+
+    // Failed assets are retried as well, fixing the file is how they get loaded
+    let changed: Vec<_> = self
+      .paths
+      .iter()
+      .filter(|(_, entry)| {
+        entry.token.strong_count() > 0
+          && !self.reloading.contains(&entry.id)
+          && self.states.get(&entry.id) != Some(&LoadState::Loading)
+      })
+      .filter(|(path, entry)| fingerprint(&self.root.join(path)) != entry.fingerprint)
+      .map(|(path, entry)| (path.clone(), entry.id, entry.asset_type))
+      .collect();
+
+    for (path, id, asset_type) in changed {
+      let extension = extension(&path).unwrap_or_default();
+      let Some(loader) = self.loaders.get(&extension).cloned() else {
+        continue;
+      };
+      // The extension was taken over by a loader for another type since the asset was loaded
+      // Reported once, the file is picked up again when it changes next
+      if loader.asset_type() != asset_type {
+        let error = AssetError::WrongAssetType {
+          path: path.clone(),
+          expected: self.types[&asset_type].name,
+        };
+        self.paths.get_mut(&path).unwrap().fingerprint = fingerprint(&self.root.join(&path));
+        changes.failed.push(AssetFailed {
+          id,
+          path,
+          error: error.to_string(),
+        });
+        continue;
+      }
+      self.spawn_load(id, path, loader);
+    }
+  }
+
+  // Moves finished loads into their Assets<T> and drops the assets nothing points to anymore
+  pub(crate) fn update(&mut self, resources: &ResourceManager) -> AssetChanges {
+    let mut changes = AssetChanges::default();
+    self.poll_changes(&mut changes);
+
+    let finished: Vec<_> = self.receiver.lock().try_iter().collect();
+
+    for Finished { id, path, result } in finished {
+      // Every handle was dropped while it was loading
+      let Some(entry) = self
+        .paths
+        .get(&path)
+        .filter(|entry| entry.id == id && entry.token.strong_count() > 0)
+      else {
+        self.states.remove(&id);
+        self.reloading.remove(&id);
+        continue;
+      };
+
+      let reloaded = self.reloading.remove(&id);
+      let asset_type = &self.types[&entry.asset_type];
+      let result = result.and_then(|asset| (asset_type.insert)(resources, &path, entry, asset));
+      match result {
+        Ok(()) => {
+          self.states.insert(id, LoadState::Loaded);
+          if reloaded {
+            changes.modified.push(AssetModified { id, path });
+          } else {
+            changes.loaded.push(AssetLoaded { id, path });
+          }
+        }
+        Err(error) => {
+          if !reloaded {
+            self.states.insert(id, LoadState::Failed);
+          }
+          changes.failed.push(AssetFailed {
+            id,
+            path,
+            error: error.to_string(),
+          });
+        }
+      }
+    }
+
+    for asset_type in self.types.values() {
+      for id in (asset_type.collect_unused)(resources) {
+        self.states.remove(&id);
+        self.reloading.remove(&id);
+      }
+    }
+    // Paths of assets that are still loading stay until the load comes back
+    let states = &mut self.states;
+    self.paths.retain(|_, entry| {
+      let alive = entry.token.strong_count() > 0;
+      if !alive && states.get(&entry.id) != Some(&LoadState::Loading) {
+        states.remove(&entry.id);
+        return false;
+      }
+      true
+    });
+
+    changes
+  }
+}
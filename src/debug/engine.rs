@@ -0,0 +1,157 @@
+use super::{DebugOverlay, InspectedComponent};
+use crate::asset::Assets;
+use crate::error::DataError;
+use crate::event::{InputState, Time};
+use crate::p1::P1;
+use crate::rendering::Image;
+use crate::text::{Font, GlyphAtlas, Text, TextLayout};
+use crate::ui::type_text;
+
+use winit::keyboard::KeyCode;
+
+// The overlay and the inspection of components it's built on
+impl P1 {
+  // Does nothing without a DebugOverlay resource
+  pub(crate) fn update_debug_overlay(&mut self, time: &Time) -> Result<(), DataError> {
+    let Some(mut overlay) = self.resource_manager.get_mut::<DebugOverlay>() else {
+      return Ok(());
+    };
+    overlay.record_frame(time.delta_secs());
+    let (toggled, text, tab, escape, enter) = {
+      let input = self.resource_manager.get::<InputState>().unwrap();
+      (
+        input.just_pressed(overlay.toggle_key),
+        input.text().to_owned(),
+        input.just_pressed(KeyCode::Tab),
+        input.just_pressed(KeyCode::Escape),
+        input.just_pressed(KeyCode::Enter) || input.just_pressed(KeyCode::NumpadEnter),
+      )
+    };
+    if toggled {
+      overlay.enabled = !overlay.enabled;
+    }
+    if !overlay.enabled {
+      return Ok(());
+    }
+
+    if overlay
+      .selected()
+      .is_some_and(|entity| !self.contains_entity(entity))
+    {
+      overlay.select(None);
+    }
+    let mut inspected = match overlay.selected() {
+      Some(entity) => Some(self.inspect(entity)?),
+      None => None,
+    };
+
+    if let (Some(entity), Some(components)) = (overlay.selected(), inspected.as_ref()) {
+      let serializable: Vec<_> = components
+        .iter()
+        .filter_map(|component| {
+          let value = component.value.as_ref()?;
+          Some((component.name.clone(), value.to_string()))
+        })
+        .collect();
+      if tab && !serializable.is_empty() {
+        let next = overlay
+          .editing
+          .as_ref()
+          .map_or(0, |(index, _)| (index + 1) % serializable.len());
+        overlay.editing = Some((next, serializable[next].1.clone()));
+        overlay.error = None;
+      } else if escape {
+        overlay.editing = None;
+        overlay.error = None;
+      } else if let Some((index, edited)) = overlay.editing.as_mut() {
+        match serializable.get(*index) {
+          // The component was removed while it was being edited
+          None => overlay.editing = None,
+          Some((name, _)) => {
+            type_text(edited, &text, None);
+            if enter {
+              match self.edit_component(entity, name, edited) {
+                Ok(()) => {
+                  overlay.editing = None;
+                  overlay.error = None;
+                  inspected = Some(self.inspect(entity)?);
+                }
+                Err(error) => overlay.error = Some(error.to_string()),
+              }
+            }
+          }
+        }
+      }
+    }
+
+    let systems = self.system_timings();
+    overlay.compose(
+      time.delta_secs(),
+      self.entity_manager.len(),
+      self.archetype_count(),
+      &systems,
+      inspected.as_deref(),
+    );
+
+    {
+      let fonts = self.resource_manager.get::<Assets<Font>>().unwrap();
+      let mut images = self.resource_manager.get_mut::<Assets<Image>>().unwrap();
+      let mut atlas = self.resource_manager.get_mut::<GlyphAtlas>().unwrap();
+      let text = Text::new(overlay.text(), overlay.font.clone()).with_size(overlay.font_size);
+      overlay.layout = match fonts.get(&overlay.font) {
+        Some(font) => TextLayout::new(&text, font, None, &mut atlas, &mut images),
+        None => TextLayout::default(),
+      };
+    }
+    drop(overlay);
+    self.sync_atlas();
+    Ok(())
+  }
+
+  // Components of the entity in the order they were added, with the values of the serializable ones
+  pub fn inspect(&self, entity: u32) -> Result<Vec<InspectedComponent>, DataError> {
+    let components = self.component_manager.read();
+    Ok(
+      self
+        .entity_manager
+        .components(entity)?
+        .into_iter()
+        .map(|type_id| {
+          let info = self.registry.get(type_id);
+          InspectedComponent {
+            name: self.registry.name(type_id),
+            type_id,
+            value: info
+              .and_then(|info| info.read_json(&components, entity))
+              .and_then(Result::ok),
+            debug: info.and_then(|info| info.read_debug(&components, entity)),
+          }
+        })
+        .collect(),
+    )
+  }
+
+  // Replaces a component of the entity with one read from JSON
+  // The component is found by the name the registry has for it
+  pub fn edit_component(&self, entity: u32, name: &str, json: &str) -> Result<(), DataError> {
+    let info = self
+      .registry
+      .by_name(name)?
+      .ok_or_else(|| DataError::UnknownComponent(name.to_owned()))?;
+    if !info.is_serializable() {
+      return Err(DataError::NotSerializable(name.to_owned()));
+    }
+    if !self
+      .entity_manager
+      .components(entity)?
+      .contains(&info.type_id())
+    {
+      return Err(DataError::MissingComponent(name.to_owned()));
+    }
+    let value = serde_json::from_str(json)?;
+    info
+      .write_json(&self.component_manager.read(), entity, value)
+      .ok_or_else(|| DataError::MissingComponent(name.to_owned()))??;
+    Ok(())
+  }
+}
@@ -0,0 +1,193 @@
+mod engine;
+mod overlay;
+
+pub use overlay::{DebugOverlay, InspectedComponent, SystemTiming};
+
+#[cfg(test)]
+mod tests {
+  use super::DebugOverlay;
+  use crate::ecs::Query;
+  use crate::error::DataError;
+  use crate::event::builtin::Update;
+  use crate::event::{Clock, Event, InputEvent, BACKSPACE};
+  use crate::p1::P1;
+  use crate::rendering::Image;
+  use crate::spatial::Transform;
+  use crate::testing::{add_font, gpu_available, update_until};
+  use crate::ui::Button;
+
+  use glam::Vec3;
+  use winit::keyboard::KeyCode;
+
+  fn overlay(engine: &P1) -> String {
+    engine.resource::<DebugOverlay>().unwrap().text().to_owned()
+  }
+
+  #[test]
+  fn inspector_reads_and_edits_components() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let entity = engine.create_entity();
+    engine
+      .add_component(entity, Transform::from_xyz(1.0, 2.0, 3.0))
+      .unwrap();
+    engine.add_component(entity, Button).unwrap();
+
+    let components = engine.inspect(entity).unwrap();
+    assert_eq!(components.len(), 2);
+    assert_eq!(components[0].name, "Transform");
+    assert_eq!(
+      components[0].value.as_ref().unwrap()["translation"],
+      serde_json::json!([1.0, 2.0, 3.0])
+    );
+    // Named without being registered, but there's no telling how to serialize it
+    assert_eq!(components[1].name, "Button");
+    assert!(components[1].value.is_none());
+
+    engine
+      .edit_component(
+        entity,
+        "Transform",
+        r#"{"translation":[4.0,5.0,6.0],"rotation":[0.0,0.0,0.0,1.0],"scale":[1.0,1.0,1.0]}"#,
+      )
+      .unwrap();
+    assert_eq!(
+      engine.with_component::<Transform, _>(entity, |transform| transform.translation),
+      Some(Vec3::new(4.0, 5.0, 6.0))
+    );
+
+    assert!(matches!(
+      engine.edit_component(entity, "Button", "null"),
+      Err(DataError::NotSerializable(name)) if name == "Button"
+    ));
+    assert!(matches!(
+      engine.edit_component(entity, "Camera", "{}"),
+      Err(DataError::UnknownComponent(_))
+    ));
+    assert!(matches!(
+      engine.edit_component(entity, "Transform", "{"),
+      Err(DataError::Json(_))
+    ));
+    let other = engine.create_entity();
+    assert!(matches!(
+      engine.edit_component(other, "Transform", "{}"),
+      Err(DataError::MissingComponent(_))
+    ));
+  }
+
+  #[test]
+  fn overlay_shows_stats_and_edits_live() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let font = add_font(&mut engine);
+    engine.insert_resource(DebugOverlay::new(font));
+    let system = |_: Query<&Transform>, _: Event<Update>| {};
+    engine.register_system(system).unwrap();
+
+    let entity = engine.create_entity();
+    engine.add_component(entity, Transform::IDENTITY).unwrap();
+    let other = engine.create_entity();
+    engine.add_component(other, Button).unwrap();
+
+    // Hidden until toggled
+    engine.update().unwrap();
+    assert!(overlay(&engine).is_empty());
+    engine.send_input(InputEvent::KeyPressed(KeyCode::F3));
+    engine.update().unwrap();
+    let text = overlay(&engine);
+    assert!(text.contains("2 entities, 2 archetypes"), "{text}");
+    assert!(text.contains("on Update"));
+    assert!(!engine
+      .resource::<DebugOverlay>()
+      .unwrap()
+      .layout
+      .glyphs()
+      .is_empty());
+    update_until(&mut engine, |engine| engine.system_timings()[0].runs() > 0);
+
+    engine
+      .resource_mut::<DebugOverlay>()
+      .unwrap()
+      .select(Some(entity));
+    engine.send_input(InputEvent::KeyReleased(KeyCode::F3));
+    engine.send_input(InputEvent::KeyPressed(KeyCode::Tab));
+    engine.update().unwrap();
+    let text = overlay(&engine);
+    assert!(text.contains(&format!("entity {entity}")));
+    assert!(text.contains("Transform > {"));
+
+    // Broken JSON is kept for fixing, with the reason it wasn't applied
+    for character in ['x', '\r'] {
+      engine.send_input(InputEvent::Character(character));
+    }
+    engine.send_input(InputEvent::KeyPressed(KeyCode::Enter));
+    engine.update().unwrap();
+    assert!(engine.resource::<DebugOverlay>().unwrap().error().is_some());
+    assert!(engine
+      .resource::<DebugOverlay>()
+      .unwrap()
+      .editing()
+      .unwrap()
+      .1
+      .ends_with("}x"));
+
+    engine.send_input(InputEvent::KeyReleased(KeyCode::Enter));
+    engine.send_input(InputEvent::Character(BACKSPACE));
+    engine.send_input(InputEvent::KeyPressed(KeyCode::Enter));
+    engine.update().unwrap();
+    {
+      let overlay = engine.resource::<DebugOverlay>().unwrap();
+      assert!(overlay.error().is_none());
+      assert!(overlay.editing().is_none());
+    }
+
+    engine.resource_mut::<DebugOverlay>().unwrap().editing = Some((
+      0,
+      r#"{"translation":[7.0,0.0,0.0],"rotation":[0.0,0.0,0.0,1.0],"scale":[1.0,1.0,1.0]}"#
+        .to_owned(),
+    ));
+    engine.send_input(InputEvent::KeyReleased(KeyCode::Enter));
+    engine.update().unwrap();
+    engine.send_input(InputEvent::KeyPressed(KeyCode::Enter));
+    engine.update().unwrap();
+    assert_eq!(
+      engine.with_component::<Transform, _>(entity, |transform| transform.translation),
+      Some(Vec3::new(7.0, 0.0, 0.0))
+    );
+    assert!(overlay(&engine).contains("[7.0,0.0,0.0]"));
+
+    // An edit of a component the entity no longer has is dropped
+    engine.resource_mut::<DebugOverlay>().unwrap().editing = Some((5, "{}".to_owned()));
+    engine.send_input(InputEvent::KeyReleased(KeyCode::Enter));
+    engine.update().unwrap();
+    engine.send_input(InputEvent::KeyPressed(KeyCode::Enter));
+    engine.update().unwrap();
+    assert!(engine
+      .resource::<DebugOverlay>()
+      .unwrap()
+      .editing()
+      .is_none());
+  }
+
+  #[test]
+  fn overlay_draws_over_the_frame() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(256, 128).unwrap();
+    if !gpu_available(&engine, "overlay_draws_over_the_frame") {
+      return;
+    }
+    let font = add_font(&mut engine);
+    let mut overlay = DebugOverlay::new(font);
+    overlay.enabled = true;
+    engine.insert_resource(overlay);
+    engine.update().unwrap();
+
+    let image = engine.render_to_image().unwrap();
+    let lit =
+      |image: &Image| (0..40).any(|y| (0..200).any(|x| image.pixel(x, y).unwrap()[0] > 128));
+    assert!(lit(&image));
+    assert_eq!(image.pixel(255, 127).unwrap()[..3], [0, 0, 0]);
+
+    engine.send_input(InputEvent::KeyPressed(KeyCode::F3));
+    engine.update().unwrap();
+    assert!(!lit(&engine.render_to_image().unwrap()));
+  }
+}
@@ -0,0 +1,194 @@
+use crate::asset::Handle;
+use crate::text::{Font, TextLayout};
+
+use std::any::TypeId;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::Duration;
+
+use serde_json::Value;
+use winit::keyboard::KeyCode;
+
+// Frames averaged for the frame time
+const FRAME_HISTORY: usize = 60;
+
+// How long the last run of a registered system took
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemTiming {
+  pub(crate) name: String,
+  pub(crate) last: Duration,
+  pub(crate) runs: u64,
+}
+
+impl SystemTiming {
+  pub(crate) fn new(name: String) -> Self {
+    Self {
+      name,
+      last: Duration::ZERO,
+      runs: 0,
+    }
+  }
+
+  pub(crate) fn record(&mut self, duration: Duration) {
+    self.last = duration;
+    self.runs += 1;
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+  pub fn last(&self) -> Duration {
+    self.last
+  }
+  pub fn runs(&self) -> u64 {
+    self.runs
+  }
+}
+
+// One component of an inspected entity, value is None for components that aren't serializable
+// and debug for the ones that don't implement Debug
+#[derive(Clone, Debug, PartialEq)]
+pub struct InspectedComponent {
+  pub name: String,
+  pub type_id: TypeId,
+  pub value: Option<Value>,
+  pub debug: Option<String>,
+}
+
+// Shown over every render target while enabled, toggled with toggle_key
+// With an entity selected, Tab goes through its serializable components and puts the JSON of one in an editable line
+// Enter writes it back to the component, Escape leaves it as it was
+pub struct DebugOverlay {
+  pub enabled: bool,
+  pub toggle_key: KeyCode,
+  pub font: Handle<Font>,
+  pub font_size: f32,
+  selected: Option<u32>,
+  frame_times: VecDeque<f64>,
+  // Index among the serializable components of the selected entity, with what was typed so far
+  pub(crate) editing: Option<(usize, String)>,
+  pub(crate) error: Option<String>,
+  pub(crate) text: String,
+  pub(crate) layout: TextLayout,
+}
+
+impl DebugOverlay {
+  pub fn new(font: Handle<Font>) -> Self {
+    Self {
+      enabled: false,
+      toggle_key: KeyCode::F3,
+      font,
+      font_size: 12.0,
+      selected: None,
+      frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+      editing: None,
+      error: None,
+      text: String::new(),
+      layout: TextLayout::default(),
+    }
+  }
+
+  pub fn select(&mut self, entity: Option<u32>) {
+    if self.selected != entity {
+      self.editing = None;
+      self.error = None;
+    }
+    self.selected = entity;
+  }
+
+  pub fn selected(&self) -> Option<u32> {
+    self.selected
+  }
+
+  // What is drawn, as it was on the last update
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  // Component being edited and the JSON typed so far
+  pub fn editing(&self) -> Option<(usize, &str)> {
+    self
+      .editing
+      .as_ref()
+      .map(|(index, value)| (*index, value.as_str()))
+  }
+
+  // Why the last edit wasn't applied
+  pub fn error(&self) -> Option<&str> {
+    self.error.as_deref()
+  }
+
+  pub(crate) fn record_frame(&mut self, delta: f64) {
+    if self.frame_times.len() == FRAME_HISTORY {
+      self.frame_times.pop_front();
+    }
+    self.frame_times.push_back(delta);
+  }
+
+  pub fn average_frame_time(&self) -> f64 {
+    if self.frame_times.is_empty() {
+      return 0.0;
+    }
+    self.frame_times.iter().sum::<f64>() / self.frame_times.len() as f64
+  }
+
+  pub(crate) fn compose(
+    &mut self,
+    frame_time: f64,
+    entities: usize,
+    archetypes: usize,
+    systems: &[SystemTiming],
+    inspected: Option<&[InspectedComponent]>,
+  ) {
+    let average = self.average_frame_time();
+    let mut text = String::new();
+    let _ = writeln!(
+      text,
+      "frame {:.2} ms, average {:.2} ms ({:.0} fps)",
+      frame_time * 1000.0,
+      average * 1000.0,
+      if average > 0.0 { 1.0 / average } else { 0.0 }
+    );
+    let _ = writeln!(text, "{entities} entities, {archetypes} archetypes");
+    for system in systems {
+      let _ = writeln!(
+        text,
+        "{} {:.3} ms",
+        system.name,
+        system.last.as_secs_f64() * 1000.0
+      );
+    }
+
+    if let (Some(entity), Some(components)) = (self.selected, inspected) {
+      let _ = writeln!(text, "entity {entity}");
+      let mut serializable = 0;
+      for component in components {
+        match &component.value {
+          Some(value) => {
+            let value = match &self.editing {
+              Some((index, edited)) if *index == serializable => format!("> {edited}_"),
+              _ => value.to_string(),
+            };
+            let _ = writeln!(text, "  {} {value}", component.name);
+            serializable += 1;
+          }
+          // Read only
+          None => {
+            let _ = writeln!(
+              text,
+              "  {} {}",
+              component.name,
+              component.debug.as_deref().unwrap_or_default()
+            );
+          }
+        }
+      }
+      if let Some(error) = &self.error {
+        let _ = writeln!(text, "  {error}");
+      }
+    }
+
+    text.pop();
+    self.text = text;
+  }
+}
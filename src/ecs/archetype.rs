@@ -23,6 +23,10 @@ impl Archetype {
     &mut self.entities
   }
 
+  pub fn matches(&self, c_ids: &[TypeId]) -> bool {
+    self.c_ids.iter().all(|c_id| c_ids.contains(c_id))
+  }
+
   pub fn id_from_c_ids(c_ids: &[TypeId]) -> u128 {
     c_ids
       .iter()
@@ -47,6 +51,25 @@ impl ArchetypeManager {
     self.0.get_mut(&archetype)
   }
 
+  // Keeps every archetype in sync with the components the entity now has
+  pub fn update_entity(&mut self, entity: u32, c_ids: &[TypeId]) {
+    for mut archetype in self.0.iter_mut() {
+      let matches = archetype.matches(c_ids);
+      let entities = archetype.entities_mut();
+      match (matches, entities.contains(&entity)) {
+        (true, false) => entities.push(entity),
+        (false, true) => entities.retain(|e| *e != entity),
+        _ => {}
+      }
+    }
+  }
+
+  pub fn remove_entity(&mut self, entity: u32) {
+    for mut archetype in self.0.iter_mut() {
+      archetype.entities_mut().retain(|e| *e != entity);
+    }
+  }
+
   pub fn insert(&mut self, c_ids: Vec<TypeId>, entities: Vec<u32>) -> RefMut<'_, u128, Archetype> {
     match self.0.entry(Archetype::id_from_c_ids(&c_ids)) {
       Entry::Vacant(entry) => entry.insert(Archetype::new(c_ids, entities)),
@@ -8,10 +8,32 @@ use dashmap::{
 };
 use rustc_hash::FxHasher;
 
+use super::registry::interned_short_name;
+use super::ComponentInfo;
 use crate::utility::ErasedMapContainer;
 
 pub trait Component: Send + Sync + Any {
-  // Need some input-controlled human-readable type names for component serialization
+  // Name of the type without its module paths, generics included so Handle<Mesh> and Handle<Texture> differ
+  fn component_name() -> &'static str
+  where
+    Self: Sized,
+  {
+    interned_short_name::<Self>()
+  }
+
+  // What the registry keeps about the type, the derive fills in the vtables the type supports
+  fn component_info() -> ComponentInfo
+  where
+    Self: Sized,
+  {
+    ComponentInfo::of::<Self>()
+  }
+}
+
+// For components holding entity ids, so they keep pointing at the right entities when spawned from a scene or prefab
+// #[derive(Component)] picks the implementation up on its own
+pub trait MapEntities {
+  fn map_entities(&mut self, map: &mut dyn FnMut(u32) -> u32);
 }
 
 pub(crate) struct ComponentManager(
@@ -50,4 +72,14 @@ impl ComponentManager {
       Entry::Occupied(entry) => entry.into_ref(),
     }
   }
+
+  pub fn create_container_from_info(
+    &mut self,
+    info: &ComponentInfo,
+  ) -> RefMut<'_, TypeId, ErasedMapContainer<u32>> {
+    match self.0.entry(info.type_id()) {
+      Entry::Vacant(entry) => entry.insert(info.new_container()),
+      Entry::Occupied(entry) => entry.into_ref(),
+    }
+  }
 }
@@ -1,10 +1,15 @@
 use dashmap::DashMap;
 use std::hash::BuildHasherDefault;
-use std::{any::TypeId, collections::HashSet};
+use std::sync::Arc;
+use std::{
+  any::TypeId,
+  collections::{HashMap, HashSet},
+};
 
+use parking_lot::RwLock;
 use rustc_hash::FxHasher;
 
-use super::Component;
+use super::{Component, Relation, Relations};
 use crate::error::DataError;
 
 pub(crate) struct EntityManager {
@@ -16,6 +21,37 @@ pub(crate) struct EntityManager {
   // Furthermore, this is not meant to be in a multithreaded context
   // This is because entity creation needs to happen in the main thread in order to update all archetypes properly from there
   next_entity_id: u32,
+  // Shared with the system threads so queries can filter on them
+  relations: Arc<RwLock<Relations>>,
+  // Distinct sets of components among the entities, whether or not a query ever asked for them
+  component_sets: ComponentSets,
+}
+
+// How many entities have each set of components, kept sorted
+// Archetype ids saturate with a few components, so the sets themselves are the keys
+#[derive(Default)]
+struct ComponentSets(HashMap<Vec<TypeId>, usize>);
+
+impl ComponentSets {
+  fn add(&mut self, components: &[TypeId]) {
+    *self.0.entry(Self::sorted(components)).or_default() += 1;
+  }
+
+  fn remove(&mut self, components: &[TypeId]) {
+    let set = Self::sorted(components);
+    if let Some(count) = self.0.get_mut(&set) {
+      *count -= 1;
+      if *count == 0 {
+        self.0.remove(&set);
+      }
+    }
+  }
+
+  fn sorted(components: &[TypeId]) -> Vec<TypeId> {
+    let mut components = components.to_vec();
+    components.sort();
+    components
+  }
 }
 
 impl EntityManager {
@@ -23,6 +59,8 @@ impl EntityManager {
     EntityManager {
       entities: DashMap::with_hasher(BuildHasherDefault::default()),
       next_entity_id: 0u32,
+      relations: Arc::new(RwLock::new(Relations::new())),
+      component_sets: ComponentSets::default(),
     }
   }
 
@@ -30,6 +68,7 @@ impl EntityManager {
     let id = self.next_entity_id;
     self.next_entity_id += 1;
     self.entities.insert(id, Vec::new());
+    self.component_sets.add(&[]);
     id
   }
 
@@ -44,20 +83,124 @@ impl EntityManager {
   }
 
   pub fn add_component<C: Component>(&mut self, entity: u32) -> Result<(), DataError> {
+    self.add_component_id(entity, TypeId::of::<C>(), C::component_name())
+  }
+
+  // For components only known through the registry
+  pub fn add_component_id(
+    &mut self,
+    entity: u32,
+    c_id: TypeId,
+    name: &'static str,
+  ) -> Result<(), DataError> {
     let mut components = self
       .entities
       .get_mut(&entity)
       .ok_or(DataError::EntityNotFound)?;
-    let c_id = TypeId::of::<C>();
     if components.contains(&c_id) {
-      return Err(DataError::ComponentExistsForEntity);
+      return Err(DataError::ComponentExistsForEntity(name));
     } else {
+      self.component_sets.remove(&components);
       components.push(c_id);
+      self.component_sets.add(&components);
     }
 
     Ok(())
   }
 
+  pub fn remove_component<C: Component>(&mut self, entity: u32) -> Result<bool, DataError> {
+    self.remove_component_id(entity, TypeId::of::<C>())
+  }
+
+  pub fn remove_component_id(&mut self, entity: u32, c_id: TypeId) -> Result<bool, DataError> {
+    let mut components = self
+      .entities
+      .get_mut(&entity)
+      .ok_or(DataError::EntityNotFound)?;
+    let had_component = components.contains(&c_id);
+    if had_component {
+      self.component_sets.remove(&components);
+      components.retain(|id| *id != c_id);
+      self.component_sets.add(&components);
+    }
+
+    Ok(had_component)
+  }
+
+  pub fn components(&self, entity: u32) -> Result<Vec<TypeId>, DataError> {
+    Ok(
+      self
+        .entities
+        .get(&entity)
+        .ok_or(DataError::EntityNotFound)?
+        .clone(),
+    )
+  }
+
+  // Only meant for restoring snapshots, where ids have to come back as they were
+  pub fn insert_entity(&mut self, entity: u32) {
+    if !self.entities.contains_key(&entity) {
+      self.entities.insert(entity, Vec::new());
+      self.component_sets.add(&[]);
+    }
+  }
+
+  pub fn next_id(&self) -> u32 {
+    self.next_entity_id
+  }
+  pub fn set_next_id(&mut self, id: u32) {
+    self.next_entity_id = id;
+  }
+
+  pub fn contains(&self, entity: u32) -> bool {
+    self.entities.contains_key(&entity)
+  }
+
+  pub fn entities(&self) -> Vec<u32> {
+    let mut entities: Vec<u32> = self.entities.iter().map(|entity| *entity.key()).collect();
+    entities.sort();
+    entities
+  }
+
+  pub fn len(&self) -> usize {
+    self.entities.len()
+  }
+
+  pub fn archetype_count(&self) -> usize {
+    self.component_sets.0.len()
+  }
+
+  // Returns the components the entity had so they can be dropped
+  // Its relations go with it, from both ends
+  pub fn remove_entity(&mut self, entity: u32) -> Result<Vec<TypeId>, DataError> {
+    let (_, components) = self
+      .entities
+      .remove(&entity)
+      .ok_or(DataError::EntityNotFound)?;
+    self.component_sets.remove(&components);
+    self.relations.write().remove_entity(entity);
+    Ok(components)
+  }
+
+  pub fn relations(&self) -> &Arc<RwLock<Relations>> {
+    &self.relations
+  }
+
+  pub fn relate<R: Relation>(
+    &mut self,
+    source: u32,
+    target: u32,
+  ) -> Result<Option<u32>, DataError> {
+    if !self.contains(source) || !self.contains(target) {
+      return Err(DataError::EntityNotFound);
+    }
+    Ok(self.relations.write().relate::<R>(source, target))
+  }
+
+  pub fn unrelate<R: Relation>(&mut self, source: u32, target: u32) -> bool {
+    self.relations.write().unrelate::<R>(source, target)
+  }
+
   pub fn get_archetype(&self, c_ids: &[TypeId]) -> Vec<u32> {
     let mut result = Vec::new();
 
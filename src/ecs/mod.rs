@@ -2,11 +2,24 @@ mod archetype;
 mod component;
 mod entity;
 mod query;
+mod registry;
+mod relation;
+mod resource;
 
-pub use component::Component;
+pub use component::{Component, MapEntities};
 pub use query::Query;
+pub use registry::{
+  ComponentInfo, ComponentRegistry, DebugFallback, DebugFn, DebugProbe, DefaultFallback, DefaultFn,
+  DefaultProbe, MapEntitiesFallback, MapEntitiesFn, MapEntitiesProbe, Probe, SerdeFallback,
+  SerdeProbe, SerdeVtable,
+};
+pub use relation::{ChildOf, OwnedBy, Relation, Targets};
+pub use resource::{Res, ResMut};
 
 pub(crate) use archetype::{Archetype, ArchetypeManager};
 pub(crate) use component::ComponentManager;
 pub(crate) use entity::EntityManager;
 pub(crate) use query::QueryData;
+pub(crate) use registry::short_name;
+pub(crate) use relation::Relations;
+pub(crate) use resource::ResourceManager;
@@ -1,28 +1,103 @@
-use std::any::TypeId;
+use std::any::{Any, TypeId};
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::marker::Sync;
 use std::ops::{Deref, DerefMut};
 use std::slice::{Iter, IterMut};
 
 use dashmap::mapref::one::{MappedRef, MappedRefMut};
-use parking_lot::RwLockReadGuard;
+use parking_lot::{RwLock, RwLockReadGuard};
 
-use super::{Component, ComponentManager};
+use super::{Component, ComponentManager, Relation, Relations, Res, ResMut, ResourceManager};
 use crate::error::{DataError, InternalDataError};
 use crate::utility::ErasedMapContainer;
 
-pub struct Query<'iterable, 'item: 'iterable, D: QueryData>(&'iterable mut Vec<D::Item<'item>>);
+pub struct Query<'iterable, 'item: 'iterable, D: QueryData> {
+  data: &'iterable mut Vec<D::Item<'item>>,
+  // Entity of every item, in the same order
+  entities: &'iterable [u32],
+  resources: &'iterable ResourceManager,
+  relations: &'iterable RwLock<Relations>,
+}
 
 impl<'iterable, 'item: 'iterable, D: QueryData> Query<'iterable, 'item, D> {
-  pub fn new(data: &'iterable mut Vec<D::Item<'item>>) -> Self {
-    Self(data)
+  pub(crate) fn new(
+    data: &'iterable mut Vec<D::Item<'item>>,
+    entities: &'iterable [u32],
+    resources: &'iterable ResourceManager,
+    relations: &'iterable RwLock<Relations>,
+  ) -> Self {
+    Self {
+      data,
+      entities,
+      resources,
+      relations,
+    }
   }
 
   pub fn iter(&self) -> Iter<'_, D::Item<'item>> {
-    self.0.iter()
+    self.data.iter()
   }
   pub fn iter_mut(&mut self) -> IterMut<'_, D::Item<'item>> {
-    self.0.iter_mut()
+    self.data.iter_mut()
+  }
+
+  pub fn entities(&self) -> &[u32] {
+    self.entities
+  }
+
+  // Items of the entities that have the relation to the target
+  pub fn related_to<R: Relation>(&self, target: u32) -> impl Iterator<Item = &D::Item<'item>> {
+    let sources = self.relations.read().sources::<R>(target).to_vec();
+    self.filter(sources)
+  }
+  pub fn related_to_mut<R: Relation>(
+    &mut self,
+    target: u32,
+  ) -> impl Iterator<Item = &mut D::Item<'item>> {
+    let sources = self.relations.read().sources::<R>(target).to_vec();
+    self.filter_mut(sources)
+  }
+
+  // Items of the entities the source has the relation to
+  pub fn related_from<R: Relation>(&self, source: u32) -> impl Iterator<Item = &D::Item<'item>> {
+    let targets = self.relations.read().targets::<R>(source).to_vec();
+    self.filter(targets)
+  }
+  pub fn related_from_mut<R: Relation>(
+    &mut self,
+    source: u32,
+  ) -> impl Iterator<Item = &mut D::Item<'item>> {
+    let targets = self.relations.read().targets::<R>(source).to_vec();
+    self.filter_mut(targets)
+  }
+
+  // Looked up once per item, a set keeps that from scaling with the number of related entities
+  fn filter(&self, entities: Vec<u32>) -> impl Iterator<Item = &D::Item<'item>> {
+    let entities: HashSet<_> = entities.into_iter().collect();
+    self
+      .entities
+      .iter()
+      .zip(self.data.iter())
+      .filter(move |(entity, _)| entities.contains(entity))
+      .map(|(_, item)| item)
+  }
+  fn filter_mut(&mut self, entities: Vec<u32>) -> impl Iterator<Item = &mut D::Item<'item>> {
+    let entities: HashSet<_> = entities.into_iter().collect();
+    self
+      .entities
+      .iter()
+      .zip(self.data.iter_mut())
+      .filter(move |(entity, _)| entities.contains(entity))
+      .map(|(_, item)| item)
+  }
+
+  // Resources aren't part of the query data, they're shared by every system
+  pub fn resource<T: Send + Sync + Any>(&self) -> Option<Res<T>> {
+    self.resources.get::<T>()
+  }
+  pub fn resource_mut<T: Send + Sync + Any>(&self) -> Option<ResMut<T>> {
+    self.resources.get_mut::<T>()
   }
 }
 
@@ -96,7 +171,7 @@ impl<C: Component> QueryData for &C {
   ) -> Result<Self::Item<'item>, DataError> {
     let container = component_manager
       .get_container::<C>()
-      .ok_or(InternalDataError::ContainerNotFound)?;
+      .ok_or(InternalDataError::ContainerNotFound(C::component_name()))?;
     Ok(ComponentRef(container.map(|c| c.get::<C>(entity).unwrap())))
   }
 }
@@ -114,7 +189,7 @@ impl<C: Component> QueryData for &mut C {
   ) -> Result<Self::Item<'item>, DataError> {
     let container = component_manager
       .get_container_mut::<C>()
-      .ok_or(InternalDataError::ContainerNotFound)?;
+      .ok_or(InternalDataError::ContainerNotFound(C::component_name()))?;
     Ok(ComponentRefMut(
       container.map(|c| c.get_mut::<C>(entity).unwrap()),
     ))
@@ -0,0 +1,379 @@
+use std::any::{type_name, Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+
+use bytemuck::Pod;
+use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{Component, ComponentManager, MapEntities};
+use crate::error::{BinaryError, DataError};
+use crate::utility::{ErasedMapContainer, SyncBox};
+
+// Type names without their module paths, generics included
+pub(crate) fn short_name(full: &str) -> String {
+  let mut name = String::with_capacity(full.len());
+  let mut segment = String::new();
+  for character in full.chars() {
+    if character.is_alphanumeric() || character == '_' || character == ':' {
+      segment.push(character);
+    } else {
+      name.push_str(segment.rsplit("::").next().unwrap_or_default());
+      segment.clear();
+      name.push(character);
+    }
+  }
+  name.push_str(segment.rsplit("::").next().unwrap_or_default());
+  name
+}
+
+// short_name of a type, leaked once per type so ComponentInfo can keep it as a &'static str
+pub(crate) fn interned_short_name<T: ?Sized + 'static>() -> &'static str {
+  static NAMES: RwLock<BTreeMap<TypeId, &'static str>> = RwLock::new(BTreeMap::new());
+  if let Some(name) = NAMES.read().get(&TypeId::of::<T>()) {
+    return name;
+  }
+  NAMES
+    .write()
+    .entry(TypeId::of::<T>())
+    .or_insert_with(|| Box::leak(short_name(type_name::<T>()).into_boxed_str()))
+}
+
+// Reads and writes a component as JSON without knowing its type
+#[derive(Clone, Copy, Debug)]
+pub struct SerdeVtable {
+  pub serialize: fn(&dyn Any) -> Result<Value, serde_json::Error>,
+  pub deserialize: fn(Value) -> Result<SyncBox, serde_json::Error>,
+}
+
+impl SerdeVtable {
+  pub fn of<C: Component + Serialize + DeserializeOwned>() -> Self {
+    Self {
+      serialize: |component| serde_json::to_value(component.downcast_ref::<C>().unwrap()),
+      deserialize: |value| serde_json::from_value::<C>(value).map(SyncBox::new),
+    }
+  }
+}
+
+// Writes and reads a component as the bytes of a snapshot, either as plain old data or through postcard
+#[derive(Clone, Copy, Debug)]
+pub struct BinaryVtable {
+  pub encode: fn(&dyn Any, &mut Vec<u8>) -> Result<(), BinaryError>,
+  pub decode: fn(&[u8]) -> Result<SyncBox, BinaryError>,
+}
+
+impl BinaryVtable {
+  pub fn pod<C: Component + Pod>() -> Self {
+    Self {
+      encode: |component, bytes| {
+        bytes.extend_from_slice(bytemuck::bytes_of(component.downcast_ref::<C>().unwrap()));
+        Ok(())
+      },
+      decode: |bytes| {
+        bytemuck::try_pod_read_unaligned::<C>(bytes)
+          .map(SyncBox::new)
+          .map_err(|error| format!("{error:?}").into())
+      },
+    }
+  }
+
+  pub fn serde<C: Component + Serialize + DeserializeOwned>() -> Self {
+    Self {
+      encode: |component, bytes| {
+        let encoded = postcard::to_extend(component.downcast_ref::<C>().unwrap(), Vec::new())?;
+        bytes.extend(encoded);
+        Ok(())
+      },
+      decode: |bytes| Ok(SyncBox::new(postcard::from_bytes::<C>(bytes)?)),
+    }
+  }
+}
+
+pub type DebugFn = fn(&dyn Any) -> String;
+pub type DefaultFn = fn() -> SyncBox;
+pub type MapEntitiesFn = fn(&mut dyn Any, &mut dyn FnMut(u32) -> u32);
+
+// What the engine knows about a component type without knowing the type
+#[derive(Clone, Copy, Debug)]
+pub struct ComponentInfo {
+  name: &'static str,
+  type_id: TypeId,
+  size: usize,
+  align: usize,
+  serde: Option<SerdeVtable>,
+  debug: Option<DebugFn>,
+  default: Option<DefaultFn>,
+  map_entities: Option<MapEntitiesFn>,
+  binary: Option<BinaryVtable>,
+  new_container: fn() -> ErasedMapContainer<u32>,
+}
+
+impl ComponentInfo {
+  // Without any vtable, #[derive(Component)] adds the ones the type supports
+  pub fn of<C: Component>() -> Self {
+    Self {
+      name: C::component_name(),
+      type_id: TypeId::of::<C>(),
+      size: size_of::<C>(),
+      align: align_of::<C>(),
+      serde: None,
+      debug: None,
+      default: None,
+      map_entities: None,
+      binary: None,
+      new_container: ErasedMapContainer::new::<C>,
+    }
+  }
+
+  pub fn with_serde(mut self, serde: Option<SerdeVtable>) -> Self {
+    self.serde = serde;
+    self
+  }
+  pub fn with_debug(mut self, debug: Option<DebugFn>) -> Self {
+    self.debug = debug;
+    self
+  }
+  pub fn with_default(mut self, default: Option<DefaultFn>) -> Self {
+    self.default = default;
+    self
+  }
+  pub fn with_map_entities(mut self, map_entities: Option<MapEntitiesFn>) -> Self {
+    self.map_entities = map_entities;
+    self
+  }
+
+  pub fn name(&self) -> &'static str {
+    self.name
+  }
+  pub fn type_id(&self) -> TypeId {
+    self.type_id
+  }
+  pub fn size(&self) -> usize {
+    self.size
+  }
+  pub fn align(&self) -> usize {
+    self.align
+  }
+  pub fn serde(&self) -> Option<SerdeVtable> {
+    self.serde
+  }
+  pub fn is_serializable(&self) -> bool {
+    self.serde.is_some()
+  }
+  pub fn has_debug(&self) -> bool {
+    self.debug.is_some()
+  }
+  pub fn has_default(&self) -> bool {
+    self.default.is_some()
+  }
+  pub fn maps_entities(&self) -> bool {
+    self.map_entities.is_some()
+  }
+  pub fn binary(&self) -> Option<BinaryVtable> {
+    self.binary
+  }
+
+  // None when the type has no Debug implementation or the value isn't of that type
+  pub fn debug(&self, component: &dyn Any) -> Option<String> {
+    let debug = self.debug?;
+    (component.type_id() == self.type_id).then(|| debug(component))
+  }
+
+  pub fn default_value(&self) -> Option<SyncBox> {
+    self.default.map(|default| default())
+  }
+
+  // Does nothing for components without entity ids
+  pub(crate) fn map_entities(&self, component: &mut SyncBox, map: &mut dyn FnMut(u32) -> u32) {
+    if let Some(map_entities) = self.map_entities {
+      if component.inner_type_id() == self.type_id {
+        map_entities(component.as_any_mut(), map);
+      }
+    }
+  }
+
+  pub(crate) fn new_container(&self) -> ErasedMapContainer<u32> {
+    (self.new_container)()
+  }
+
+  // None when the entity doesn't have the component or it isn't serializable
+  pub(crate) fn read_json(
+    &self,
+    components: &ComponentManager,
+    entity: u32,
+  ) -> Option<Result<Value, serde_json::Error>> {
+    let serde = self.serde?;
+    let container = components.get_container_from_id(&self.type_id)?;
+    let component = container.get_erased(&entity)?;
+    Some((serde.serialize)(component.as_any()))
+  }
+
+  pub(crate) fn read_debug(&self, components: &ComponentManager, entity: u32) -> Option<String> {
+    let container = components.get_container_from_id(&self.type_id)?;
+    self.debug(container.get_erased(&entity)?.as_any())
+  }
+
+  // The component is left as it was when the value doesn't deserialize
+  pub(crate) fn write_json(
+    &self,
+    components: &ComponentManager,
+    entity: u32,
+    value: Value,
+  ) -> Option<Result<(), serde_json::Error>> {
+    let serde = self.serde?;
+    let mut container = components.get_container_mut_from_id(&self.type_id)?;
+    if !container.contains_key(&entity) {
+      return None;
+    }
+    Some((serde.deserialize)(value).map(|component| {
+      // Deserialized as the container's type, it can't mismatch
+      let _ = container.replace_erased(entity, component);
+    }))
+  }
+}
+
+// Every component type added to an entity so far, with what its derive put in its ComponentInfo
+// Scenes and snapshots find types by name, so a name two types share can't be looked up
+#[derive(Default)]
+pub struct ComponentRegistry {
+  components: HashMap<TypeId, ComponentInfo>,
+  names: HashMap<&'static str, Vec<TypeId>>,
+}
+
+impl ComponentRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // Keeps what was registered before for that type
+  pub fn register<C: Component>(&mut self) {
+    self.entry(TypeId::of::<C>(), C::component_info);
+  }
+
+  // For components implemented by hand, which don't get their serde vtable from the derive
+  pub fn register_serde<C: Component + Serialize + DeserializeOwned>(&mut self) {
+    self.entry(TypeId::of::<C>(), C::component_info).serde = Some(SerdeVtable::of::<C>());
+  }
+
+  // Snapshots only hold components that opted into one of the two binary encodings
+  pub fn register_pod<C: Component + Pod>(&mut self) {
+    self.entry(TypeId::of::<C>(), C::component_info).binary = Some(BinaryVtable::pod::<C>());
+  }
+  pub fn register_postcard<C: Component + Serialize + DeserializeOwned>(&mut self) {
+    self.entry(TypeId::of::<C>(), C::component_info).binary = Some(BinaryVtable::serde::<C>());
+  }
+
+  fn entry(&mut self, type_id: TypeId, info: fn() -> ComponentInfo) -> &mut ComponentInfo {
+    self.components.entry(type_id).or_insert_with(|| {
+      let info = info();
+      self.names.entry(info.name).or_default().push(type_id);
+      info
+    })
+  }
+
+  pub fn get(&self, type_id: TypeId) -> Option<&ComponentInfo> {
+    self.components.get(&type_id)
+  }
+
+  // None when no type has the name, an error when more than one does
+  pub fn by_name(&self, name: &str) -> Result<Option<&ComponentInfo>, DataError> {
+    match self.names.get_key_value(name) {
+      None => Ok(None),
+      Some((_, types)) if types.len() == 1 => Ok(self.get(types[0])),
+      Some((&name, _)) => Err(DataError::DuplicateComponentName(name)),
+    }
+  }
+
+  // Falls back on the TypeId for types that were never registered
+  pub fn name(&self, type_id: TypeId) -> String {
+    self
+      .get(type_id)
+      .map_or_else(|| format!("{type_id:?}"), |info| info.name.to_owned())
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &ComponentInfo> {
+    self.components.values()
+  }
+
+  pub fn len(&self) -> usize {
+    self.components.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.components.is_empty()
+  }
+}
+
+// Lets #[derive(Component)] find out which traits the type implements without asking for attributes
+// Methods are called on &&Probe<C>, the impls on &Probe<C> win whenever C meets their bounds
+// and the fallbacks on Probe<C> are picked otherwise
+pub struct Probe<C>(PhantomData<C>);
+
+impl<C> Probe<C> {
+  #[allow(clippy::new_without_default)]
+  pub fn new() -> Self {
+    Self(PhantomData)
+  }
+}
+
+pub trait SerdeProbe {
+  fn serde(&self) -> Option<SerdeVtable>;
+}
+impl<C: Component + Serialize + DeserializeOwned> SerdeProbe for &Probe<C> {
+  fn serde(&self) -> Option<SerdeVtable> {
+    Some(SerdeVtable::of::<C>())
+  }
+}
+pub trait SerdeFallback {
+  fn serde(&self) -> Option<SerdeVtable> {
+    None
+  }
+}
+impl<C> SerdeFallback for Probe<C> {}
+
+pub trait DebugProbe {
+  fn debug(&self) -> Option<DebugFn>;
+}
+impl<C: Component + std::fmt::Debug> DebugProbe for &Probe<C> {
+  fn debug(&self) -> Option<DebugFn> {
+    Some(|component| format!("{:?}", component.downcast_ref::<C>().unwrap()))
+  }
+}
+pub trait DebugFallback {
+  fn debug(&self) -> Option<DebugFn> {
+    None
+  }
+}
+impl<C> DebugFallback for Probe<C> {}
+
+pub trait DefaultProbe {
+  fn default_fn(&self) -> Option<DefaultFn>;
+}
+impl<C: Component + Default> DefaultProbe for &Probe<C> {
+  fn default_fn(&self) -> Option<DefaultFn> {
+    Some(|| SyncBox::new(C::default()))
+  }
+}
+pub trait DefaultFallback {
+  fn default_fn(&self) -> Option<DefaultFn> {
+    None
+  }
+}
+impl<C> DefaultFallback for Probe<C> {}
+
+pub trait MapEntitiesProbe {
+  fn map_entities(&self) -> Option<MapEntitiesFn>;
+}
+impl<C: Component + MapEntities> MapEntitiesProbe for &Probe<C> {
+  fn map_entities(&self) -> Option<MapEntitiesFn> {
+    Some(|component, map| component.downcast_mut::<C>().unwrap().map_entities(map))
+  }
+}
+pub trait MapEntitiesFallback {
+  fn map_entities(&self) -> Option<MapEntitiesFn> {
+    None
+  }
+}
+impl<C> MapEntitiesFallback for Probe<C> {}
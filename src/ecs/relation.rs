@@ -0,0 +1,141 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+
+// A kind of link from a source entity to a target entity, looked up from either end
+// Exclusive relations give a source one target at most, relating it again replaces the previous one
+pub trait Relation: Send + Sync + 'static {
+  const EXCLUSIVE: bool = false;
+}
+
+// The source aims at the target
+pub struct Targets;
+impl Relation for Targets {}
+
+// The source belongs to the target
+pub struct OwnedBy;
+impl Relation for OwnedBy {
+  const EXCLUSIVE: bool = true;
+}
+
+// Kept in sync with Parent and Children, P1::relate goes through P1::set_parent for it
+pub struct ChildOf;
+impl Relation for ChildOf {
+  const EXCLUSIVE: bool = true;
+}
+
+// Both directions are stored so neither lookup has to go through every pair
+#[derive(Default)]
+struct RelationTable {
+  targets: HashMap<u32, Vec<u32>>,
+  sources: HashMap<u32, Vec<u32>>,
+}
+
+impl RelationTable {
+  fn insert(&mut self, source: u32, target: u32) -> bool {
+    let targets = self.targets.entry(source).or_default();
+    if targets.contains(&target) {
+      return false;
+    }
+    targets.push(target);
+    self.sources.entry(target).or_default().push(source);
+    true
+  }
+
+  fn remove(&mut self, source: u32, target: u32) -> bool {
+    let removed = detach(&mut self.targets, source, target);
+    if removed {
+      detach(&mut self.sources, target, source);
+    }
+    removed
+  }
+
+  fn remove_entity(&mut self, entity: u32) {
+    for target in self.targets.remove(&entity).unwrap_or_default() {
+      detach(&mut self.sources, target, entity);
+    }
+    for source in self.sources.remove(&entity).unwrap_or_default() {
+      detach(&mut self.targets, source, entity);
+    }
+  }
+}
+
+// Drops the key along with its last entity
+fn detach(map: &mut HashMap<u32, Vec<u32>>, key: u32, entity: u32) -> bool {
+  let Some(entities) = map.get_mut(&key) else {
+    return false;
+  };
+  let Some(index) = entities.iter().position(|e| *e == entity) else {
+    return false;
+  };
+  entities.remove(index);
+  if entities.is_empty() {
+    map.remove(&key);
+  }
+  true
+}
+
+// Every relation between entities, by relation type, in the order they were made
+#[derive(Default)]
+pub(crate) struct Relations(HashMap<TypeId, RelationTable>);
+
+impl Relations {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // Returns the target it replaced for exclusive relations
+  pub fn relate<R: Relation>(&mut self, source: u32, target: u32) -> Option<u32> {
+    let table = self.0.entry(TypeId::of::<R>()).or_default();
+    let mut replaced = None;
+    if R::EXCLUSIVE {
+      if let Some(previous) = table
+        .targets
+        .get(&source)
+        .and_then(|targets| targets.first())
+      {
+        if *previous != target {
+          replaced = Some(*previous);
+        }
+      }
+      if let Some(previous) = replaced {
+        table.remove(source, previous);
+      }
+    }
+    table.insert(source, target);
+    replaced
+  }
+
+  pub fn unrelate<R: Relation>(&mut self, source: u32, target: u32) -> bool {
+    self
+      .0
+      .get_mut(&TypeId::of::<R>())
+      .is_some_and(|table| table.remove(source, target))
+  }
+
+  pub fn contains<R: Relation>(&self, source: u32, target: u32) -> bool {
+    self.targets::<R>(source).contains(&target)
+  }
+
+  pub fn targets<R: Relation>(&self, source: u32) -> &[u32] {
+    self
+      .0
+      .get(&TypeId::of::<R>())
+      .and_then(|table| table.targets.get(&source))
+      .map_or(&[], Vec::as_slice)
+  }
+
+  pub fn sources<R: Relation>(&self, target: u32) -> &[u32] {
+    self
+      .0
+      .get(&TypeId::of::<R>())
+      .and_then(|table| table.sources.get(&target))
+      .map_or(&[], Vec::as_slice)
+  }
+
+  // Whichever end of the relations the entity is on
+  pub fn remove_entity(&mut self, entity: u32) {
+    for table in self.0.values_mut() {
+      table.remove_entity(entity);
+    }
+  }
+}
@@ -0,0 +1,77 @@
+use std::any::{Any, TypeId};
+use std::hash::BuildHasherDefault;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use parking_lot::lock_api::{ArcRwLockReadGuard, ArcRwLockWriteGuard};
+use parking_lot::{RawRwLock, RwLock};
+use rustc_hash::FxHasher;
+
+use crate::utility::SyncBox;
+
+// Engine-wide singletons, not tied to any entity
+// Each resource has its own lock so holding one never blocks access to another sharing its map shard
+pub(crate) struct ResourceManager(
+  DashMap<TypeId, Arc<RwLock<SyncBox>>, BuildHasherDefault<FxHasher>>,
+);
+
+impl ResourceManager {
+  pub fn new() -> Self {
+    Self(DashMap::with_hasher(BuildHasherDefault::default()))
+  }
+
+  pub fn insert<T: Send + Sync + Any>(&self, resource: T) {
+    self.0.insert(
+      TypeId::of::<T>(),
+      Arc::new(RwLock::new(SyncBox::new(resource))),
+    );
+  }
+
+  pub fn contains<T: Send + Sync + Any>(&self) -> bool {
+    self.0.contains_key(&TypeId::of::<T>())
+  }
+
+  fn lock<T: Send + Sync + Any>(&self) -> Option<Arc<RwLock<SyncBox>>> {
+    self.0.get(&TypeId::of::<T>()).map(|lock| lock.clone())
+  }
+
+  pub fn get<T: Send + Sync + Any>(&self) -> Option<Res<T>> {
+    let guard = self.lock::<T>()?.read_arc();
+    guard.cast_ref::<T>()?;
+    Some(Res(guard, PhantomData))
+  }
+
+  pub fn get_mut<T: Send + Sync + Any>(&self) -> Option<ResMut<T>> {
+    let mut guard = self.lock::<T>()?.write_arc();
+    guard.cast_mut::<T>()?;
+    Some(ResMut(guard, PhantomData))
+  }
+}
+
+pub struct Res<T>(ArcRwLockReadGuard<RawRwLock, SyncBox>, PhantomData<T>);
+
+impl<T: Send + Sync + Any> Deref for Res<T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    // The type was checked when the guard was taken
+    self.0.cast_ref::<T>().unwrap()
+  }
+}
+
+pub struct ResMut<T>(ArcRwLockWriteGuard<RawRwLock, SyncBox>, PhantomData<T>);
+
+impl<T: Send + Sync + Any> Deref for ResMut<T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    self.0.cast_ref::<T>().unwrap()
+  }
+}
+impl<T: Send + Sync + Any> DerefMut for ResMut<T> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    self.0.cast_mut::<T>().unwrap()
+  }
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AssetError {
+  #[error("No asset loader is registered for files ending in '.{0}'.")]
+  NoLoader(String),
+  #[error("'{path}' does not load into a {expected}.")]
+  WrongAssetType {
+    path: PathBuf,
+    expected: &'static str,
+  },
+  #[error("Could not read the asset file.")]
+  Io(#[from] std::io::Error),
+  #[error("Could not decode the image.")]
+  Image(#[from] png::DecodingError),
+  #[error("Could not read the font.")]
+  Font(#[from] ab_glyph::InvalidFont),
+  #[error("The asset file is not valid UTF-8.")]
+  Utf8(#[from] std::string::FromUtf8Error),
+  #[error("Could not parse the asset file.")]
+  Parse(#[from] serde_json::Error),
+  #[error("Could not read the prefab.")]
+  Prefab(#[from] crate::error::SceneError),
+  #[error("{0}")]
+  Custom(String),
+}
@@ -3,21 +3,33 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum InternalDataError {
-  #[error("A component was pushed to an incompatible container.")]
-  MismatchedComponentType,
-  #[error("No container for the provided component type was found.")]
-  ContainerNotFound,
+  #[error("A {found} component was pushed to the container of {expected}.")]
+  MismatchedComponentType {
+    expected: &'static str,
+    found: &'static str,
+  },
+  #[error("No container for {0} components was found.")]
+  ContainerNotFound(&'static str),
 }
 
 #[derive(Error, Debug)]
 pub enum DataError {
   #[error("No entities with the provided id was found.")]
   EntityNotFound,
-  // Need input-defined typename info soon
-  #[error(
-    "Cannot attach component to entity because a component of that type is already attached."
-  )]
-  ComponentExistsForEntity,
+  #[error("Cannot attach {0} to the entity because it already has one.")]
+  ComponentExistsForEntity(&'static str),
+  #[error("An entity cannot be parented to itself or to one of its descendants.")]
+  InvalidParent,
+  #[error("No component named {0} was registered.")]
+  UnknownComponent(String),
+  #[error("The entity has no {0} component.")]
+  MissingComponent(String),
+  #[error("{0} cannot be read or written as JSON, it has to be registered with serde.")]
+  NotSerializable(String),
+  #[error("More than one component type is named {0}, it can't be found by name.")]
+  DuplicateComponentName(&'static str),
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
   #[error(transparent)]
   Internal(#[from] InternalDataError),
 }
@@ -25,10 +37,10 @@ pub enum DataError {
 impl From<UtilityContainerError> for DataError {
   fn from(value: UtilityContainerError) -> Self {
     match value {
-      UtilityContainerError::MismatchedTypeId(_, _) => {
-        DataError::Internal(InternalDataError::MismatchedComponentType)
+      UtilityContainerError::MismatchedType { expected, found } => {
+        DataError::Internal(InternalDataError::MismatchedComponentType { expected, found })
       }
-      UtilityContainerError::EntryOccupied => DataError::ComponentExistsForEntity,
+      UtilityContainerError::EntryOccupied(name) => DataError::ComponentExistsForEntity(name),
     }
   }
 }
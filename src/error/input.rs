@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum InputError {
+  #[error("No action or axis named '{0}' was found.")]
+  UnknownAction(String),
+  #[error("Could not read or write the input file.")]
+  Io(#[from] std::io::Error),
+  #[error("Could not parse the input file.")]
+  Parse(#[from] serde_json::Error),
+  #[error("Input recordings of version {0} are not supported.")]
+  UnsupportedRecording(u32),
+  #[error("Replaying inputs requires the engine to run on a manual clock.")]
+  ReplayNeedsManualClock,
+}
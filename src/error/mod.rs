@@ -1,11 +1,23 @@
+mod asset;
 mod data;
 mod event;
+mod input;
 mod p1;
+mod render;
+mod scene;
+mod snapshot;
 mod system;
 mod utility;
+mod window;
 
+pub use asset::AssetError;
 pub use data::{DataError, InternalDataError};
 pub use event::EventError;
+pub use input::InputError;
 pub use p1::P1Error;
+pub use render::RenderError;
+pub use scene::SceneError;
+pub use snapshot::{BinaryError, SnapshotError};
 pub use system::SystemError;
 pub use utility::UtilityContainerError;
+pub use window::WindowError;
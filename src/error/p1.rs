@@ -1,6 +1,9 @@
 use thiserror::Error;
 
-use super::{DataError, InternalDataError, SystemError};
+use super::{
+  AssetError, DataError, EventError, InputError, InternalDataError, RenderError, SceneError,
+  SnapshotError, SystemError, WindowError,
+};
 
 #[derive(Error, Debug)]
 pub enum P1Error {
@@ -8,6 +11,20 @@ pub enum P1Error {
   Data(#[from] DataError),
   #[error(transparent)]
   System(#[from] SystemError),
+  #[error(transparent)]
+  Event(#[from] EventError),
+  #[error(transparent)]
+  Input(#[from] InputError),
+  #[error(transparent)]
+  Window(#[from] WindowError),
+  #[error(transparent)]
+  Render(#[from] RenderError),
+  #[error(transparent)]
+  Asset(#[from] AssetError),
+  #[error(transparent)]
+  Scene(#[from] SceneError),
+  #[error(transparent)]
+  Snapshot(#[from] SnapshotError),
 }
 
 impl From<InternalDataError> for P1Error {
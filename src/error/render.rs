@@ -0,0 +1,23 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RenderError {
+  #[error("No graphics adapter compatible with the requested mode was found.")]
+  NoAdapter,
+  #[error("Could not open the graphics device.")]
+  RequestDevice(#[from] wgpu::RequestDeviceError),
+  #[error("Could not create a surface for the window.")]
+  CreateSurface(#[from] wgpu::CreateSurfaceError),
+  #[error("The window surface is not supported by the graphics adapter.")]
+  UnsupportedSurface,
+  #[error("The graphics device ran out of memory.")]
+  OutOfMemory,
+  #[error("No render target for window entity {0} was found.")]
+  TargetNotFound(u32),
+  #[error("The renderer has no offscreen target, it wasn't created headless.")]
+  NoOffscreenTarget,
+  #[error("The null backend draws nothing, there are no pixels to read back.")]
+  NullBackend,
+  #[error("Could not map the readback buffer.")]
+  Readback(#[from] wgpu::BufferAsyncError),
+}
@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+use super::DataError;
+
+#[derive(Error, Debug)]
+pub enum SceneError {
+  #[error("The scene is version {found}, only version {supported} can be loaded.")]
+  UnsupportedVersion { found: u64, supported: u64 },
+  #[error("The scene has no version field.")]
+  MissingVersion,
+  #[error("Entity {entity} of the scene has a {name} component, but no component by that name was registered.")]
+  UnknownComponent { entity: u32, name: String },
+  #[error("{0} cannot be read from JSON, it has to be registered with serde.")]
+  NotSerializable(String),
+  #[error("Could not read the {name} component of entity {entity} of the scene.")]
+  Component {
+    entity: u32,
+    name: String,
+    #[source]
+    source: serde_json::Error,
+  },
+  #[error("Entity {0} is in the scene more than once.")]
+  DuplicateEntity(u32),
+  #[error(
+    "Entity {entity} of the scene has entity {target} as a child, which isn't in the scene."
+  )]
+  MissingEntity { entity: u32, target: u32 },
+  #[error(
+    "Entity {0} of the scene is a child of more than one entity, or of one of its descendants."
+  )]
+  InvalidHierarchy(u32),
+  #[error("The prefab has no field named {0}.")]
+  UnknownField(String),
+  #[error("Field {0} of the prefab doesn't point at a value of a component in the prefab.")]
+  InvalidField(String),
+  #[error("Could not parse the scene.")]
+  Json(#[from] serde_json::Error),
+  #[error(transparent)]
+  Data(#[from] DataError),
+}
@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+use super::DataError;
+
+// Whatever the binary encoding of a component failed with
+pub type BinaryError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+  #[error("The bytes are not a P1 snapshot.")]
+  InvalidHeader,
+  #[error("The snapshot is version {found}, only version {supported} can be restored.")]
+  UnsupportedVersion { found: u32, supported: u32 },
+  #[error("The snapshot ends in the middle of {0}.")]
+  Truncated(&'static str),
+  #[error("The snapshot has a {0} column, but no component by that name was registered.")]
+  UnknownComponent(String),
+  #[error("{0} has no binary encoding, it has to be registered as plain old data or with serde.")]
+  NotEncodable(String),
+  #[error("Could not encode the {name} component of entity {entity}.")]
+  Encode {
+    name: &'static str,
+    entity: u32,
+    #[source]
+    source: BinaryError,
+  },
+  #[error("Could not decode the {name} component of entity {entity}.")]
+  Decode {
+    name: String,
+    entity: u32,
+    #[source]
+    source: BinaryError,
+  },
+  #[error("The delta was made against another snapshot than the one it's applied to.")]
+  DeltaMismatch,
+  #[error("The delta changes bytes past the end of the snapshot.")]
+  DeltaOutOfRange,
+  #[error(transparent)]
+  Data(#[from] DataError),
+}
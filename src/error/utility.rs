@@ -1,10 +1,12 @@
-use std::any::TypeId;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum UtilityContainerError {
-  #[error("A type was pushed to an incompatible container.")]
-  MismatchedTypeId(TypeId, TypeId),
-  #[error("Container for provided component type was not found.")]
-  EntryOccupied,
+  #[error("Cannot push {found} to a container of {expected}.")]
+  MismatchedType {
+    expected: &'static str,
+    found: &'static str,
+  },
+  #[error("The container of {0} already has a value for that key.")]
+  EntryOccupied(&'static str),
 }
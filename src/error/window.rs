@@ -0,0 +1,10 @@
+use thiserror::Error;
+use winit::error::{EventLoopError, OsError};
+
+#[derive(Error, Debug)]
+pub enum WindowError {
+  #[error("The window event loop failed.")]
+  EventLoop(#[from] EventLoopError),
+  #[error("Could not create a window.")]
+  Creation(#[from] OsError),
+}
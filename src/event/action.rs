@@ -0,0 +1,197 @@
+use super::{GamepadAxis, GamepadButton, InputState};
+use crate::error::InputError;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+  Key(KeyCode),
+  Mouse(MouseButton),
+  Gamepad(GamepadButton),
+}
+
+impl Binding {
+  fn pressed(&self, input: &InputState) -> bool {
+    match *self {
+      Binding::Key(key) => input.keys().pressed(key),
+      Binding::Mouse(button) => input.mouse_buttons().pressed(button),
+      Binding::Gamepad(button) => input.gamepad_buttons().pressed(button),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+  // Two buttons pushing the axis towards -1 and 1
+  Buttons {
+    negative: Binding,
+    positive: Binding,
+  },
+  Gamepad(GamepadAxis),
+}
+
+impl AxisBinding {
+  fn value(&self, input: &InputState) -> f32 {
+    match *self {
+      AxisBinding::Buttons { negative, positive } => {
+        positive.pressed(input) as i32 as f32 - negative.pressed(input) as i32 as f32
+      }
+      AxisBinding::Gamepad(axis) => input.gamepad_axis(axis),
+    }
+  }
+}
+
+// The part of the action map that gets saved and loaded
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionBindings {
+  #[serde(default)]
+  pub actions: BTreeMap<String, Vec<Binding>>,
+  #[serde(default)]
+  pub axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ActionValue {
+  pressed: bool,
+  just_pressed: bool,
+  just_released: bool,
+}
+
+// Resource mapping gameplay names to raw inputs, refreshed by the engine every frame
+pub struct ActionMap {
+  bindings: ActionBindings,
+  actions: HashMap<String, ActionValue>,
+  axes: HashMap<String, f32>,
+  // Held actions that were cleared, released on the next refresh
+  cleared: Vec<String>,
+}
+
+impl ActionMap {
+  pub fn new() -> Self {
+    Self::from_bindings(ActionBindings::default())
+  }
+
+  pub fn from_bindings(bindings: ActionBindings) -> Self {
+    Self {
+      bindings,
+      actions: HashMap::new(),
+      axes: HashMap::new(),
+      cleared: Vec::new(),
+    }
+  }
+
+  pub fn from_json(json: &str) -> Result<Self, InputError> {
+    Ok(Self::from_bindings(serde_json::from_str(json)?))
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, InputError> {
+    Self::from_json(&fs::read_to_string(path)?)
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InputError> {
+    fs::write(path, serde_json::to_string_pretty(&self.bindings)?)?;
+    Ok(())
+  }
+
+  pub fn bindings(&self) -> &ActionBindings {
+    &self.bindings
+  }
+
+  pub fn bind(&mut self, action: &str, binding: Binding) {
+    let bindings = self.bindings.actions.entry(action.to_owned()).or_default();
+    if !bindings.contains(&binding) {
+      bindings.push(binding);
+    }
+  }
+
+  pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+    let bindings = self.bindings.axes.entry(axis.to_owned()).or_default();
+    if !bindings.contains(&binding) {
+      bindings.push(binding);
+    }
+  }
+
+  pub fn unbind(&mut self, action: &str, binding: Binding) -> Result<(), InputError> {
+    self
+      .bindings
+      .actions
+      .get_mut(action)
+      .ok_or_else(|| InputError::UnknownAction(action.to_owned()))?
+      .retain(|b| *b != binding);
+    Ok(())
+  }
+
+  // Swaps a binding in place, or adds it if the old one wasn't bound
+  pub fn rebind(&mut self, action: &str, old: Binding, new: Binding) -> Result<(), InputError> {
+    let bindings = self
+      .bindings
+      .actions
+      .get_mut(action)
+      .ok_or_else(|| InputError::UnknownAction(action.to_owned()))?;
+    match bindings.iter_mut().find(|b| **b == old) {
+      Some(binding) => *binding = new,
+      None => bindings.push(new),
+    }
+    Ok(())
+  }
+
+  pub fn clear(&mut self, action: &str) {
+    if self.pressed(action) {
+      self.cleared.push(action.to_owned());
+    }
+    self.bindings.actions.remove(action);
+    self.bindings.axes.remove(action);
+    self.actions.remove(action);
+    self.axes.remove(action);
+  }
+
+  pub fn pressed(&self, action: &str) -> bool {
+    self.actions.get(action).is_some_and(|a| a.pressed)
+  }
+  pub fn just_pressed(&self, action: &str) -> bool {
+    self.actions.get(action).is_some_and(|a| a.just_pressed)
+  }
+  pub fn just_released(&self, action: &str) -> bool {
+    self.actions.get(action).is_some_and(|a| a.just_released)
+  }
+
+  pub fn axis(&self, axis: &str) -> f32 {
+    self.axes.get(axis).copied().unwrap_or(0.0)
+  }
+
+  // Returns the actions that were just pressed and just released, in that order
+  pub(crate) fn refresh(&mut self, input: &InputState) -> (Vec<String>, Vec<String>) {
+    let mut pressed = Vec::new();
+    let mut released = std::mem::take(&mut self.cleared);
+
+    for (name, bindings) in self.bindings.actions.iter() {
+      let value = self.actions.entry(name.clone()).or_default();
+      let is_pressed = bindings.iter().any(|binding| binding.pressed(input));
+      value.just_pressed = is_pressed && !value.pressed;
+      value.just_released = !is_pressed && value.pressed;
+      value.pressed = is_pressed;
+
+      if value.just_pressed {
+        pressed.push(name.clone());
+      } else if value.just_released {
+        released.push(name.clone());
+      }
+    }
+
+    for (name, bindings) in self.bindings.axes.iter() {
+      let value = bindings
+        .iter()
+        .map(|binding| binding.value(input))
+        .sum::<f32>();
+      self.axes.insert(name.clone(), value.clamp(-1.0, 1.0));
+    }
+
+    (pressed, released)
+  }
+}
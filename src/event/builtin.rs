@@ -1,29 +1,166 @@
 use std::any::TypeId;
+use std::path::PathBuf;
 
-use super::{EventData, EventManager, IntervalListener, SimpleListener, Tick};
+use super::{EventData, EventManager, IntervalListener, SimpleListener, Tick, Time, TimerSource};
 use crate::error::EventError;
 
-use chrono::TimeDelta;
 use macros::EventData;
-use winit::window::WindowId;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
 
-/*pub struct Exit;
-impl EventData for Exit {
-  type Item = WindowId;
+#[derive(EventData, Clone, Copy)]
+pub struct Resume;
 
-  fn get_item(tick: &Tick/* , command_managers: _ */) -> Self::Item {
-    // Will first fetch the command manager using the Self::Item TypeId
-    // then the Self TypeId to get the appropriate command list
-    // finally, gets the youngest command of that type IF that tick is younger than the passed tick
-    command_managers.get_latest_value_for::<Self>(tick).ok_or(EventError::CannotFetchEventData(TypeId::of::<Self>(), *tick))
-  }
-}*/
+// The last window was closed, the event loop stops right after
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct Exit;
 
-#[derive(EventData)]
-pub struct Resume;
+// Window payloads hold the entity of the window, it is already despawned when this is emitted
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct WindowClosed {
+  pub window: u32,
+}
+
+// Sizes are in physical pixels
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct Resized {
+  pub window: u32,
+  pub width: u32,
+  pub height: u32,
+}
 
-#[derive(EventData)]
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct Focused {
+  pub window: u32,
+  pub focused: bool,
+}
+
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct ScaleFactorChanged {
+  pub window: u32,
+  pub scale_factor: f64,
+}
+
+// Emitted once per frame by the engine with the timings of that frame
 pub struct Update;
+impl EventData for Update {
+  type Item = Time;
+
+  fn get_item(tick: &Tick, event_manager: &EventManager) -> Result<Self::Item, EventError> {
+    event_manager
+      .latest::<Self>()
+      .ok_or(EventError::CannotFetchEventData(TypeId::of::<Self>(), *tick))
+  }
+}
+
+// Emitted every time a scheduled or entity timer expires
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct TimerFinished {
+  pub source: TimerSource,
+}
+
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct KeyDown {
+  pub key: KeyCode,
+}
+
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct KeyUp {
+  pub key: KeyCode,
+}
+
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct MouseButtonDown {
+  pub button: MouseButton,
+}
+
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct MouseButtonUp {
+  pub button: MouseButton,
+}
+
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct MouseMove {
+  pub position: (f64, f64),
+  pub delta: (f64, f64),
+}
+
+// Emitted by the engine when an action of the ActionMap resource changes state
+#[derive(EventData, Clone, Debug)]
+pub struct ActionPressed {
+  pub action: String,
+}
+
+#[derive(EventData, Clone, Debug)]
+pub struct ActionReleased {
+  pub action: String,
+}
+
+// Emitted from P1::update once the asset is in its Assets<T>, the id is the one of its handles
+#[derive(EventData, Clone, Debug)]
+pub struct AssetLoaded {
+  pub id: u64,
+  pub path: PathBuf,
+}
+
+// The file of a loaded asset changed and the new version replaced it in its Assets<T>
+#[derive(EventData, Clone, Debug)]
+pub struct AssetModified {
+  pub id: u64,
+  pub path: PathBuf,
+}
+
+// Also emitted when reloading a modified asset failed, the previous version is kept then
+#[derive(EventData, Clone, Debug)]
+pub struct AssetFailed {
+  pub id: u64,
+  pub path: PathBuf,
+  pub error: String,
+}
+
+// UI payloads hold the entity of the widget
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct ButtonClicked {
+  pub entity: u32,
+}
+
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct CheckboxToggled {
+  pub entity: u32,
+  pub checked: bool,
+}
+
+#[derive(EventData, Clone, Copy, Debug)]
+pub struct SliderChanged {
+  pub entity: u32,
+  pub value: f32,
+}
+
+#[derive(EventData, Clone, Debug)]
+pub struct TextChanged {
+  pub entity: u32,
+  pub value: String,
+}
+
+#[derive(EventData, Clone, Debug)]
+pub struct TextSubmitted {
+  pub entity: u32,
+  pub value: String,
+}
+
+// Entities of the instance in the prefab's order, the id is the one Commands::instantiate gave it
+#[derive(EventData, Clone, Debug)]
+pub struct PrefabInstantiated {
+  pub id: u64,
+  pub entities: Vec<u32>,
+}
+
+// The instance was dropped, its overrides didn't apply or its prefab failed to load
+#[derive(EventData, Clone, Debug)]
+pub struct PrefabFailed {
+  pub id: u64,
+  pub error: String,
+}
 
 // Make this a bitmask
 /*pub struct BuiltinSettings {
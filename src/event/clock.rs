@@ -0,0 +1,158 @@
+use super::Tick;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use parking_lot::RwLock;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Time {
+  delta: TimeDelta,
+  elapsed: TimeDelta,
+  alpha: f64,
+  fixed_steps: u32,
+  frame: u64,
+}
+
+impl Time {
+  fn zero() -> Self {
+    Self {
+      delta: TimeDelta::zero(),
+      elapsed: TimeDelta::zero(),
+      alpha: 0.0,
+      fixed_steps: 0,
+      frame: 0,
+    }
+  }
+
+  pub fn delta(&self) -> TimeDelta {
+    self.delta
+  }
+  pub fn delta_secs(&self) -> f64 {
+    self.delta.num_nanoseconds().unwrap_or(0) as f64 / 1e9
+  }
+  pub fn elapsed(&self) -> TimeDelta {
+    self.elapsed
+  }
+  // How far the frame is between the last fixed step and the next one, from 0 to 1
+  pub fn alpha(&self) -> f64 {
+    self.alpha
+  }
+  // Number of fixed steps that fit in this frame
+  pub fn fixed_steps(&self) -> u32 {
+    self.fixed_steps
+  }
+  pub fn frame(&self) -> u64 {
+    self.frame
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ClockSource {
+  Real,
+  // Only moves when advanced, so tests get the exact same timings every run
+  Manual(DateTime<Utc>),
+}
+
+struct ClockState {
+  source: ClockSource,
+  offset: TimeDelta,
+  start: DateTime<Utc>,
+  last_frame: Option<DateTime<Utc>>,
+  fixed_step: TimeDelta,
+  accumulator: TimeDelta,
+  time: Time,
+}
+
+impl ClockState {
+  fn now(&self) -> DateTime<Utc> {
+    let base = match self.source {
+      ClockSource::Real => Utc::now(),
+      ClockSource::Manual(origin) => origin,
+    };
+    base + self.offset
+  }
+}
+
+// The engine's single source of time, every thread reads from it so systems agree on frame timings
+pub struct Clock(RwLock<ClockState>);
+
+impl Clock {
+  pub fn new() -> Self {
+    Self::with_source(ClockSource::Real)
+  }
+
+  pub fn manual() -> Self {
+    Self::with_source(ClockSource::Manual(DateTime::UNIX_EPOCH))
+  }
+
+  fn with_source(source: ClockSource) -> Self {
+    let start = match source {
+      ClockSource::Real => Utc::now(),
+      ClockSource::Manual(origin) => origin,
+    };
+    Self(RwLock::new(ClockState {
+      source,
+      offset: TimeDelta::zero(),
+      start,
+      last_frame: None,
+      fixed_step: TimeDelta::nanoseconds(1_000_000_000 / 60),
+      accumulator: TimeDelta::zero(),
+      time: Time::zero(),
+    }))
+  }
+
+  pub fn is_manual(&self) -> bool {
+    matches!(self.0.read().source, ClockSource::Manual(_))
+  }
+
+  pub fn now(&self) -> Tick {
+    self.0.read().now().into()
+  }
+
+  // Moves the clock forward, this is the only way a manual clock progresses
+  pub fn advance(&self, delta: TimeDelta) {
+    self.0.write().offset += delta;
+  }
+
+  pub fn fixed_step(&self) -> TimeDelta {
+    self.0.read().fixed_step
+  }
+  pub fn set_fixed_step(&self, step: TimeDelta) {
+    assert!(step > TimeDelta::zero(), "Fixed step must be positive.");
+    self.0.write().fixed_step = step;
+  }
+
+  // Latest frame timings computed by tick_frame
+  pub fn time(&self) -> Time {
+    self.0.read().time
+  }
+
+  pub fn tick_frame(&self) -> Time {
+    let mut state = self.0.write();
+    let now = state.now();
+    let delta = state
+      .last_frame
+      .map(|last| (now - last).max(TimeDelta::zero()))
+      .unwrap_or(TimeDelta::zero());
+    state.last_frame = Some(now);
+
+    let fixed_step = state.fixed_step;
+    state.accumulator += delta;
+    let mut fixed_steps = 0;
+    while state.accumulator >= fixed_step {
+      state.accumulator -= fixed_step;
+      fixed_steps += 1;
+    }
+
+    let alpha = state.accumulator.num_nanoseconds().unwrap_or(0) as f64
+      / fixed_step.num_nanoseconds().unwrap_or(1) as f64;
+
+    state.time = Time {
+      delta,
+      elapsed: now - state.start,
+      alpha,
+      fixed_steps,
+      frame: state.time.frame + 1,
+    };
+    state.time
+  }
+}
@@ -1,135 +1,252 @@
-use super::Tick;
+use super::{Clock, Tick};
 use crate::error::EventError;
+use crate::utility::SyncBox;
 
 use std::any::{Any, TypeId};
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::hash::BuildHasherDefault;
-use std::ops::Add;
+use std::slice::Iter;
 use std::sync::Arc;
 
-use chrono::TimeDelta;
 use parking_lot::RwLock;
 use rustc_hash::FxHasher;
 
-pub trait EventData: Send + Sync + Any {
-  type Item: Clone + Copy;
+pub trait EventData: Sized + Send + Sync + Any {
+  type Item: Clone + Send + Sync + Any;
 
-  fn get_item(tick: &Tick /* , command_managers: _ */) -> Self::Item;
+  fn get_item(tick: &Tick, event_manager: &EventManager) -> Result<Self::Item, EventError>;
+
+  // Everything emitted since the passed tick, so systems running slower than the event don't miss any
+  fn get_items(tick: &Tick, event_manager: &EventManager) -> Result<Vec<Self::Item>, EventError> {
+    let items = event_manager.since::<Self>(tick);
+    if items.is_empty() {
+      Ok(vec![Self::get_item(tick, event_manager)?])
+    } else {
+      Ok(items)
+    }
+  }
 }
 
 pub trait EventListener: Send + Sync + Any {
-  fn check(&self, other: &Tick) -> bool;
+  fn check(&self, other: &Tick, now: &Tick) -> bool;
 
-  fn emit(&self);
+  fn emit(&self, tick: Tick);
 }
 
 pub struct SimpleListener(Arc<RwLock<Tick>>);
 
 impl SimpleListener {
   pub fn new() -> Self {
-    Self(Arc::new(RwLock::new(Tick::new())))
+    Self(Arc::new(RwLock::new(Tick::origin())))
   }
 }
 impl EventListener for SimpleListener {
-  fn check(&self, other: &Tick) -> bool {
+  fn check(&self, other: &Tick, _: &Tick) -> bool {
     self.0.read().cmp(other) == Ordering::Greater
   }
 
-  fn emit(&self) {
-    self.0.write().touch();
+  fn emit(&self, tick: Tick) {
+    *self.0.write() = tick;
   }
 }
 
-pub struct IntervalListener(Arc<RwLock<Tick>>, u32);
+// The interval starts counting from the first check, which is when the engine clock is known
+pub struct IntervalListener(Arc<RwLock<Option<Tick>>>, u32);
 
 impl IntervalListener {
   pub fn new(interval: u32) -> Self {
-    Self(Arc::new(RwLock::new(Tick::new())), interval)
+    Self(Arc::new(RwLock::new(None)), interval)
   }
 
-  fn update(&self) {
-    let check = self.0.read().add(self.1).cmp(&Tick::new());
-    if check == Ordering::Less || check == Ordering::Equal {
-      self.0.write().touch();
+  fn update(&self, now: &Tick) {
+    let mut guard = self.0.write();
+    let last = guard.get_or_insert(*now);
+    if self.1 == 0 {
+      *last = *now;
+      return;
+    }
+
+    // Step by whole intervals instead of restarting from now so lateness doesn't accumulate
+    let elapsed = now.delta(last).num_milliseconds();
+    let interval = i64::from(self.1);
+    if *now > *last && elapsed >= interval {
+      // Too far behind for a tick to step that much, there's no cadence left to keep anyway
+      match u32::try_from(elapsed - elapsed % interval) {
+        Ok(step) => *last += step,
+        Err(_) => *last = *now,
+      }
     }
   }
 }
 impl EventListener for IntervalListener {
-  fn check(&self, other: &Tick) -> bool {
-    self.update();
+  fn check(&self, other: &Tick, now: &Tick) -> bool {
+    self.update(now);
 
-    self.0.read().cmp(other) == Ordering::Greater
+    self.0.read().is_some_and(|last| last.cmp(other) == Ordering::Greater)
   }
 
-  fn emit(&self) {
-    self.0.write().touch();
+  fn emit(&self, tick: Tick) {
+    *self.0.write() = Some(tick);
   }
 }
 
-pub struct Event<E: EventData>(E::Item);
+pub struct Event<E: EventData>(Vec<E::Item>);
 
 impl<E: EventData> Event<E> {
-  pub fn new(data: E::Item) -> Self {
+  pub fn new(data: Vec<E::Item>) -> Self {
     Self(data)
   }
 
+  // Latest data for the event
   pub fn get_data(&self) -> E::Item {
-    self.0
+    self.0.last().cloned().unwrap()
+  }
+
+  pub fn iter(&self) -> Iter<'_, E::Item> {
+    self.0.iter()
+  }
+}
+
+// Past this, the oldest data of an event gets dropped
+const EVENT_QUEUE_CAPACITY: usize = 256;
+
+struct EventQueue<T>(VecDeque<(Tick, T)>);
+
+impl<T: Clone> EventQueue<T> {
+  fn new() -> Self {
+    Self(VecDeque::new())
+  }
+
+  fn push(&mut self, tick: Tick, item: T) {
+    if self.0.len() == EVENT_QUEUE_CAPACITY {
+      self.0.pop_front();
+    }
+    self.0.push_back((tick, item));
+  }
+
+  fn latest(&self) -> Option<T> {
+    self.0.back().map(|(_, item)| item.clone())
+  }
+
+  fn since(&self, tick: &Tick) -> Vec<T> {
+    self
+      .0
+      .iter()
+      .filter(|(t, _)| t > tick)
+      .map(|(_, item)| item.clone())
+      .collect()
   }
 }
 
 type EventListenerMap =
   HashMap<TypeId, Arc<RwLock<Box<dyn EventListener>>>, BuildHasherDefault<FxHasher>>;
-pub struct EventManager(EventListenerMap);
+type EventQueueMap = HashMap<TypeId, SyncBox, BuildHasherDefault<FxHasher>>;
+
+pub struct EventManager {
+  listeners: EventListenerMap,
+  // Data emitted alongside events, stored with the tick it was emitted at
+  queues: EventQueueMap,
+  clock: Arc<Clock>,
+}
 
 impl EventManager {
-  pub fn new() -> Self {
-    Self(HashMap::with_hasher(BuildHasherDefault::default()))
+  pub fn new(clock: Arc<Clock>) -> Self {
+    Self {
+      listeners: HashMap::with_hasher(BuildHasherDefault::default()),
+      queues: HashMap::with_hasher(BuildHasherDefault::default()),
+      clock,
+    }
+  }
+
+  pub fn clock(&self) -> &Clock {
+    &self.clock
   }
 
   pub fn register_listener<E: EventData, L: EventListener>(
     &mut self,
     listener: L,
   ) -> Result<(), EventError> {
-    match self.0.entry(TypeId::of::<E>()) {
+    match self.listeners.entry(TypeId::of::<E>()) {
       Entry::Vacant(entry) => {
         entry.insert(Arc::new(RwLock::new(Box::new(listener))));
       }
       Entry::Occupied(_) => return Err(EventError::EventAlreadyRegistered(TypeId::of::<E>())),
     }
+    self.queues.insert(
+      TypeId::of::<E>(),
+      SyncBox::new(EventQueue::<E::Item>::new()),
+    );
 
     Ok(())
   }
 
+  pub fn is_registered<E: EventData>(&self) -> bool {
+    self.listeners.contains_key(&TypeId::of::<E>())
+  }
+
   pub fn check<E: EventData>(&self, tick: &Tick) -> Result<bool, EventError> {
     let key = TypeId::of::<E>();
     Ok(
       self
-        .0
+        .listeners
         .get(&key)
         .ok_or(EventError::EventNotFound(key))?
         .read()
-        .check(tick),
+        .check(tick, &self.clock.now()),
     )
   }
 
+  // Events that can't come up with their data on their own have to go through emit_with
   pub fn emit<E: EventData>(&self) -> Result<(), EventError> {
     let key = TypeId::of::<E>();
+    let listener = self
+      .listeners
+      .get(&key)
+      .ok_or(EventError::EventNotFound(key))?;
+    let tick = self.clock.now();
+    E::get_item(&tick, self)?;
+    listener.read().emit(tick);
+    Ok(())
+  }
+
+  // Requires exclusive access so systems never observe the data without the matching listener tick
+  pub fn emit_with<E: EventData>(&mut self, item: E::Item) -> Result<(), EventError> {
+    let key = TypeId::of::<E>();
+    let tick = self.clock.now();
     self
-      .0
+      .queues
+      .get_mut(&key)
+      .and_then(|queue| queue.cast_mut::<EventQueue<E::Item>>())
+      .ok_or(EventError::EventNotFound(key))?
+      .push(tick, item);
+    self
+      .listeners
       .get(&key)
       .ok_or(EventError::EventNotFound(key))?
       .read()
-      .emit();
+      .emit(tick);
     Ok(())
   }
-}
 
-// To deal with inputs will require a proper input manager
-// + some input calculations will require deltas
-// + inputs will need to be cached alongside their ticks in order to accurately reply to threads
+  pub fn latest<E: EventData>(&self) -> Option<E::Item> {
+    self
+      .queues
+      .get(&TypeId::of::<E>())
+      .and_then(|queue| queue.cast_ref::<EventQueue<E::Item>>())
+      .and_then(|queue| queue.latest())
+  }
+
+  pub fn since<E: EventData>(&self, tick: &Tick) -> Vec<E::Item> {
+    self
+      .queues
+      .get(&TypeId::of::<E>())
+      .and_then(|queue| queue.cast_ref::<EventQueue<E::Item>>())
+      .map(|queue| queue.since(tick))
+      .unwrap_or_default()
+  }
+}
 
 // Event managers will need to sort each events in their own containers each with a different key type and store listeners
 // They will store the default and user registered events into the engine
@@ -1,7 +1,306 @@
-use super::Tick;
+use super::{InputRecording, RecordedFrame, Tick, Time};
 
-pub struct InputManager {}
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+// winit doesn't do gamepads, these get fed through P1::send_input by whatever backend reads them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+  South,
+  East,
+  North,
+  West,
+  LeftBumper,
+  RightBumper,
+  Select,
+  Start,
+  LeftStick,
+  RightStick,
+  DPadUp,
+  DPadDown,
+  DPadLeft,
+  DPadRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+  LeftStickX,
+  LeftStickY,
+  RightStickX,
+  RightStickY,
+  LeftTrigger,
+  RightTrigger,
+}
+
+// Engine-side copy of the window inputs we care about, independent of winit's event lifetimes
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+  KeyPressed(KeyCode),
+  KeyReleased(KeyCode),
+  MousePressed(MouseButton),
+  MouseReleased(MouseButton),
+  CursorMoved(f64, f64),
+  Scrolled(f64, f64),
+  // Text typed with the keyboard, one per character, key repeats included
+  // Backspace is typed as '\u{8}' so edits apply in the order they came in
+  Character(char),
+  GamepadPressed(GamepadButton),
+  GamepadReleased(GamepadButton),
+  GamepadAxisMoved(GamepadAxis, f32),
+}
+
+// Approximation of a line height for touchpads reporting scrolling in pixels
+const PIXELS_PER_LINE: f64 = 20.0;
+pub const BACKSPACE: char = '\u{8}';
+
+impl InputEvent {
+  // A key press can come with the text it typed
+  pub fn from_window_event(event: &WindowEvent) -> Vec<Self> {
+    let input = match event {
+      WindowEvent::KeyboardInput { event, .. } => {
+        let mut inputs = Vec::new();
+        if let (PhysicalKey::Code(code), false) = (event.physical_key, event.repeat) {
+          inputs.push(match event.state {
+            ElementState::Pressed => InputEvent::KeyPressed(code),
+            ElementState::Released => InputEvent::KeyReleased(code),
+          });
+        }
+        if event.state == ElementState::Pressed {
+          // Platforms don't agree on the text of a backspace
+          if event.physical_key == PhysicalKey::Code(KeyCode::Backspace) {
+            inputs.push(InputEvent::Character(BACKSPACE));
+          } else {
+            let text = event.text.as_deref().unwrap_or_default();
+            inputs.extend(text.chars().map(InputEvent::Character));
+          }
+        }
+        return inputs;
+      }
+      WindowEvent::MouseInput { state, button, .. } => Some(match state {
+        ElementState::Pressed => InputEvent::MousePressed(*button),
+        ElementState::Released => InputEvent::MouseReleased(*button),
+      }),
+      WindowEvent::CursorMoved { position, .. } => {
+        Some(InputEvent::CursorMoved(position.x, position.y))
+      }
+      WindowEvent::MouseWheel { delta, .. } => Some(match delta {
+        MouseScrollDelta::LineDelta(x, y) => InputEvent::Scrolled(*x as f64, *y as f64),
+        MouseScrollDelta::PixelDelta(position) => {
+          InputEvent::Scrolled(position.x / PIXELS_PER_LINE, position.y / PIXELS_PER_LINE)
+        }
+      }),
+      _ => None,
+    };
+    input.into_iter().collect()
+  }
+}
+
+pub struct ButtonInput<T: Copy + Eq + Hash> {
+  // Stores when each button went down
+  pressed: HashMap<T, Tick>,
+  just_pressed: HashSet<T>,
+  just_released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> ButtonInput<T> {
+  fn new() -> Self {
+    Self {
+      pressed: HashMap::new(),
+      just_pressed: HashSet::new(),
+      just_released: HashSet::new(),
+    }
+  }
+
+  fn clear(&mut self) {
+    self.just_pressed.clear();
+    self.just_released.clear();
+  }
+
+  fn press(&mut self, button: T, tick: Tick) -> bool {
+    if self.pressed.contains_key(&button) {
+      return false;
+    }
+    self.pressed.insert(button, tick);
+    self.just_pressed.insert(button);
+    true
+  }
+
+  fn release(&mut self, button: T) -> bool {
+    if self.pressed.remove(&button).is_none() {
+      return false;
+    }
+    self.just_released.insert(button);
+    true
+  }
+
+  pub fn pressed(&self, button: T) -> bool {
+    self.pressed.contains_key(&button)
+  }
+  pub fn just_pressed(&self, button: T) -> bool {
+    self.just_pressed.contains(&button)
+  }
+  pub fn just_released(&self, button: T) -> bool {
+    self.just_released.contains(&button)
+  }
+  pub fn pressed_at(&self, button: T) -> Option<Tick> {
+    self.pressed.get(&button).copied()
+  }
+  pub fn get_pressed(&self) -> impl Iterator<Item = &T> {
+    self.pressed.keys()
+  }
+}
+
+// Available to systems as a resource, just_* queries are only true for the frame the change happened in
+pub struct InputState {
+  keys: ButtonInput<KeyCode>,
+  mouse_buttons: ButtonInput<MouseButton>,
+  gamepad_buttons: ButtonInput<GamepadButton>,
+  gamepad_axes: HashMap<GamepadAxis, f32>,
+  cursor: Option<(f64, f64)>,
+  cursor_delta: (f64, f64),
+  scroll: (f64, f64),
+  // Typed this frame, backspaces included
+  text: String,
+  tick: Option<Tick>,
+}
+
+impl InputState {
+  pub fn new() -> Self {
+    Self {
+      keys: ButtonInput::new(),
+      mouse_buttons: ButtonInput::new(),
+      gamepad_buttons: ButtonInput::new(),
+      gamepad_axes: HashMap::new(),
+      cursor: None,
+      cursor_delta: (0.0, 0.0),
+      scroll: (0.0, 0.0),
+      text: String::new(),
+      tick: None,
+    }
+  }
+
+  pub fn keys(&self) -> &ButtonInput<KeyCode> {
+    &self.keys
+  }
+  pub fn mouse_buttons(&self) -> &ButtonInput<MouseButton> {
+    &self.mouse_buttons
+  }
+  pub fn gamepad_buttons(&self) -> &ButtonInput<GamepadButton> {
+    &self.gamepad_buttons
+  }
+  pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+    self.gamepad_axes.get(&axis).copied().unwrap_or(0.0)
+  }
+
+  pub fn pressed(&self, key: KeyCode) -> bool {
+    self.keys.pressed(key)
+  }
+  pub fn just_pressed(&self, key: KeyCode) -> bool {
+    self.keys.just_pressed(key)
+  }
+  pub fn just_released(&self, key: KeyCode) -> bool {
+    self.keys.just_released(key)
+  }
+
+  pub fn cursor(&self) -> Option<(f64, f64)> {
+    self.cursor
+  }
+  pub fn cursor_delta(&self) -> (f64, f64) {
+    self.cursor_delta
+  }
+  pub fn scroll(&self) -> (f64, f64) {
+    self.scroll
+  }
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  // Tick of the latest input applied
+  pub fn tick(&self) -> Option<Tick> {
+    self.tick
+  }
+
+  pub(crate) fn begin_frame(&mut self) {
+    self.keys.clear();
+    self.mouse_buttons.clear();
+    self.gamepad_buttons.clear();
+    self.cursor_delta = (0.0, 0.0);
+    self.scroll = (0.0, 0.0);
+    self.text.clear();
+  }
+
+  // Returns whether the input changed anything, pressing an already held key doesn't
+  pub(crate) fn apply(&mut self, tick: Tick, event: &InputEvent) -> bool {
+    self.tick = Some(tick);
+    match *event {
+      InputEvent::KeyPressed(key) => self.keys.press(key, tick),
+      InputEvent::KeyReleased(key) => self.keys.release(key),
+      InputEvent::MousePressed(button) => self.mouse_buttons.press(button, tick),
+      InputEvent::MouseReleased(button) => self.mouse_buttons.release(button),
+      InputEvent::CursorMoved(x, y) => {
+        if let Some((last_x, last_y)) = self.cursor {
+          self.cursor_delta.0 += x - last_x;
+          self.cursor_delta.1 += y - last_y;
+        }
+        self.cursor = Some((x, y));
+        true
+      }
+      InputEvent::Scrolled(x, y) => {
+        self.scroll.0 += x;
+        self.scroll.1 += y;
+        true
+      }
+      InputEvent::Character(character) => {
+        self.text.push(character);
+        true
+      }
+      InputEvent::GamepadPressed(button) => self.gamepad_buttons.press(button, tick),
+      InputEvent::GamepadReleased(button) => self.gamepad_buttons.release(button),
+      InputEvent::GamepadAxisMoved(axis, value) => {
+        let value = value.clamp(-1.0, 1.0);
+        self.gamepad_axes.insert(axis, value) != Some(value)
+      }
+    }
+  }
+}
+
+// Collects inputs as they come in, they are only applied once per frame by the engine
+pub(crate) struct InputManager {
+  pending: Vec<(Tick, InputEvent)>,
+  recording: Option<InputRecording>,
+}
 
 impl InputManager {
-  pub fn gl_input_callback() {}
+  pub fn new() -> Self {
+    Self {
+      pending: Vec::new(),
+      recording: None,
+    }
+  }
+
+  pub fn record(&mut self, tick: Tick, event: InputEvent) {
+    self.pending.push((tick, event));
+  }
+
+  // The frame the inputs get applied in is kept so replays land them in the same frame
+  pub fn drain(&mut self, time: &Time) -> Vec<(Tick, InputEvent)> {
+    let inputs: Vec<_> = self.pending.drain(..).collect();
+    if let Some(recording) = self.recording.as_mut() {
+      recording.push_frame(RecordedFrame::new(time, inputs.clone()));
+    }
+    inputs
+  }
+
+  pub fn start_recording(&mut self, fixed_step: TimeDelta, start: Tick) {
+    self.recording = Some(InputRecording::new(fixed_step, start));
+  }
+
+  pub fn stop_recording(&mut self) -> Option<InputRecording> {
+    self.recording.take()
+  }
 }
@@ -1,15 +1,31 @@
+mod action;
 pub mod builtin;
+mod clock;
 mod event;
 mod input;
+mod replay;
 mod tick;
+mod timer;
 
+pub use action::{ActionBindings, ActionMap, AxisBinding, Binding};
+pub use clock::{Clock, Time};
 pub use event::{Event, EventData, EventListener, EventManager, IntervalListener, SimpleListener};
 pub use tick::Tick;
+pub use timer::{Timer, TimerId, TimerMode, TimerSource};
+
+pub use input::{ButtonInput, GamepadAxis, GamepadButton, InputEvent, InputState, BACKSPACE};
+pub use replay::{InputRecording, RecordedFrame};
+
+pub(crate) use input::InputManager;
+pub(crate) use timer::TimerManager;
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::error::EventError;
+  use chrono::TimeDelta;
   use std::cmp::Ordering;
+  use std::sync::Arc;
   use std::thread::sleep;
   use std::time::Duration;
 
@@ -36,5 +52,161 @@ mod tests {
     assert!(a.cmp(&b) == Ordering::Less);
   }
 
-  // Need more operators for ticks to test with
+  #[test]
+  fn manual_clock_frames() {
+    let clock = Clock::manual();
+    clock.set_fixed_step(TimeDelta::milliseconds(10));
+
+    let first = clock.tick_frame();
+    assert_eq!(first.frame(), 1);
+    assert_eq!(first.delta(), TimeDelta::zero());
+
+    clock.advance(TimeDelta::milliseconds(25));
+    let second = clock.tick_frame();
+    assert_eq!(second.frame(), 2);
+    assert_eq!(second.delta(), TimeDelta::milliseconds(25));
+    assert_eq!(second.elapsed(), TimeDelta::milliseconds(25));
+    assert_eq!(second.fixed_steps(), 2);
+    assert!((second.alpha() - 0.5).abs() < 1e-9);
+
+    clock.advance(TimeDelta::milliseconds(5));
+    let third = clock.tick_frame();
+    assert_eq!(third.fixed_steps(), 1);
+    assert_eq!(third.alpha(), 0.0);
+    assert_eq!(clock.time(), third);
+  }
+
+  #[test]
+  fn timer_modes() {
+    let mut once = Timer::once(TimeDelta::milliseconds(10));
+    assert_eq!(once.tick(TimeDelta::milliseconds(6)), 0);
+    assert_eq!(once.tick(TimeDelta::milliseconds(6)), 1);
+    assert!(once.is_finished());
+    assert_eq!(once.tick(TimeDelta::milliseconds(20)), 0);
+
+    let mut repeating = Timer::repeating(TimeDelta::milliseconds(10));
+    assert_eq!(repeating.tick(TimeDelta::milliseconds(25)), 2);
+    assert_eq!(repeating.elapsed(), TimeDelta::milliseconds(5));
+
+    repeating.pause();
+    assert_eq!(repeating.tick(TimeDelta::milliseconds(50)), 0);
+    repeating.resume();
+    repeating.reset();
+    assert_eq!(repeating.tick(TimeDelta::milliseconds(9)), 0);
+    assert_eq!(repeating.remaining(), TimeDelta::milliseconds(1));
+  }
+
+  #[test]
+  fn zero_length_timers_repeat_once_per_tick() {
+    let mut repeating = Timer::repeating(TimeDelta::zero());
+    assert_eq!(repeating.tick(TimeDelta::milliseconds(16)), 1);
+    assert_eq!(repeating.tick(TimeDelta::zero()), 1);
+    assert_eq!(repeating.elapsed(), TimeDelta::zero());
+  }
+
+  #[test]
+  fn action_bindings() {
+    use winit::keyboard::KeyCode;
+
+    let mut actions = ActionMap::from_json(
+      r#"{
+        "actions": { "jump": [{ "Key": "Space" }, { "Gamepad": "South" }] },
+        "axes": {
+          "move_x": [
+            { "Buttons": { "negative": { "Key": "KeyA" }, "positive": { "Key": "KeyD" } } },
+            { "Gamepad": "LeftStickX" }
+          ]
+        }
+      }"#,
+    )
+    .unwrap();
+
+    let mut input = InputState::new();
+    let tick = Tick::new();
+    input.apply(tick, &InputEvent::GamepadPressed(GamepadButton::South));
+    input.apply(tick, &InputEvent::KeyPressed(KeyCode::KeyD));
+    input.apply(
+      tick,
+      &InputEvent::GamepadAxisMoved(GamepadAxis::LeftStickX, 0.5),
+    );
+
+    let (pressed, released) = actions.refresh(&input);
+    assert_eq!(pressed, vec!["jump".to_owned()]);
+    assert!(released.is_empty());
+    assert!(actions.just_pressed("jump"));
+    assert_eq!(actions.axis("move_x"), 1.0);
+
+    actions
+      .rebind(
+        "jump",
+        Binding::Gamepad(GamepadButton::South),
+        Binding::Key(KeyCode::KeyW),
+      )
+      .unwrap();
+    let (_, released) = actions.refresh(&input);
+    assert_eq!(released, vec!["jump".to_owned()]);
+    assert!(!actions.pressed("jump"));
+    assert!(actions
+      .unbind("crouch", Binding::Key(KeyCode::KeyC))
+      .is_err());
+
+    // Clearing a held action still lets listeners see it released
+    input.apply(tick, &InputEvent::KeyPressed(KeyCode::KeyW));
+    assert_eq!(actions.refresh(&input).0, vec!["jump".to_owned()]);
+    actions.clear("jump");
+    let (_, released) = actions.refresh(&input);
+    assert_eq!(released, vec!["jump".to_owned()]);
+    assert!(actions.refresh(&input).1.is_empty());
+  }
+
+  #[test]
+  fn interval_listener_keeps_cadence() {
+    let clock = Clock::manual();
+    let listener = IntervalListener::new(10);
+    let start = clock.now();
+    listener.emit(start);
+
+    clock.advance(TimeDelta::milliseconds(25));
+    assert!(listener.check(&start, &clock.now()));
+
+    // Fired at 20ms, not 25ms, so the next one is still due at 30ms
+    let seen = clock.now();
+    clock.advance(TimeDelta::milliseconds(5));
+    assert!(listener.check(&seen, &clock.now()));
+  }
+
+  #[test]
+  fn interval_listener_restarts_when_far_behind() {
+    let clock = Clock::manual();
+    let listener = IntervalListener::new(10);
+    let start = clock.now();
+    listener.emit(start);
+
+    // More milliseconds than a tick can step by at once
+    clock.advance(TimeDelta::days(60));
+    assert!(listener.check(&start, &clock.now()));
+    let seen = clock.now();
+    clock.advance(TimeDelta::milliseconds(5));
+    assert!(!listener.check(&seen, &clock.now()));
+  }
+
+  #[test]
+  fn emitting_data_events_requires_data() {
+    use winit::keyboard::KeyCode;
+
+    let mut events = EventManager::new(Arc::new(Clock::manual()));
+    events
+      .register_listener::<builtin::KeyDown, _>(SimpleListener::new())
+      .unwrap();
+    assert!(matches!(
+      events.emit::<builtin::KeyDown>(),
+      Err(EventError::CannotFetchEventData(..))
+    ));
+
+    let key = KeyCode::KeyA;
+    events
+      .emit_with::<builtin::KeyDown>(builtin::KeyDown { key })
+      .unwrap();
+    events.emit::<builtin::KeyDown>().unwrap();
+  }
 }
@@ -0,0 +1,94 @@
+use super::{InputEvent, Tick, Time};
+use crate::error::InputError;
+
+use std::fs;
+use std::path::Path;
+
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+
+// Bumped whenever the layout of recordings changes
+const RECORDING_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+  frame: u64,
+  // TimeDelta has no serde support, nanoseconds are exact enough
+  delta_nanos: i64,
+  inputs: Vec<(Tick, InputEvent)>,
+}
+
+impl RecordedFrame {
+  pub fn new(time: &Time, inputs: Vec<(Tick, InputEvent)>) -> Self {
+    Self {
+      frame: time.frame(),
+      delta_nanos: time.delta().num_nanoseconds().unwrap_or(i64::MAX),
+      inputs,
+    }
+  }
+
+  pub fn frame(&self) -> u64 {
+    self.frame
+  }
+  pub fn delta(&self) -> TimeDelta {
+    TimeDelta::nanoseconds(self.delta_nanos)
+  }
+  pub fn inputs(&self) -> &[(Tick, InputEvent)] {
+    &self.inputs
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+  version: u32,
+  fixed_step_nanos: i64,
+  // Clock time when recording started, replays line the inputs up from there
+  start: Tick,
+  frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+  pub fn new(fixed_step: TimeDelta, start: Tick) -> Self {
+    Self {
+      version: RECORDING_VERSION,
+      fixed_step_nanos: fixed_step.num_nanoseconds().unwrap_or(i64::MAX),
+      start,
+      frames: Vec::new(),
+    }
+  }
+
+  pub fn push_frame(&mut self, frame: RecordedFrame) {
+    self.frames.push(frame);
+  }
+
+  pub fn fixed_step(&self) -> TimeDelta {
+    TimeDelta::nanoseconds(self.fixed_step_nanos)
+  }
+  pub fn start(&self) -> Tick {
+    self.start
+  }
+  pub fn frames(&self) -> &[RecordedFrame] {
+    &self.frames
+  }
+
+  pub fn to_json(&self) -> Result<String, InputError> {
+    Ok(serde_json::to_string(self)?)
+  }
+
+  pub fn from_json(json: &str) -> Result<Self, InputError> {
+    let recording: Self = serde_json::from_str(json)?;
+    if recording.version != RECORDING_VERSION {
+      return Err(InputError::UnsupportedRecording(recording.version));
+    }
+    Ok(recording)
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InputError> {
+    fs::write(path, self.to_json()?)?;
+    Ok(())
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, InputError> {
+    Self::from_json(&fs::read_to_string(path)?)
+  }
+}
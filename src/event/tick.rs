@@ -1,27 +1,51 @@
 use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
-pub struct Tick(DateTime<Utc>);
+// Breaks ties between ticks taken at the same instant, which always happens under a manual clock
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+fn next_sequence() -> u64 {
+  NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Tick(DateTime<Utc>, u64);
 
 impl Tick {
   pub fn new() -> Self {
-    Self(Utc::now())
+    Self(Utc::now(), next_sequence())
+  }
+
+  // Older than any other tick, for things that never happened yet
+  pub fn origin() -> Self {
+    Self(DateTime::<Utc>::MIN_UTC, 0)
   }
 
   pub fn touch(&mut self) {
-    self.0 = Utc::now()
+    self.0 = Utc::now();
+    self.1 = next_sequence();
   }
 
   pub fn delta(&self, other: &Tick) -> TimeDelta {
     (self.0 - other.0).abs()
   }
+
+  pub fn time(&self) -> DateTime<Utc> {
+    self.0
+  }
+
+  // Keeps its order among ticks taken at the same instant
+  pub fn shifted(&self, delta: TimeDelta) -> Self {
+    Self(self.0 + delta, self.1)
+  }
 }
 
 impl From<DateTime<Utc>> for Tick {
   fn from(value: DateTime<Utc>) -> Self {
-    Self(value)
+    Self(value, next_sequence())
   }
 }
 
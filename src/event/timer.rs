@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use chrono::TimeDelta;
+use macros::Component;
+
+use crate::ecs::Component;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerMode {
+  Once,
+  Repeating,
+}
+
+// Can be used on its own, scheduled on the engine or attached to an entity
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Timer {
+  duration: TimeDelta,
+  elapsed: TimeDelta,
+  mode: TimerMode,
+  paused: bool,
+  finished: bool,
+  times_finished: u32,
+}
+
+impl Timer {
+  pub fn new(duration: TimeDelta, mode: TimerMode) -> Self {
+    Self {
+      duration: duration.max(TimeDelta::zero()),
+      elapsed: TimeDelta::zero(),
+      mode,
+      paused: false,
+      finished: false,
+      times_finished: 0,
+    }
+  }
+
+  pub fn once(duration: TimeDelta) -> Self {
+    Self::new(duration, TimerMode::Once)
+  }
+
+  pub fn repeating(duration: TimeDelta) -> Self {
+    Self::new(duration, TimerMode::Repeating)
+  }
+
+  // Returns how many times the timer expired during that delta, at most once for zero-length ones
+  pub fn tick(&mut self, delta: TimeDelta) -> u32 {
+    self.times_finished = 0;
+    if self.paused || (self.mode == TimerMode::Once && self.finished) {
+      return 0;
+    }
+
+    self.elapsed += delta;
+    if self.elapsed < self.duration {
+      return 0;
+    }
+
+    self.finished = true;
+    self.times_finished = match self.mode {
+      TimerMode::Once => {
+        self.elapsed = self.duration;
+        1
+      }
+      // It would expire endlessly otherwise
+      TimerMode::Repeating if self.duration.is_zero() => {
+        self.elapsed = TimeDelta::zero();
+        1
+      }
+      TimerMode::Repeating => {
+        let duration = self.duration.num_nanoseconds().unwrap_or(i64::MAX);
+        let elapsed = self.elapsed.num_nanoseconds().unwrap_or(i64::MAX);
+        self.elapsed = TimeDelta::nanoseconds(elapsed % duration);
+        (elapsed / duration).min(u32::MAX as i64) as u32
+      }
+    };
+    self.times_finished
+  }
+
+  pub fn pause(&mut self) {
+    self.paused = true;
+  }
+  pub fn resume(&mut self) {
+    self.paused = false;
+  }
+  pub fn reset(&mut self) {
+    self.elapsed = TimeDelta::zero();
+    self.finished = false;
+    self.times_finished = 0;
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.paused
+  }
+  // A repeating timer counts as finished once it expired at least once
+  pub fn is_finished(&self) -> bool {
+    self.finished
+  }
+  // Expirations during the last tick
+  pub fn times_finished(&self) -> u32 {
+    self.times_finished
+  }
+
+  pub fn mode(&self) -> TimerMode {
+    self.mode
+  }
+  pub fn duration(&self) -> TimeDelta {
+    self.duration
+  }
+  pub fn elapsed(&self) -> TimeDelta {
+    self.elapsed
+  }
+  pub fn remaining(&self) -> TimeDelta {
+    self.duration - self.elapsed
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerId(u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerSource {
+  Scheduled(TimerId),
+  Entity(u32),
+}
+
+// Timers scheduled on the engine itself rather than on an entity
+pub(crate) struct TimerManager {
+  timers: HashMap<TimerId, Timer>,
+  next_timer_id: u64,
+}
+
+impl TimerManager {
+  pub fn new() -> Self {
+    Self {
+      timers: HashMap::new(),
+      next_timer_id: 0,
+    }
+  }
+
+  pub fn schedule(&mut self, timer: Timer) -> TimerId {
+    let id = TimerId(self.next_timer_id);
+    self.next_timer_id += 1;
+    self.timers.insert(id, timer);
+    id
+  }
+
+  pub fn get(&self, id: TimerId) -> Option<&Timer> {
+    self.timers.get(&id)
+  }
+  pub fn get_mut(&mut self, id: TimerId) -> Option<&mut Timer> {
+    self.timers.get_mut(&id)
+  }
+
+  pub fn cancel(&mut self, id: TimerId) -> Option<Timer> {
+    self.timers.remove(&id)
+  }
+
+  // Finished one-shot timers are dropped, the returned ids are in scheduling order
+  pub fn tick(&mut self, delta: TimeDelta) -> Vec<TimerId> {
+    let mut finished = Vec::new();
+    for (id, timer) in self.timers.iter_mut() {
+      finished.extend(std::iter::repeat_n(*id, timer.tick(delta) as usize));
+    }
+    finished.sort();
+
+    self
+      .timers
+      .retain(|_, timer| timer.mode() == TimerMode::Repeating || !timer.is_finished());
+    finished
+  }
+}
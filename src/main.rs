@@ -1,8 +1,16 @@
+mod asset;
+mod debug;
 mod ecs;
 mod error;
 mod event;
 mod p1;
 mod rendering;
+mod scene;
+mod spatial;
+#[cfg(test)]
+mod testing;
+mod text;
+mod ui;
 mod utility;
 
 extern crate macros;
@@ -1,23 +1,60 @@
-use std::any::TypeId;
-use std::collections::HashSet;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
+use crate::asset::{AssetServer, Assets, Handle};
+use crate::debug::SystemTiming;
 use crate::ecs::{
-  Archetype, ArchetypeManager, Component, ComponentManager, EntityManager, Query, QueryData,
+  short_name, Archetype, ArchetypeManager, ChildOf, Component, ComponentInfo, ComponentManager,
+  ComponentRegistry, EntityManager, Query, QueryData, Relation, Res, ResMut, ResourceManager,
 };
-use crate::error::{DataError, EventError, SystemError};
-use crate::event::builtin::Update;
-use crate::event::{Event, EventData, EventListener, EventManager, IntervalListener, Tick};
+use crate::error::{DataError, EventError, InputError, P1Error, SystemError, WindowError};
+use crate::event::builtin::{
+  ActionPressed, ActionReleased, AssetFailed, AssetLoaded, AssetModified, ButtonClicked,
+  CheckboxToggled, Exit, Focused, KeyDown, KeyUp, MouseButtonDown, MouseButtonUp, MouseMove,
+  PrefabFailed, PrefabInstantiated, Resized, Resume, ScaleFactorChanged, SliderChanged,
+  TextChanged, TextSubmitted, TimerFinished, Update, WindowClosed,
+};
+use crate::event::{
+  ActionMap, Clock, Event, EventData, EventManager, InputEvent, InputManager, InputRecording,
+  InputState, SimpleListener, Time, Timer, TimerId, TimerManager, TimerSource,
+};
+use crate::rendering::{
+  Camera2d, Camera3d, ExtractedMaterial, Image, Mesh, RenderQueue, Renderer, WindowHandler,
+};
+use crate::scene::{Commands, Prefab};
+use crate::spatial::{Children, GlobalTransform, Parent, Transform};
+use crate::text::{Font, GlyphAtlas};
+use crate::utility::SyncBox;
 use chrono::TimeDelta;
+use glam::Mat4;
 use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use winit::event_loop::{ControlFlow, EventLoop};
+
+pub(crate) type ExtractMaterials = fn(&P1, &Assets<Image>) -> Vec<ExtractedMaterial>;
 
 pub struct P1 {
-  entity_manager: EntityManager,
+  pub(crate) entity_manager: EntityManager,
   archetype_manager: Arc<RwLock<ArchetypeManager>>,
-  component_manager: Arc<RwLock<ComponentManager>>,
-  event_manager: Arc<RwLock<EventManager>>,
+  pub(crate) component_manager: Arc<RwLock<ComponentManager>>,
+  pub(crate) event_manager: Arc<RwLock<EventManager>>,
+  pub(crate) resource_manager: Arc<ResourceManager>,
+  input_manager: Arc<RwLock<InputManager>>,
+  clock: Arc<Clock>,
+  timer_manager: TimerManager,
+  // Names of every component type added so far, and how to serialize the ones registered with serde
+  pub(crate) registry: ComponentRegistry,
+  // One per registered system, written by its thread after every run
+  system_timings: Arc<RwLock<Vec<SystemTiming>>>,
+  // Extraction of every registered material type
+  pub(crate) materials: HashMap<TypeId, ExtractMaterials>,
+  // 1x1 white image for flat colored quads, held so it's never collected
+  pub(crate) white: Handle<Image>,
   // Systems need to exist soon and hold the handle
   thread_handles: Vec<JoinHandle<()>>,
   is_alive: Arc<AtomicBool>,
@@ -25,18 +62,303 @@ pub struct P1 {
 
 impl P1 {
   pub fn new() -> Result<Self, EventError> {
-    let mut event_manager = EventManager::new();
-    event_manager.register_listener::<Update, _>(IntervalListener::new(0))?;
+    Self::with_clock(Clock::new())
+  }
+
+  // Use Clock::manual() for deterministic timings
+  pub fn with_clock(clock: Clock) -> Result<Self, EventError> {
+    let clock = Arc::new(clock);
+    let mut event_manager = EventManager::new(clock.clone());
+    event_manager.register_listener::<Update, _>(SimpleListener::new())?;
+    event_manager.register_listener::<TimerFinished, _>(SimpleListener::new())?;
+    event_manager.register_listener::<KeyDown, _>(SimpleListener::new())?;
+    event_manager.register_listener::<KeyUp, _>(SimpleListener::new())?;
+    event_manager.register_listener::<MouseButtonDown, _>(SimpleListener::new())?;
+    event_manager.register_listener::<MouseButtonUp, _>(SimpleListener::new())?;
+    event_manager.register_listener::<MouseMove, _>(SimpleListener::new())?;
+    event_manager.register_listener::<ActionPressed, _>(SimpleListener::new())?;
+    event_manager.register_listener::<ActionReleased, _>(SimpleListener::new())?;
+    event_manager.register_listener::<Resume, _>(SimpleListener::new())?;
+    event_manager.register_listener::<Exit, _>(SimpleListener::new())?;
+    event_manager.register_listener::<WindowClosed, _>(SimpleListener::new())?;
+    event_manager.register_listener::<Resized, _>(SimpleListener::new())?;
+    event_manager.register_listener::<Focused, _>(SimpleListener::new())?;
+    event_manager.register_listener::<ScaleFactorChanged, _>(SimpleListener::new())?;
+    event_manager.register_listener::<AssetLoaded, _>(SimpleListener::new())?;
+    event_manager.register_listener::<AssetModified, _>(SimpleListener::new())?;
+    event_manager.register_listener::<AssetFailed, _>(SimpleListener::new())?;
+    event_manager.register_listener::<ButtonClicked, _>(SimpleListener::new())?;
+    event_manager.register_listener::<CheckboxToggled, _>(SimpleListener::new())?;
+    event_manager.register_listener::<SliderChanged, _>(SimpleListener::new())?;
+    event_manager.register_listener::<TextChanged, _>(SimpleListener::new())?;
+    event_manager.register_listener::<TextSubmitted, _>(SimpleListener::new())?;
+    event_manager.register_listener::<PrefabInstantiated, _>(SimpleListener::new())?;
+    event_manager.register_listener::<PrefabFailed, _>(SimpleListener::new())?;
+
+    let resource_manager = ResourceManager::new();
+    resource_manager.insert(InputState::new());
+    resource_manager.insert(ActionMap::new());
+    resource_manager.insert(RenderQueue::new());
+    let mut images = Assets::<Image>::new();
+    let white = images.add(Image::solid(1, 1, [255, 255, 255, 255]));
+    let atlas = GlyphAtlas::new(&mut images, 256, 256);
+    resource_manager.insert(images);
+    resource_manager.insert(atlas);
+    resource_manager.insert(Assets::<Mesh>::new());
+    resource_manager.insert(Assets::<Font>::new());
+    resource_manager.insert(Assets::<Prefab>::new());
+    resource_manager.insert(Commands::new());
+    resource_manager.insert(AssetServer::new("assets"));
+
+    // Known ahead so scenes using them load before any entity has one
+    let mut registry = ComponentRegistry::new();
+    registry.register::<Transform>();
+
     Ok(P1 {
       entity_manager: EntityManager::new(),
       archetype_manager: Arc::new(RwLock::new(ArchetypeManager::new())),
       component_manager: Arc::new(RwLock::new(ComponentManager::new())),
       event_manager: Arc::new(RwLock::new(event_manager)),
+      resource_manager: Arc::new(resource_manager),
+      input_manager: Arc::new(RwLock::new(InputManager::new())),
+      clock,
+      timer_manager: TimerManager::new(),
+      registry,
+      system_timings: Arc::new(RwLock::new(Vec::new())),
+      materials: HashMap::new(),
+      white,
       thread_handles: Vec::new(),
       is_alive: Arc::new(AtomicBool::new(true)),
     })
   }
 
+  pub fn clock(&self) -> &Clock {
+    &self.clock
+  }
+
+  pub fn insert_resource<T: Send + Sync + Any>(&mut self, resource: T) {
+    self.resource_manager.insert(resource);
+  }
+
+  pub fn resource<T: Send + Sync + Any>(&self) -> Option<Res<T>> {
+    self.resource_manager.get::<T>()
+  }
+
+  pub fn resource_mut<T: Send + Sync + Any>(&self) -> Option<ResMut<T>> {
+    self.resource_manager.get_mut::<T>()
+  }
+
+  pub fn emit<E: EventData>(&mut self, item: E::Item) -> Result<(), EventError> {
+    self.event_manager.write().emit_with::<E>(item)
+  }
+
+  pub fn latest_event<E: EventData>(&self) -> Option<E::Item> {
+    self.event_manager.read().latest::<E>()
+  }
+
+  // Blocks until every window is closed, updating the engine every time winit runs out of events
+  // Opens a default window if no entity has a WindowConfig
+  // Has to be called from the main thread on most platforms
+  pub fn run_windowed(&mut self) -> Result<(), P1Error> {
+    let event_loop = EventLoop::new().map_err(WindowError::from)?;
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    let mut handler = WindowHandler::new(self);
+    event_loop
+      .run_app(&mut handler)
+      .map_err(WindowError::from)?;
+    handler.into_result()
+  }
+
+  // Inputs are buffered and only applied on the next update
+  pub fn send_input(&mut self, event: InputEvent) {
+    self.input_manager.write().record(self.clock.now(), event);
+  }
+
+  // Everything sent until stop_recording is kept, grouped by the frame it was applied in
+  pub fn start_recording(&mut self) {
+    self
+      .input_manager
+      .write()
+      .start_recording(self.clock.fixed_step(), self.clock.now());
+  }
+
+  pub fn stop_recording(&mut self) -> Option<InputRecording> {
+    self.input_manager.write().stop_recording()
+  }
+
+  // Stands in for the window, each recorded frame gets its inputs and timings back before updating
+  // Relative to the current time, the clock isn't reset and inputs are moved to start from now
+  pub fn replay(&mut self, recording: &InputRecording) -> Result<(), P1Error> {
+    if !self.clock.is_manual() {
+      return Err(InputError::ReplayNeedsManualClock.into());
+    }
+
+    self.clock.set_fixed_step(recording.fixed_step());
+    let offset = self.clock.now().time() - recording.start().time();
+    for frame in recording.frames() {
+      self.clock.advance(frame.delta());
+      {
+        let mut input_manager = self.input_manager.write();
+        for (tick, input) in frame.inputs() {
+          input_manager.record(tick.shifted(offset), *input);
+        }
+      }
+      self.update()?;
+    }
+    Ok(())
+  }
+
+  // Advances the engine by one frame, every system listening to Update gets the same timings
+  pub fn update(&mut self) -> Result<Time, P1Error> {
+    let time = self.clock.tick_frame();
+    self.process_input(&time)?;
+    self.update_actions()?;
+    self.process_assets()?;
+    self.apply_commands()?;
+    let finished = self.tick_timers(time.delta());
+    self.propagate_transforms()?;
+    self.update_cameras::<Camera2d>();
+    self.update_cameras::<Camera3d>();
+    self.update_anchors();
+    self.layout_ui()?;
+    self.update_interactions()?;
+    self.update_text()?;
+    self.update_debug_overlay(&time)?;
+
+    let mut event_manager = self.event_manager.write();
+    for source in finished {
+      event_manager.emit_with::<TimerFinished>(TimerFinished { source })?;
+    }
+    event_manager.emit_with::<Update>(time)?;
+    Ok(time)
+  }
+
+  fn process_input(&mut self, time: &Time) -> Result<(), EventError> {
+    let pending = self.input_manager.write().drain(time);
+    let mut state = self.resource_manager.get_mut::<InputState>().unwrap();
+    state.begin_frame();
+
+    let mut event_manager = self.event_manager.write();
+    for (tick, input) in pending {
+      let last_cursor = state.cursor();
+      if !state.apply(tick, &input) {
+        continue;
+      }
+
+      match input {
+        InputEvent::KeyPressed(key) => event_manager.emit_with::<KeyDown>(KeyDown { key })?,
+        InputEvent::KeyReleased(key) => event_manager.emit_with::<KeyUp>(KeyUp { key })?,
+        InputEvent::MousePressed(button) => {
+          event_manager.emit_with::<MouseButtonDown>(MouseButtonDown { button })?
+        }
+        InputEvent::MouseReleased(button) => {
+          event_manager.emit_with::<MouseButtonUp>(MouseButtonUp { button })?
+        }
+        InputEvent::CursorMoved(x, y) => {
+          let delta = last_cursor
+            .map(|(last_x, last_y)| (x - last_x, y - last_y))
+            .unwrap_or((0.0, 0.0));
+          event_manager.emit_with::<MouseMove>(MouseMove {
+            position: (x, y),
+            delta,
+          })?
+        }
+        InputEvent::Scrolled(_, _)
+        | InputEvent::Character(_)
+        | InputEvent::GamepadPressed(_)
+        | InputEvent::GamepadReleased(_)
+        | InputEvent::GamepadAxisMoved(_, _) => {}
+      }
+    }
+
+    Ok(())
+  }
+
+  fn update_actions(&mut self) -> Result<(), EventError> {
+    let (pressed, released) = match self.resource_manager.get_mut::<ActionMap>() {
+      Some(mut actions) => actions.refresh(&self.resource_manager.get::<InputState>().unwrap()),
+      None => return Ok(()),
+    };
+
+    let mut event_manager = self.event_manager.write();
+    for action in pressed {
+      event_manager.emit_with::<ActionPressed>(ActionPressed { action })?;
+    }
+    for action in released {
+      event_manager.emit_with::<ActionReleased>(ActionReleased { action })?;
+    }
+    Ok(())
+  }
+
+  fn process_assets(&mut self) -> Result<(), EventError> {
+    let Some(mut server) = self.resource_manager.get_mut::<AssetServer>() else {
+      return Ok(());
+    };
+    let changes = server.update(&self.resource_manager);
+    drop(server);
+
+    // Modified images need to reach the GPU again
+    if let Some(mut renderer) = self.resource_manager.get_mut::<Renderer>() {
+      for event in changes.modified.iter() {
+        renderer.invalidate_texture(event.id);
+      }
+    }
+
+    let mut event_manager = self.event_manager.write();
+    for event in changes.loaded {
+      event_manager.emit_with::<AssetLoaded>(event)?;
+    }
+    for event in changes.modified {
+      event_manager.emit_with::<AssetModified>(event)?;
+    }
+    for event in changes.failed {
+      event_manager.emit_with::<AssetFailed>(event)?;
+    }
+    Ok(())
+  }
+
+  fn tick_timers(&mut self, delta: TimeDelta) -> Vec<TimerSource> {
+    let mut finished: Vec<_> = self
+      .timer_manager
+      .tick(delta)
+      .into_iter()
+      .map(TimerSource::Scheduled)
+      .collect();
+
+    if let Some(mut container) = self.component_manager.read().get_container_mut::<Timer>() {
+      let mut expired: Vec<_> = container
+        .entries_mut::<Timer>()
+        .flat_map(|(entity, timer)| std::iter::repeat_n(*entity, timer.tick(delta) as usize))
+        .collect();
+      expired.sort();
+      finished.extend(expired.into_iter().map(TimerSource::Entity));
+    }
+
+    finished
+  }
+
+  pub fn schedule(&mut self, delay: TimeDelta) -> TimerId {
+    self.timer_manager.schedule(Timer::once(delay))
+  }
+
+  pub fn schedule_repeating(&mut self, interval: TimeDelta) -> TimerId {
+    self.timer_manager.schedule(Timer::repeating(interval))
+  }
+
+  pub fn timer(&self, id: TimerId) -> Option<&Timer> {
+    self.timer_manager.get(id)
+  }
+
+  // Pausing, resuming and resetting goes through here
+  pub fn timer_mut(&mut self, id: TimerId) -> Option<&mut Timer> {
+    self.timer_manager.get_mut(id)
+  }
+
+  pub fn cancel_timer(&mut self, id: TimerId) -> Option<Timer> {
+    self.timer_manager.cancel(id)
+  }
+
   pub fn create_entity(&mut self) -> u32 {
     self.entity_manager.create_entity()
   }
@@ -51,16 +373,312 @@ impl P1 {
     component: C,
   ) -> Result<(), DataError> {
     if self.has_component::<C>(entity)? {
-      return Err(DataError::ComponentExistsForEntity);
+      return Err(DataError::ComponentExistsForEntity(C::component_name()));
     }
     self.entity_manager.add_component::<C>(entity)?;
+    self.registry.register::<C>();
 
     self
       .component_manager
       .write()
       .create_container::<C>()
-      .insert(entity, component)
-      .map_err(|e| e.into())
+      .insert(entity, component)?;
+
+    let c_ids = self.entity_manager.components(entity)?;
+    self.archetype_manager.write().update_entity(entity, &c_ids);
+    Ok(())
+  }
+
+  // Same as add_component for components that were built from the registry
+  pub(crate) fn add_component_erased(
+    &mut self,
+    entity: u32,
+    info: &ComponentInfo,
+    component: SyncBox,
+  ) -> Result<(), DataError> {
+    self
+      .entity_manager
+      .add_component_id(entity, info.type_id(), info.name())?;
+    self
+      .component_manager
+      .write()
+      .create_container_from_info(info)
+      .insert_erased(entity, component)?;
+
+    let c_ids = self.entity_manager.components(entity)?;
+    self.archetype_manager.write().update_entity(entity, &c_ids);
+    Ok(())
+  }
+
+  pub(crate) fn remove_component_erased(
+    &mut self,
+    entity: u32,
+    c_id: TypeId,
+  ) -> Result<bool, DataError> {
+    if !self.entity_manager.remove_component_id(entity, c_id)? {
+      return Ok(false);
+    }
+    if let Some(mut container) = self
+      .component_manager
+      .read()
+      .get_container_mut_from_id(&c_id)
+    {
+      container.remove(&entity);
+    }
+
+    let c_ids = self.entity_manager.components(entity)?;
+    self.archetype_manager.write().update_entity(entity, &c_ids);
+    Ok(true)
+  }
+
+  // Returns whether the entity had the component
+  pub fn remove_component<C: Component>(&mut self, entity: u32) -> Result<bool, DataError> {
+    if !self.entity_manager.remove_component::<C>(entity)? {
+      return Ok(false);
+    }
+    if let Some(mut container) = self.component_manager.read().get_container_mut::<C>() {
+      container.remove(&entity);
+    }
+
+    let c_ids = self.entity_manager.components(entity)?;
+    self.archetype_manager.write().update_entity(entity, &c_ids);
+    Ok(true)
+  }
+
+  pub fn despawn(&mut self, entity: u32) -> Result<(), DataError> {
+    // Children outlive their parent and become roots
+    self.remove_parent(entity)?;
+    let children = self
+      .with_component::<Children, _>(entity, |children| children.0.clone())
+      .unwrap_or_default();
+    for child in children {
+      self.remove_component::<Parent>(child)?;
+    }
+
+    let c_ids = self.entity_manager.remove_entity(entity)?;
+    let component_manager = self.component_manager.write();
+    for c_id in c_ids.iter() {
+      if let Some(mut container) = component_manager.get_container_mut_from_id(c_id) {
+        container.remove(&entity);
+      }
+    }
+    self.archetype_manager.write().remove_entity(entity);
+    Ok(())
+  }
+
+  pub fn set_parent(&mut self, child: u32, parent: u32) -> Result<(), DataError> {
+    if !self.contains_entity(child) || !self.contains_entity(parent) {
+      return Err(DataError::EntityNotFound);
+    }
+    let mut ancestor = Some(parent);
+    while let Some(entity) = ancestor {
+      if entity == child {
+        return Err(DataError::InvalidParent);
+      }
+      ancestor = self.parent(entity);
+    }
+
+    self.remove_parent(child)?;
+    self.add_component(child, Parent(parent))?;
+    self.entity_manager.relate::<ChildOf>(child, parent)?;
+    if self
+      .with_component_mut::<Children, _>(parent, |children| children.0.push(child))
+      .is_none()
+    {
+      self.add_component(parent, Children(vec![child]))?;
+    }
+    Ok(())
+  }
+
+  // Returns the parent the entity had
+  pub fn remove_parent(&mut self, child: u32) -> Result<Option<u32>, DataError> {
+    let Some(parent) = self.parent(child) else {
+      return Ok(None);
+    };
+    self.remove_component::<Parent>(child)?;
+    self.entity_manager.unrelate::<ChildOf>(child, parent);
+
+    let empty = self
+      .with_component_mut::<Children, _>(parent, |children| {
+        children.0.retain(|entity| *entity != child);
+        children.is_empty()
+      })
+      .unwrap_or(false);
+    if empty {
+      self.remove_component::<Children>(parent)?;
+    }
+    Ok(Some(parent))
+  }
+
+  pub fn parent(&self, entity: u32) -> Option<u32> {
+    self.with_component::<Parent, _>(entity, |parent| parent.get())
+  }
+
+  // Returns the target it replaced, for exclusive relations
+  pub fn relate<R: Relation>(
+    &mut self,
+    source: u32,
+    target: u32,
+  ) -> Result<Option<u32>, DataError> {
+    if TypeId::of::<R>() == TypeId::of::<ChildOf>() {
+      let previous = self.parent(source).filter(|parent| *parent != target);
+      self.set_parent(source, target)?;
+      return Ok(previous);
+    }
+    self.entity_manager.relate::<R>(source, target)
+  }
+
+  // Returns whether the source had the relation to the target
+  pub fn unrelate<R: Relation>(&mut self, source: u32, target: u32) -> Result<bool, DataError> {
+    if TypeId::of::<R>() == TypeId::of::<ChildOf>() {
+      if self.parent(source) != Some(target) {
+        return Ok(false);
+      }
+      self.remove_parent(source)?;
+      return Ok(true);
+    }
+    Ok(self.entity_manager.unrelate::<R>(source, target))
+  }
+
+  pub fn is_related<R: Relation>(&self, source: u32, target: u32) -> bool {
+    self
+      .entity_manager
+      .relations()
+      .read()
+      .contains::<R>(source, target)
+  }
+
+  // What the source has the relation to
+  pub fn relation_targets<R: Relation>(&self, source: u32) -> Vec<u32> {
+    self
+      .entity_manager
+      .relations()
+      .read()
+      .targets::<R>(source)
+      .to_vec()
+  }
+
+  // What has the relation to the target
+  pub fn relation_sources<R: Relation>(&self, target: u32) -> Vec<u32> {
+    self
+      .entity_manager
+      .relations()
+      .read()
+      .sources::<R>(target)
+      .to_vec()
+  }
+
+  // Computes world transforms from the roots down
+  // Subtrees whose transforms didn't change are skipped
+  // Returns how many global transforms were recomputed
+  fn propagate_transforms(&mut self) -> Result<usize, DataError> {
+    let mut roots = self.entities_with::<Transform>();
+    roots.extend(self.entities_with::<Children>());
+    roots.sort();
+    roots.dedup();
+    roots.retain(|entity| self.parent(*entity).is_none());
+
+    let mut recomputed = 0;
+    let mut stack: Vec<_> = roots
+      .into_iter()
+      .rev()
+      .map(|root| (root, None, Mat4::IDENTITY, false))
+      .collect();
+    while let Some((entity, parent, parent_matrix, parent_changed)) = stack.pop() {
+      let local = self
+        .with_component::<Transform, _>(entity, |transform| *transform)
+        .unwrap_or_default();
+
+      let update = |global: &mut GlobalTransform| {
+        if parent_changed || global.is_stale(&local, parent) {
+          global.set(parent_matrix * local.compute_matrix(), local, parent);
+          (global.matrix(), true)
+        } else {
+          (global.matrix(), false)
+        }
+      };
+      let (matrix, changed) = match self.with_component_mut::<GlobalTransform, _>(entity, update) {
+        Some(result) => result,
+        None => {
+          let mut global = GlobalTransform::IDENTITY;
+          let result = update(&mut global);
+          self.add_component(entity, global)?;
+          result
+        }
+      };
+      if changed {
+        recomputed += 1;
+      }
+
+      let children = self
+        .with_component::<Children, _>(entity, |children| children.0.clone())
+        .unwrap_or_default();
+      stack.extend(
+        children
+          .into_iter()
+          .rev()
+          .map(|child| (child, Some(entity), matrix, changed)),
+      );
+    }
+
+    Ok(recomputed)
+  }
+
+  pub fn component_registry(&self) -> &ComponentRegistry {
+    &self.registry
+  }
+
+  // Lets components be found by name before any entity has one, when loading scenes for instance
+  pub fn register_component<C: Component>(&mut self) {
+    self.registry.register::<C>();
+  }
+
+  // Derived components are found to be serializable on their own
+  // This is for the ones implemented by hand
+  pub fn register_serde_component<C: Component + Serialize + DeserializeOwned>(&mut self) {
+    self.registry.register_serde::<C>();
+  }
+
+  pub fn system_timings(&self) -> Vec<SystemTiming> {
+    self.system_timings.read().clone()
+  }
+
+  pub fn entity_count(&self) -> usize {
+    self.entity_manager.len()
+  }
+
+  pub fn archetype_count(&self) -> usize {
+    self.entity_manager.archetype_count()
+  }
+
+  pub fn contains_entity(&self, entity: u32) -> bool {
+    self.entity_manager.contains(entity)
+  }
+
+  pub fn entities_with<C: Component>(&self) -> Vec<u32> {
+    let mut entities = self.entity_manager.get_archetype(&[TypeId::of::<C>()]);
+    entities.sort();
+    entities
+  }
+
+  pub fn with_component<C: Component, R>(
+    &self,
+    entity: u32,
+    f: impl FnOnce(&C) -> R,
+  ) -> Option<R> {
+    let lock = self.component_manager.read();
+    let container = lock.get_container::<C>()?;
+    container.get::<C>(&entity).map(f)
+  }
+
+  pub fn with_component_mut<C: Component, R>(
+    &self,
+    entity: u32,
+    f: impl FnOnce(&mut C) -> R,
+  ) -> Option<R> {
+    let lock = self.component_manager.read();
+    let mut container = lock.get_container_mut::<C>()?;
+    container.get_mut::<C>(&entity).map(f)
   }
 
   // Change archetypes to literally be a per system cache or if not, make sure they don't require a mutable access
@@ -87,33 +705,65 @@ impl P1 {
     let archetype_manager = self.archetype_manager.clone();
     let component_manager = self.component_manager.clone();
     let event_manager = self.event_manager.clone();
+    let resource_manager = self.resource_manager.clone();
+    let relations = self.entity_manager.relations().clone();
+    let clock = self.clock.clone();
     let state = self.is_alive.clone();
-    let mut tick = Tick::new();
+    let mut tick = clock.now();
+
+    let timings = self.system_timings.clone();
+    let index = {
+      let mut timings = timings.write();
+      timings.push(SystemTiming::new(format!(
+        "{} on {}",
+        short_name(std::any::type_name::<Q>()),
+        short_name(std::any::type_name::<E>())
+      )));
+      timings.len() - 1
+    };
 
     let t = thread::spawn(move || {
-      // Need event ticks soon
       while state.load(Ordering::Relaxed) {
-        let check = event_manager.read().check::<E>(&tick);
-        if check.is_err() {
-          panic!("{}", check.unwrap_err())
-        }
+        // The read lock keeps emissions out while the tick and the data are taken
+        let (now, items) = {
+          let events = event_manager.read();
+          let now = clock.now();
+          match events.check::<E>(&tick) {
+            Ok(true) => {}
+            Ok(false) => {
+              thread::yield_now();
+              continue;
+            }
+            Err(e) => panic!("{}", e),
+          }
+          match E::get_items(&tick, &events) {
+            Ok(items) => (now, items),
+            // Fired without any data to hand over, the run is skipped instead of panicking
+            Err(_) => {
+              tick = now;
+              continue;
+            }
+          }
+        };
 
-        if check.unwrap() {
-          let lock = component_manager.read();
-          let mut components: Vec<_> = archetype_manager
-            .read()
-            .get(archetype_id)
-            .unwrap()
-            .entities()
-            .iter()
-            .map(|entity| Q::fetch(&lock, entity).unwrap())
-            .collect();
-          (callback)(
-            Query::<Q>::new(&mut components),
-            Event::new(E::get_item(&tick)),
-          );
-          tick.touch();
-        }
+        let lock = component_manager.read();
+        let entities = archetype_manager
+          .read()
+          .get(archetype_id)
+          .unwrap()
+          .entities()
+          .clone();
+        let mut components: Vec<_> = entities
+          .iter()
+          .map(|entity| Q::fetch(&lock, entity).unwrap())
+          .collect();
+        let started = Instant::now();
+        (callback)(
+          Query::<Q>::new(&mut components, &entities, &resource_manager, &relations),
+          Event::new(items),
+        );
+        timings.write()[index].record(started.elapsed());
+        tick = now;
       }
     });
 
@@ -134,11 +784,39 @@ impl Drop for P1 {
 
 #[cfg(test)]
 mod tests {
+  use std::any::TypeId;
+  use std::sync::atomic::{AtomicU32, Ordering};
+  use std::sync::Mutex;
+  use std::thread::sleep;
+  use std::time::Duration;
+
   use super::{Component, Query, P1};
   use crate::{
-    event::{builtin::Update, Event, SimpleListener},
+    ecs::{ChildOf, OwnedBy, Targets},
+    error::DataError,
+    event::{
+      builtin::{KeyDown, TimerFinished, Update},
+      Clock, Event, InputEvent, InputRecording, InputState, SimpleListener, Time, Timer,
+      TimerSource,
+    },
     macros::Component,
+    spatial::{Children, GlobalTransform, Transform},
   };
+  use chrono::TimeDelta;
+  use glam::Vec3;
+  use serde::{Deserialize, Serialize};
+  use winit::keyboard::KeyCode;
+
+  // Systems run on their own threads, this waits for them to catch up
+  fn wait_for(condition: impl Fn() -> bool) {
+    for _ in 0..500 {
+      if condition() {
+        return;
+      }
+      sleep(Duration::from_millis(10));
+    }
+    panic!("Timed out waiting for systems.");
+  }
 
   #[test]
   fn entity_creation() {
@@ -172,7 +850,7 @@ mod tests {
 
   #[test]
   #[should_panic(
-    expected = "Cannot attach component to entity because a component of that type is already attached."
+    expected = "Cannot attach TestComponentA to the entity because it already has one."
   )]
   fn assigning_preexisting_component() {
     let mut engine = P1::new().unwrap();
@@ -183,6 +861,79 @@ mod tests {
     }
   }
 
+  #[derive(Component, Debug, Default, PartialEq, Serialize, Deserialize)]
+  struct Health {
+    current: u32,
+    max: u32,
+  }
+
+  #[test]
+  fn derived_components_fill_the_registry() {
+    let mut engine = P1::new().unwrap();
+    let entity = engine.create_entity();
+    engine
+      .add_component(entity, Health { current: 3, max: 5 })
+      .unwrap();
+    engine.add_component(entity, TestComponentA()).unwrap();
+    let registry = engine.component_registry();
+
+    let health = registry.get(TypeId::of::<Health>()).unwrap();
+    assert_eq!(health.name(), "Health");
+    assert_eq!(health.size(), std::mem::size_of::<Health>());
+    assert_eq!(health.align(), std::mem::align_of::<Health>());
+    assert!(health.is_serializable() && health.has_debug() && health.has_default());
+    assert_eq!(
+      health.debug(&Health { current: 1, max: 2 }).unwrap(),
+      "Health { current: 1, max: 2 }"
+    );
+    let default = health.default_value().unwrap();
+    assert_eq!(default.cast_ref::<Health>(), Some(&Health::default()));
+    let value = (health.serde().unwrap().serialize)(&Health { current: 1, max: 2 }).unwrap();
+    let read = (health.serde().unwrap().deserialize)(value).unwrap();
+    assert_eq!(read.cast_ref::<Health>(), Some(&Health { current: 1, max: 2 }));
+
+    // Nothing but the name and layout for types without the traits
+    let marker = registry.by_name("TestComponentA").unwrap().unwrap();
+    assert_eq!(marker.type_id(), TypeId::of::<TestComponentA>());
+    assert!(!marker.is_serializable() && !marker.has_debug() && !marker.has_default());
+  }
+
+  mod other {
+    use crate::ecs::Component;
+    use crate::macros::Component;
+
+    #[derive(Component)]
+    pub struct Health;
+  }
+
+  struct Wrapper<T>(T);
+  impl<T: Send + Sync + 'static> Component for Wrapper<T> {}
+
+  #[test]
+  fn component_names_can_be_shared() {
+    let mut engine = P1::new().unwrap();
+    let entity = engine.create_entity();
+    engine
+      .add_component(entity, Health { current: 3, max: 5 })
+      .unwrap();
+    engine.add_component(entity, other::Health).unwrap();
+    assert!(engine.has_component::<other::Health>(entity).unwrap());
+    assert!(matches!(
+      engine.component_registry().by_name("Health"),
+      Err(DataError::DuplicateComponentName("Health"))
+    ));
+
+    // Generics are part of the name, so every instance of a type can be found
+    assert_eq!(Wrapper::<u32>::component_name(), "Wrapper<u32>");
+    engine.add_component(entity, Wrapper(1u32)).unwrap();
+    engine.add_component(entity, Wrapper(2u8)).unwrap();
+    let registry = engine.component_registry();
+    let wrapper = registry.by_name("Wrapper<u32>").unwrap().unwrap();
+    assert_eq!(wrapper.type_id(), TypeId::of::<Wrapper<u32>>());
+    let wrapper = registry.by_name("Wrapper<u8>").unwrap().unwrap();
+    assert_eq!(wrapper.type_id(), TypeId::of::<Wrapper<u8>>());
+  }
+
   #[test]
   #[should_panic(expected = "No entities with the provided id was found.")]
   fn non_existant_entity() {
@@ -204,4 +955,313 @@ mod tests {
       panic!("{}", e);
     }
   }
+
+  #[test]
+  fn update_timings() {
+    static FRAMES: Mutex<Vec<Time>> = Mutex::new(Vec::new());
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.clock().set_fixed_step(TimeDelta::milliseconds(10));
+
+    let system = |_: Query<()>, event: Event<Update>| {
+      FRAMES.lock().unwrap().extend(event.iter());
+    };
+    engine.register_system(system).unwrap();
+
+    let first = engine.update().unwrap();
+    engine.clock().advance(TimeDelta::milliseconds(15));
+    let second = engine.update().unwrap();
+
+    wait_for(|| FRAMES.lock().unwrap().len() >= 2);
+    assert_eq!(*FRAMES.lock().unwrap(), vec![first, second]);
+    assert_eq!(second.frame(), 2);
+    assert_eq!(second.delta(), TimeDelta::milliseconds(15));
+    assert!((second.alpha() - 0.5).abs() < 1e-9);
+  }
+
+  #[test]
+  fn timers_emit_on_expiry() {
+    static FINISHED: Mutex<Vec<TimerSource>> = Mutex::new(Vec::new());
+
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let entity = engine.create_entity();
+    engine
+      .add_component(entity, Timer::repeating(TimeDelta::milliseconds(20)))
+      .unwrap();
+    let scheduled = engine.schedule(TimeDelta::milliseconds(30));
+
+    let system = |_: Query<()>, event: Event<TimerFinished>| {
+      FINISHED
+        .lock()
+        .unwrap()
+        .extend(event.iter().map(|finished| finished.source));
+    };
+    engine.register_system(system).unwrap();
+
+    engine.update().unwrap();
+    for _ in 0..2 {
+      engine.clock().advance(TimeDelta::milliseconds(20));
+      engine.update().unwrap();
+    }
+
+    wait_for(|| FINISHED.lock().unwrap().len() >= 3);
+    assert_eq!(
+      *FINISHED.lock().unwrap(),
+      vec![
+        TimerSource::Entity(entity),
+        TimerSource::Scheduled(scheduled),
+        TimerSource::Entity(entity),
+      ]
+    );
+    // One-shot timers are dropped once they expire
+    assert!(engine.timer(scheduled).is_none());
+  }
+
+  #[test]
+  fn input_state_and_events() {
+    static KEYS: Mutex<Vec<(KeyCode, bool)>> = Mutex::new(Vec::new());
+
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let system = |query: Query<()>, event: Event<KeyDown>| {
+      let has_input = query.resource::<InputState>().is_some();
+      KEYS
+        .lock()
+        .unwrap()
+        .extend(event.iter().map(|down| (down.key, has_input)));
+    };
+    engine.register_system(system).unwrap();
+
+    engine.send_input(InputEvent::KeyPressed(KeyCode::KeyA));
+    engine.send_input(InputEvent::KeyPressed(KeyCode::KeyA));
+    engine.send_input(InputEvent::CursorMoved(10.0, 10.0));
+    engine.send_input(InputEvent::CursorMoved(15.0, 12.0));
+    engine.update().unwrap();
+    {
+      let input = engine.resource::<InputState>().unwrap();
+      assert!(input.pressed(KeyCode::KeyA));
+      assert!(input.just_pressed(KeyCode::KeyA));
+      assert_eq!(input.cursor(), Some((15.0, 12.0)));
+      assert_eq!(input.cursor_delta(), (5.0, 2.0));
+    }
+
+    engine.update().unwrap();
+    assert!(!engine.resource::<InputState>().unwrap().just_pressed(KeyCode::KeyA));
+
+    engine.send_input(InputEvent::KeyReleased(KeyCode::KeyA));
+    engine.update().unwrap();
+    {
+      let input = engine.resource::<InputState>().unwrap();
+      assert!(!input.pressed(KeyCode::KeyA));
+      assert!(input.just_released(KeyCode::KeyA));
+    }
+
+    wait_for(|| !KEYS.lock().unwrap().is_empty());
+    assert_eq!(*KEYS.lock().unwrap(), vec![(KeyCode::KeyA, true)]);
+  }
+
+  #[test]
+  fn input_replay() {
+    let mut recorded = P1::with_clock(Clock::manual()).unwrap();
+    recorded.start_recording();
+    recorded.update().unwrap();
+    recorded.send_input(InputEvent::KeyPressed(KeyCode::Space));
+    recorded.clock().advance(TimeDelta::milliseconds(16));
+    recorded.update().unwrap();
+    recorded.send_input(InputEvent::CursorMoved(4.0, 2.0));
+    recorded.send_input(InputEvent::KeyPressed(KeyCode::KeyW));
+    recorded.clock().advance(TimeDelta::milliseconds(17));
+    let last = recorded.update().unwrap();
+    let recording = recorded.stop_recording().unwrap();
+    assert_eq!(recording.frames().len(), 3);
+
+    let recording = InputRecording::from_json(&recording.to_json().unwrap()).unwrap();
+    let mut replayed = P1::with_clock(Clock::manual()).unwrap();
+    replayed.replay(&recording).unwrap();
+
+    assert_eq!(replayed.clock().time(), last);
+    let (expected, actual) = (
+      recorded.resource::<InputState>().unwrap(),
+      replayed.resource::<InputState>().unwrap(),
+    );
+    for key in [KeyCode::Space, KeyCode::KeyW] {
+      assert_eq!(expected.pressed(key), actual.pressed(key));
+      assert_eq!(expected.just_pressed(key), actual.just_pressed(key));
+      assert_eq!(expected.keys().pressed_at(key), actual.keys().pressed_at(key));
+    }
+    assert_eq!(expected.cursor(), actual.cursor());
+
+    // An engine that already ran gets the inputs as long after now as they were recorded
+    let mut later = P1::with_clock(Clock::manual()).unwrap();
+    later.clock().advance(TimeDelta::seconds(5));
+    later.update().unwrap();
+    later.replay(&recording).unwrap();
+    let pressed_at = |engine: &P1| {
+      let input = engine.resource::<InputState>().unwrap();
+      input.keys().pressed_at(KeyCode::KeyW).unwrap().time()
+    };
+    assert_eq!(
+      pressed_at(&later) - pressed_at(&recorded),
+      TimeDelta::seconds(5)
+    );
+
+    assert!(P1::new().unwrap().replay(&recording).is_err());
+  }
+
+  #[test]
+  fn archetypes_follow_component_changes() {
+    static SEEN: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let system = |query: Query<&TestComponentA>, _: Event<Update>| {
+      SEEN.lock().unwrap().push(query.iter().count());
+    };
+    engine.register_system(system).unwrap();
+
+    // Entities created after the system registration still need to show up
+    let entity = engine.create_entity();
+    engine.add_component(entity, TestComponentA {}).unwrap();
+    engine.update().unwrap();
+    wait_for(|| SEEN.lock().unwrap().last() == Some(&1));
+
+    engine.despawn(entity).unwrap();
+    assert!(!engine.contains_entity(entity));
+    engine.update().unwrap();
+    wait_for(|| SEEN.lock().unwrap().last() == Some(&0));
+  }
+
+  #[test]
+  fn transforms_propagate_down_hierarchies() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let root = engine.create_entity();
+    let child = engine.create_entity();
+    let grandchild = engine.create_entity();
+    let other = engine.create_entity();
+    engine
+      .add_component(root, Transform::from_xyz(10.0, 0.0, 0.0))
+      .unwrap();
+    engine
+      .add_component(
+        child,
+        Transform::from_xyz(0.0, 5.0, 0.0).with_scale(Vec3::splat(2.0)),
+      )
+      .unwrap();
+    engine
+      .add_component(grandchild, Transform::from_xyz(1.0, 0.0, 0.0))
+      .unwrap();
+    engine.add_component(other, Transform::IDENTITY).unwrap();
+    engine.set_parent(child, root).unwrap();
+    engine.set_parent(grandchild, child).unwrap();
+    assert!(engine.set_parent(root, grandchild).is_err());
+
+    let global = |engine: &P1, entity| {
+      engine
+        .with_component::<GlobalTransform, _>(entity, |global| global.translation())
+        .unwrap()
+    };
+    assert_eq!(engine.propagate_transforms().unwrap(), 4);
+    assert_eq!(global(&engine, grandchild), Vec3::new(12.0, 5.0, 0.0));
+    assert_eq!(engine.propagate_transforms().unwrap(), 0);
+
+    // Only the moved subtree is recomputed
+    engine.with_component_mut::<Transform, _>(child, |transform| transform.translation.y = 1.0);
+    assert_eq!(engine.propagate_transforms().unwrap(), 2);
+    assert_eq!(global(&engine, grandchild), Vec3::new(12.0, 1.0, 0.0));
+
+    engine.remove_parent(child).unwrap();
+    assert!(!engine.has_component::<Children>(root).unwrap());
+    assert_eq!(engine.propagate_transforms().unwrap(), 2);
+    assert_eq!(global(&engine, grandchild), Vec3::new(2.0, 1.0, 0.0));
+
+    engine.set_parent(grandchild, root).unwrap();
+    engine.despawn(root).unwrap();
+    assert_eq!(engine.parent(grandchild), None);
+    engine.propagate_transforms().unwrap();
+    assert_eq!(global(&engine, grandchild), Vec3::new(1.0, 0.0, 0.0));
+  }
+
+  #[test]
+  fn relations_link_entities_both_ways() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let [turret, tower, enemy, boss, player] = [(); 5].map(|_| engine.create_entity());
+
+    engine.relate::<Targets>(turret, enemy).unwrap();
+    engine.relate::<Targets>(turret, boss).unwrap();
+    engine.relate::<Targets>(tower, boss).unwrap();
+    assert_eq!(engine.relation_targets::<Targets>(turret), vec![enemy, boss]);
+    assert_eq!(engine.relation_sources::<Targets>(boss), vec![turret, tower]);
+    assert!(engine.is_related::<Targets>(tower, boss));
+    assert!(!engine.is_related::<Targets>(boss, tower));
+    // Relation types don't see each other
+    assert!(engine.relation_targets::<OwnedBy>(turret).is_empty());
+
+    // Exclusive relations replace their target
+    assert_eq!(engine.relate::<OwnedBy>(turret, player).unwrap(), None);
+    assert_eq!(engine.relate::<OwnedBy>(turret, tower).unwrap(), Some(player));
+    assert_eq!(engine.relation_targets::<OwnedBy>(turret), vec![tower]);
+    assert!(engine.relation_sources::<OwnedBy>(player).is_empty());
+
+    // ChildOf is the hierarchy
+    engine.relate::<ChildOf>(turret, tower).unwrap();
+    assert_eq!(engine.parent(turret), Some(tower));
+    let child = engine.create_entity();
+    engine.set_parent(child, tower).unwrap();
+    assert_eq!(engine.relation_sources::<ChildOf>(tower), vec![turret, child]);
+    assert!(engine.unrelate::<ChildOf>(child, tower).unwrap());
+    assert_eq!(engine.parent(child), None);
+    assert!(!engine.unrelate::<Targets>(child, tower).unwrap());
+
+    // Despawning either end removes the relation
+    engine.despawn(boss).unwrap();
+    assert_eq!(engine.relation_targets::<Targets>(turret), vec![enemy]);
+    assert!(engine.relation_targets::<Targets>(tower).is_empty());
+    engine.despawn(tower).unwrap();
+    assert!(engine.relation_targets::<OwnedBy>(turret).is_empty());
+    assert!(engine.relation_targets::<ChildOf>(turret).is_empty());
+    assert!(matches!(
+      engine.relate::<Targets>(turret, boss),
+      Err(DataError::EntityNotFound)
+    ));
+  }
+
+  #[derive(Component)]
+  struct Score(u32);
+
+  #[test]
+  fn queries_filter_by_relation() {
+    static TARGET: AtomicU32 = AtomicU32::new(u32::MAX);
+    static SEEN: Mutex<Vec<(Vec<u32>, Vec<u32>)>> = Mutex::new(Vec::new());
+
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let system = |query: Query<&Score>, _: Event<Update>| {
+      let target = TARGET.load(Ordering::Relaxed);
+      let mut targeting: Vec<u32> = query
+        .related_to::<Targets>(target)
+        .map(|score| score.0)
+        .collect();
+      targeting.sort();
+      let targeted = query
+        .related_from::<Targets>(target)
+        .map(|score| score.0)
+        .collect();
+      SEEN.lock().unwrap().push((targeting, targeted));
+    };
+    engine.register_system(system).unwrap();
+
+    let entities = [(); 4].map(|_| engine.create_entity());
+    for (index, entity) in entities.iter().enumerate() {
+      engine.add_component(*entity, Score(index as u32)).unwrap();
+    }
+    engine.relate::<Targets>(entities[1], entities[0]).unwrap();
+    engine.relate::<Targets>(entities[2], entities[0]).unwrap();
+    engine.relate::<Targets>(entities[0], entities[3]).unwrap();
+    TARGET.store(entities[0], Ordering::Relaxed);
+    engine.update().unwrap();
+
+    wait_for(|| !SEEN.lock().unwrap().is_empty());
+    assert_eq!(SEEN.lock().unwrap()[0], (vec![1, 2], vec![3]));
+
+    // Despawned sources drop out of the filter
+    engine.despawn(entities[1]).unwrap();
+    engine.update().unwrap();
+    wait_for(|| SEEN.lock().unwrap().last() == Some(&(vec![2], vec![3])));
+  }
 }
@@ -0,0 +1,244 @@
+use super::RenderTarget;
+
+use std::ops::{Deref, DerefMut};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use macros::Component;
+
+use crate::ecs::Component;
+
+// Fractions of the target size, so it follows resizes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+  pub x: f32,
+  pub y: f32,
+  pub width: f32,
+  pub height: f32,
+}
+
+impl Viewport {
+  pub const FULL: Self = Self {
+    x: 0.0,
+    y: 0.0,
+    width: 1.0,
+    height: 1.0,
+  };
+
+  pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+    Self {
+      x,
+      y,
+      width,
+      height,
+    }
+  }
+
+  // Physical rectangle (x, y, width, height) within a target of the given size
+  pub fn rect(&self, size: (u32, u32)) -> (f32, f32, f32, f32) {
+    let (width, height) = (size.0 as f32, size.1 as f32);
+    (
+      self.x.clamp(0.0, 1.0) * width,
+      self.y.clamp(0.0, 1.0) * height,
+      self.width.clamp(0.0, 1.0 - self.x.clamp(0.0, 1.0)) * width,
+      self.height.clamp(0.0, 1.0 - self.y.clamp(0.0, 1.0)) * height,
+    )
+  }
+}
+
+impl Default for Viewport {
+  fn default() -> Self {
+    Self::FULL
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+  // World units per physical pixel, centered on the camera
+  Orthographic { scale: f32, near: f32, far: f32 },
+  // Vertical field of view in radians
+  Perspective { fov_y: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+  pub fn matrix(&self, width: f32, height: f32) -> Mat4 {
+    match *self {
+      Projection::Orthographic { scale, near, far } => {
+        let (half_width, half_height) = (width * scale / 2.0, height * scale / 2.0);
+        Mat4::orthographic_rh(
+          -half_width,
+          half_width,
+          -half_height,
+          half_height,
+          near,
+          far,
+        )
+      }
+      Projection::Perspective { fov_y, near, far } => {
+        Mat4::perspective_rh(fov_y, width / height.max(1.0), near, far)
+      }
+    }
+  }
+}
+
+// Shared by both camera components, the matrices are filled in by the engine every frame
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+  pub target: RenderTarget,
+  pub viewport: Viewport,
+  // Cameras of the same target render in ascending order
+  pub order: i32,
+  pub projection: Projection,
+  pub active: bool,
+  view: Mat4,
+  projection_matrix: Mat4,
+}
+
+impl Camera {
+  pub fn new(target: RenderTarget, projection: Projection) -> Self {
+    Self {
+      target,
+      viewport: Viewport::FULL,
+      order: 0,
+      projection,
+      active: true,
+      view: Mat4::IDENTITY,
+      projection_matrix: Mat4::IDENTITY,
+    }
+  }
+
+  pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+    self.viewport = viewport;
+    self
+  }
+  pub fn with_order(mut self, order: i32) -> Self {
+    self.order = order;
+    self
+  }
+
+  pub fn view(&self) -> Mat4 {
+    self.view
+  }
+  pub fn projection_matrix(&self) -> Mat4 {
+    self.projection_matrix
+  }
+  pub fn view_projection(&self) -> Mat4 {
+    self.projection_matrix * self.view
+  }
+
+  pub(crate) fn update(&mut self, transform: Mat4, target_size: (u32, u32)) {
+    let (_, _, width, height) = self.viewport.rect(target_size);
+    self.view = transform.inverse();
+    // A minimized window has no area, the last projection is kept until it comes back
+    if width > 0.0 && height > 0.0 {
+      self.projection_matrix = self.projection.matrix(width, height);
+    }
+  }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Camera2d(pub Camera);
+
+impl Camera2d {
+  pub fn new(target: RenderTarget) -> Self {
+    Self(Camera::new(
+      target,
+      Projection::Orthographic {
+        scale: 1.0,
+        near: -1000.0,
+        far: 1000.0,
+      },
+    ))
+  }
+}
+
+impl Deref for Camera2d {
+  type Target = Camera;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+impl DerefMut for Camera2d {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.0
+  }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Camera3d(pub Camera);
+
+impl Camera3d {
+  pub fn new(target: RenderTarget) -> Self {
+    Self(Camera::new(
+      target,
+      Projection::Perspective {
+        fov_y: std::f32::consts::FRAC_PI_4,
+        near: 0.1,
+        far: 1000.0,
+      },
+    ))
+  }
+}
+
+impl Deref for Camera3d {
+  type Target = Camera;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+impl DerefMut for Camera3d {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.0
+  }
+}
+
+// Layout of the uniform buffer bound at group 0 for every camera
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CameraUniform {
+  pub view_projection: [[f32; 4]; 4],
+  pub view: [[f32; 4]; 4],
+  pub projection: [[f32; 4]; 4],
+  // w is unused, kept for alignment
+  pub position: [f32; 4],
+}
+
+// What the renderer knows of a camera for the frame it is drawing
+#[derive(Clone, Debug)]
+pub struct CameraView {
+  pub entity: u32,
+  pub order: i32,
+  // Physical pixels
+  pub viewport: (f32, f32, f32, f32),
+  pub view: Mat4,
+  pub projection: Mat4,
+  pub position: Vec3,
+  // None on the null backend
+  pub bind_group: Option<wgpu::BindGroup>,
+}
+
+impl CameraView {
+  pub fn view_projection(&self) -> Mat4 {
+    self.projection * self.view
+  }
+
+  pub fn uniform(&self) -> CameraUniform {
+    CameraUniform {
+      view_projection: self.view_projection().to_cols_array_2d(),
+      view: self.view.to_cols_array_2d(),
+      projection: self.projection.to_cols_array_2d(),
+      position: self.position.extend(1.0).to_array(),
+    }
+  }
+
+  // Restricts a pass to the camera's viewport and binds its uniforms at the given group
+  pub fn apply(&self, pass: &mut wgpu::RenderPass, group: u32) {
+    let (x, y, width, height) = self.viewport;
+    pass.set_viewport(x, y, width, height, 0.0, 1.0);
+    if let Some(bind_group) = self.bind_group.as_ref() {
+      pass.set_bind_group(group, bind_group, &[]);
+    }
+  }
+}
@@ -0,0 +1,199 @@
+use std::any::TypeId;
+use std::ops::DerefMut;
+
+use super::{
+  Camera, Camera2d, Camera3d, CameraView, ExtractedMaterial, ExtractedSprite, Geometry, Image,
+  Material, MaterialDescriptor, Mesh, RenderMode, RenderQueue, RenderTarget, Renderer, Sprite,
+  SpriteInstance, WindowState,
+};
+use crate::asset::{Assets, Handle};
+use crate::ecs::Component;
+use crate::error::RenderError;
+use crate::p1::P1;
+use crate::spatial::GlobalTransform;
+
+use glam::Mat4;
+
+// What gets drawn, pulled out of the components every frame
+impl P1 {
+  // For rendering without windows
+  // The offscreen target is then rendered with RenderTarget::Offscreen
+  pub fn enable_headless_rendering(&mut self, width: u32, height: u32) -> Result<(), RenderError> {
+    self.insert_resource(Renderer::new(RenderMode::Headless { width, height })?);
+    Ok(())
+  }
+
+  // Draws everything submitted to the RenderQueue for that target, does nothing without a renderer
+  pub fn render(&mut self, target: RenderTarget) -> Result<(), RenderError> {
+    let Some(mut renderer) = self.resource_manager.get_mut::<Renderer>() else {
+      return Ok(());
+    };
+    let size = renderer.target_size(target).unwrap_or((0, 0));
+    let mut cameras = self.camera_views::<Camera2d>(target, size);
+    cameras.extend(self.camera_views::<Camera3d>(target, size));
+
+    let images = self.resource_manager.get::<Assets<Image>>().unwrap();
+    let meshes = self.resource_manager.get::<Assets<Mesh>>().unwrap();
+    let ui = self.extract_ui(target, size, &images);
+    let (sprites, materials) = if cameras.is_empty() {
+      (Vec::new(), Vec::new())
+    } else {
+      let materials = self
+        .materials
+        .values()
+        .flat_map(|extract| extract(self, &images))
+        .collect();
+      (self.extract_sprites(&images), materials)
+    };
+
+    let mut queue = self.resource_manager.get_mut::<RenderQueue>().unwrap();
+    renderer.render(
+      target, &mut queue, cameras, sprites, materials, ui, &images, &meshes,
+    )
+  }
+
+  // Lets M be drawn in place of the Sprite, or for the Handle<Mesh>, of the entities it's on
+  pub fn register_material<M: Material>(&mut self) {
+    self
+      .materials
+      .insert(TypeId::of::<M>(), Self::extract_materials::<M>);
+  }
+
+  fn extract_materials<M: Material>(&self, images: &Assets<Image>) -> Vec<ExtractedMaterial> {
+    let descriptor = MaterialDescriptor::of::<M>();
+    self
+      .entities_with::<M>()
+      .into_iter()
+      .filter_map(|entity| {
+        let transform = self
+          .with_component::<GlobalTransform, _>(entity, |transform| transform.matrix())
+          .unwrap_or(Mat4::IDENTITY);
+        let (uniform, textures) = self.with_component::<M, _>(entity, |material| {
+          (
+            bytemuck::bytes_of(&material.uniform()).to_vec(),
+            material
+              .textures()
+              .iter()
+              .map(|texture| texture.id())
+              .collect(),
+          )
+        })?;
+
+        let (layer, geometry, instance) = match self
+          .with_component::<Sprite, _>(entity, |sprite| sprite.extract(transform, images))
+        {
+          // The sprite's texture has to be loaded, it sizes the quad
+          Some(sprite) => {
+            let sprite = sprite?;
+            (sprite.layer, Geometry::Sprite, sprite.instance)
+          }
+          None => {
+            let mesh = self.with_component::<Handle<Mesh>, _>(entity, |mesh| mesh.id())?;
+            let instance = SpriteInstance::new(transform, wgpu::Color::WHITE);
+            (0, Geometry::Mesh(mesh), instance)
+          }
+        };
+        Some(ExtractedMaterial {
+          descriptor,
+          entity,
+          layer,
+          geometry,
+          instance,
+          uniform,
+          textures,
+        })
+      })
+      .collect()
+  }
+
+  fn extract_sprites(&self, images: &Assets<Image>) -> Vec<ExtractedSprite> {
+    let mut sprites = self.extract_text();
+    sprites.extend(self.extract_sprite_components(images));
+    sprites
+  }
+
+  fn extract_sprite_components(&self, images: &Assets<Image>) -> Vec<ExtractedSprite> {
+    self
+      .entities_with::<Sprite>()
+      .into_iter()
+      .filter(|entity| {
+        // Drawn by their material instead
+        self
+          .entity_manager
+          .components(*entity)
+          .is_ok_and(|components| !components.iter().any(|id| self.materials.contains_key(id)))
+      })
+      .filter_map(|entity| {
+        let transform = self
+          .with_component::<GlobalTransform, _>(entity, |transform| transform.matrix())
+          .unwrap_or(Mat4::IDENTITY);
+        self
+          .with_component::<Sprite, _>(entity, |sprite| sprite.extract(transform, images))
+          .flatten()
+      })
+      .collect()
+  }
+
+  // Draws a frame into the offscreen target of a headless renderer and reads it back
+  pub fn render_to_image(&mut self) -> Result<Image, RenderError> {
+    self.render(RenderTarget::Offscreen)?;
+    self
+      .resource_manager
+      .get::<Renderer>()
+      .ok_or(RenderError::NoOffscreenTarget)?
+      .read_offscreen()
+  }
+
+  fn camera_views<C: Component + DerefMut<Target = Camera>>(
+    &self,
+    target: RenderTarget,
+    size: (u32, u32),
+  ) -> Vec<CameraView> {
+    self
+      .entities_with::<C>()
+      .into_iter()
+      .filter_map(|entity| {
+        self
+          .with_component::<C, _>(entity, |camera| {
+            (camera.active && camera.target == target).then(|| CameraView {
+              entity,
+              order: camera.order,
+              viewport: camera.viewport.rect(size),
+              view: camera.view(),
+              projection: camera.projection_matrix(),
+              position: camera.view().inverse().w_axis.truncate(),
+              bind_group: None,
+            })
+          })
+          .flatten()
+      })
+      .collect()
+  }
+
+  fn target_size(&self, target: RenderTarget) -> Option<(u32, u32)> {
+    match target {
+      RenderTarget::Window(entity) => {
+        self.with_component::<WindowState, _>(entity, |state| (state.width, state.height))
+      }
+      RenderTarget::Offscreen => self
+        .resource::<Renderer>()
+        .and_then(|renderer| renderer.offscreen_size()),
+    }
+  }
+
+  // Cameras without a Transform sit at the origin
+  pub(crate) fn update_cameras<C: Component + DerefMut<Target = Camera>>(&mut self) {
+    for entity in self.entities_with::<C>() {
+      let transform = self
+        .with_component::<GlobalTransform, _>(entity, |transform| transform.matrix())
+        .unwrap_or(Mat4::IDENTITY);
+      let Some(target) = self.with_component::<C, _>(entity, |camera| camera.target) else {
+        continue;
+      };
+      let Some(size) = self.target_size(target) else {
+        continue;
+      };
+      self.with_component_mut::<C, _>(entity, |camera| camera.update(transform, size));
+    }
+  }
+}
@@ -0,0 +1,127 @@
+use super::Image;
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+// Software adapters don't all rasterize edges and blend the same, goldens allow for some drift
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+  // Largest difference allowed on any channel before a pixel counts as different
+  pub channel: u8,
+  // Fraction of the pixels allowed to be different
+  pub pixels: f64,
+}
+
+impl Tolerance {
+  pub const EXACT: Self = Self {
+    channel: 0,
+    pixels: 0.0,
+  };
+}
+
+impl Default for Tolerance {
+  fn default() -> Self {
+    Self {
+      channel: 2,
+      pixels: 0.001,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageDiff {
+  pub different_pixels: usize,
+  pub total_pixels: usize,
+  pub max_difference: u8,
+}
+
+impl ImageDiff {
+  pub fn within(&self, tolerance: Tolerance) -> bool {
+    self.different_pixels as f64 <= self.total_pixels as f64 * tolerance.pixels
+  }
+}
+
+// None when the sizes don't match
+pub fn compare_images(
+  actual: &Image,
+  expected: &Image,
+  channel_tolerance: u8,
+) -> Option<ImageDiff> {
+  if actual.size() != expected.size() {
+    return None;
+  }
+
+  let mut diff = ImageDiff {
+    different_pixels: 0,
+    total_pixels: (actual.width * actual.height) as usize,
+    max_difference: 0,
+  };
+  for (a, e) in actual
+    .data
+    .chunks_exact(4)
+    .zip(expected.data.chunks_exact(4))
+  {
+    let difference = a.iter().zip(e).map(|(a, e)| a.abs_diff(*e)).max().unwrap();
+    diff.max_difference = diff.max_difference.max(difference);
+    if difference > channel_tolerance {
+      diff.different_pixels += 1;
+    }
+  }
+  Some(diff)
+}
+
+// Where golden images are kept, relative to the crate root
+pub const GOLDEN_DIR: &str = "tests/golden";
+
+pub fn golden_path(name: &str) -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join(GOLDEN_DIR)
+    .join(format!("{name}.png"))
+}
+
+// Compares against tests/golden/<name>.png, panicking with the details when it differs
+// Goldens are only ever written with P1_UPDATE_GOLDEN=1, a missing one fails like a different one
+// A failing image is saved next to its golden as <name>.actual.png
+pub fn assert_golden(name: &str, actual: &Image, tolerance: Tolerance) {
+  let path = golden_path(name);
+  if env::var("P1_UPDATE_GOLDEN").is_ok_and(|value| value == "1") {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    actual.save_png(&path).unwrap();
+    return;
+  }
+  if !path.exists() {
+    let actual_path = path.with_extension("actual.png");
+    actual.save_png(&actual_path).unwrap();
+    panic!(
+      "{name} has no golden at {}, see {} and run with P1_UPDATE_GOLDEN=1 to accept it",
+      path.display(),
+      actual_path.display()
+    );
+  }
+
+  let expected = Image::from_png(&std::fs::read(&path).unwrap()).unwrap();
+  let actual_path = path.with_extension("actual.png");
+  match compare_images(actual, &expected, tolerance.channel) {
+    Some(diff) if diff.within(tolerance) => {
+      let _ = std::fs::remove_file(actual_path);
+    }
+    diff => {
+      actual.save_png(&actual_path).unwrap();
+      match diff {
+        Some(diff) => panic!(
+          "{name} differs from its golden on {} of {} pixels (up to {}), see {}",
+          diff.different_pixels,
+          diff.total_pixels,
+          diff.max_difference,
+          actual_path.display()
+        ),
+        None => panic!(
+          "{name} is {:?} but its golden is {:?}, see {}",
+          actual.size(),
+          expected.size(),
+          actual_path.display()
+        ),
+      }
+    }
+  }
+}
@@ -0,0 +1,89 @@
+use crate::error::AssetError;
+
+use std::fs;
+use std::path::Path;
+
+// CPU side pixels of a texture, tightly packed RGBA8 in sRGB, first row at the top
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+  pub width: u32,
+  pub height: u32,
+  pub data: Vec<u8>,
+}
+
+impl Image {
+  // None when the data doesn't match the size
+  pub fn new(width: u32, height: u32, data: Vec<u8>) -> Option<Self> {
+    (data.len() == width as usize * height as usize * 4).then_some(Self {
+      width,
+      height,
+      data,
+    })
+  }
+
+  pub fn solid(width: u32, height: u32, color: [u8; 4]) -> Self {
+    Self {
+      width,
+      height,
+      data: color.repeat(width as usize * height as usize),
+    }
+  }
+
+  pub fn size(&self) -> (u32, u32) {
+    (self.width, self.height)
+  }
+
+  pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+    if x >= self.width || y >= self.height {
+      return None;
+    }
+    let start = (y as usize * self.width as usize + x as usize) * 4;
+    self.data[start..start + 4].try_into().ok()
+  }
+
+  pub fn from_png(bytes: &[u8]) -> Result<Self, AssetError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    let data = match info.color_type {
+      png::ColorType::Rgba => buffer,
+      png::ColorType::Rgb => buffer
+        .chunks_exact(3)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect(),
+      png::ColorType::GrayscaleAlpha => buffer
+        .chunks_exact(2)
+        .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+        .collect(),
+      png::ColorType::Grayscale => buffer
+        .iter()
+        .flat_map(|value| [*value, *value, *value, 255])
+        .collect(),
+      // Expanded to RGB by the transformations
+      png::ColorType::Indexed => unreachable!(),
+    };
+    Self::new(info.width, info.height, data)
+      .ok_or_else(|| AssetError::Custom("The image data doesn't match its size.".to_owned()))
+  }
+
+  pub fn to_png(&self) -> Result<Vec<u8>, AssetError> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+      .write_header()
+      .and_then(|mut writer| writer.write_image_data(&self.data))
+      .map_err(|error| AssetError::Custom(error.to_string()))?;
+    Ok(bytes)
+  }
+
+  pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), AssetError> {
+    fs::write(path, self.to_png()?)?;
+    Ok(())
+  }
+}
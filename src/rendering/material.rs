@@ -0,0 +1,512 @@
+use super::{Image, Mesh, MeshVertex, SpriteInstance, SpriteRenderer};
+use crate::asset::{Assets, Handle};
+use crate::ecs::Component;
+
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use bytemuck::Pod;
+
+const PRELUDE: &str = include_str!("material.wgsl");
+
+// How the geometry of a draw reaches the vertex shader
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VertexLayout {
+  // Instanced unit quad placed by a Sprite, drawn with vs_sprite
+  Sprite,
+  // Mesh vertices plus the instance, drawn with vs_mesh
+  Mesh,
+}
+
+// Replaces the default look of the Sprite or Handle<Mesh> on the same entity
+// Registered with P1::register_material, which decides which entities it draws
+//
+// The shader only has to provide fs_main taking the VertexOutput of the prelude (material.wgsl)
+// Group 0 is the camera, group 1 belongs to the material:
+//   binding 0 is the uniform when it isn't (),
+//   the textures come next, followed by a filtering sampler when there are any
+pub trait Material: Component + Clone {
+  type Uniform: Pod;
+
+  fn shader() -> &'static str;
+
+  // Has to be the same for every instance, the bind group layout depends on it
+  fn texture_count() -> u32 {
+    0
+  }
+
+  fn blend() -> Option<wgpu::BlendState> {
+    Some(wgpu::BlendState::ALPHA_BLENDING)
+  }
+
+  fn uniform(&self) -> Self::Uniform;
+
+  fn textures(&self) -> Vec<Handle<Image>> {
+    Vec::new()
+  }
+}
+
+// What the renderer keeps of a material type, so it never needs the type itself
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MaterialDescriptor {
+  pub type_id: TypeId,
+  pub name: &'static str,
+  pub shader: &'static str,
+  pub uniform_size: u64,
+  pub texture_count: u32,
+  pub blend: Option<wgpu::BlendState>,
+}
+
+impl MaterialDescriptor {
+  pub fn of<M: Material>() -> Self {
+    Self {
+      type_id: TypeId::of::<M>(),
+      name: std::any::type_name::<M>(),
+      shader: M::shader(),
+      uniform_size: std::mem::size_of::<M::Uniform>() as u64,
+      texture_count: M::texture_count(),
+      blend: M::blend(),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Geometry {
+  Sprite,
+  // Id of the Mesh asset
+  Mesh(u64),
+}
+
+impl Geometry {
+  fn layout(&self) -> VertexLayout {
+    match self {
+      Geometry::Sprite => VertexLayout::Sprite,
+      Geometry::Mesh(_) => VertexLayout::Mesh,
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ExtractedMaterial {
+  pub descriptor: MaterialDescriptor,
+  pub entity: u32,
+  pub layer: i32,
+  pub geometry: Geometry,
+  pub instance: SpriteInstance,
+  pub uniform: Vec<u8>,
+  pub textures: Vec<u64>,
+}
+
+// Pipelines are built once for every combination of these
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct PipelineKey {
+  pub material: TypeId,
+  pub vertex: VertexLayout,
+  pub format: wgpu::TextureFormat,
+}
+
+struct MaterialType {
+  shader: wgpu::ShaderModule,
+  layout: wgpu::BindGroupLayout,
+  pipeline_layout: wgpu::PipelineLayout,
+}
+
+struct MaterialBinding {
+  uniform: Option<wgpu::Buffer>,
+  textures: Vec<u64>,
+  bind_group: wgpu::BindGroup,
+}
+
+struct GpuMesh {
+  vertices: wgpu::Buffer,
+  indices: wgpu::Buffer,
+  count: u32,
+}
+
+// One draw per entity, they can't be merged since every entity has its own bind group
+pub(crate) struct MaterialDraw {
+  key: PipelineKey,
+  binding: (TypeId, u32),
+  geometry: Geometry,
+  instance: u32,
+  pub layer: i32,
+}
+
+pub(crate) struct MaterialRenderer {
+  camera_layout: wgpu::BindGroupLayout,
+  sampler: wgpu::Sampler,
+  types: HashMap<TypeId, MaterialType>,
+  pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+  // By material type and entity
+  bindings: HashMap<(TypeId, u32), MaterialBinding>,
+  meshes: HashMap<u64, GpuMesh>,
+  instances: Option<wgpu::Buffer>,
+}
+
+impl MaterialRenderer {
+  pub fn new(device: &wgpu::Device, camera_layout: &wgpu::BindGroupLayout) -> Self {
+    Self {
+      camera_layout: camera_layout.clone(),
+      sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("p1 material sampler"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+      }),
+      types: HashMap::new(),
+      pipelines: HashMap::new(),
+      bindings: HashMap::new(),
+      meshes: HashMap::new(),
+      instances: None,
+    }
+  }
+
+  pub fn pipeline_count(&self) -> usize {
+    self.pipelines.len()
+  }
+
+  fn material_type(
+    &mut self,
+    device: &wgpu::Device,
+    descriptor: &MaterialDescriptor,
+  ) -> &MaterialType {
+    self.types.entry(descriptor.type_id).or_insert_with(|| {
+      let mut entries = Vec::new();
+      if descriptor.uniform_size > 0 {
+        entries.push(wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+          },
+          count: None,
+        });
+      }
+      for binding in 1..=descriptor.texture_count {
+        entries.push(wgpu::BindGroupLayoutEntry {
+          binding,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+          },
+          count: None,
+        });
+      }
+      if descriptor.texture_count > 0 {
+        entries.push(wgpu::BindGroupLayoutEntry {
+          binding: descriptor.texture_count + 1,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+          count: None,
+        });
+      }
+
+      let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(descriptor.name),
+        entries: &entries,
+      });
+      let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(descriptor.name),
+        bind_group_layouts: &[&self.camera_layout, &layout],
+        push_constant_ranges: &[],
+      });
+      let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(descriptor.name),
+        source: wgpu::ShaderSource::Wgsl(format!("{PRELUDE}\n{}", descriptor.shader).into()),
+      });
+      MaterialType {
+        shader,
+        layout,
+        pipeline_layout,
+      }
+    })
+  }
+
+  fn pipeline(&mut self, device: &wgpu::Device, descriptor: &MaterialDescriptor, key: PipelineKey) {
+    if self.pipelines.contains_key(&key) {
+      return;
+    }
+    let material = self.material_type(device, descriptor);
+
+    let (entry_point, buffers, primitive) = match key.vertex {
+      VertexLayout::Sprite => (
+        "vs_sprite",
+        vec![SpriteInstance::layout()],
+        wgpu::PrimitiveState {
+          topology: wgpu::PrimitiveTopology::TriangleStrip,
+          ..Default::default()
+        },
+      ),
+      // There is no depth buffer, culling keeps convex meshes looking right
+      VertexLayout::Mesh => (
+        "vs_mesh",
+        vec![MeshVertex::layout(), SpriteInstance::layout()],
+        wgpu::PrimitiveState {
+          topology: wgpu::PrimitiveTopology::TriangleList,
+          cull_mode: Some(wgpu::Face::Back),
+          ..Default::default()
+        },
+      ),
+    };
+
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+      label: Some(descriptor.name),
+      layout: Some(&material.pipeline_layout),
+      vertex: wgpu::VertexState {
+        module: &material.shader,
+        entry_point: Some(entry_point),
+        compilation_options: Default::default(),
+        buffers: &buffers,
+      },
+      fragment: Some(wgpu::FragmentState {
+        module: &material.shader,
+        entry_point: Some("fs_main"),
+        compilation_options: Default::default(),
+        targets: &[Some(wgpu::ColorTargetState {
+          format: key.format,
+          blend: descriptor.blend,
+          write_mask: wgpu::ColorWrites::ALL,
+        })],
+      }),
+      primitive,
+      depth_stencil: None,
+      multisample: wgpu::MultisampleState::default(),
+      multiview: None,
+      cache: None,
+    });
+    self.pipelines.insert(key, pipeline);
+  }
+
+  // Returns whether the bind group could be made, it can't while a texture isn't loaded
+  fn bind(
+    &mut self,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    draw: &ExtractedMaterial,
+    textures: &mut SpriteRenderer,
+    images: &Assets<Image>,
+  ) -> bool {
+    let key = (draw.descriptor.type_id, draw.entity);
+    if let Some(binding) = self.bindings.get(&key) {
+      if binding.textures == draw.textures {
+        if let Some(buffer) = binding.uniform.as_ref() {
+          queue.write_buffer(buffer, 0, &draw.uniform);
+        }
+        return true;
+      }
+    }
+
+    let mut views = Vec::new();
+    for id in draw.textures.iter() {
+      match textures.texture_view(device, queue, *id, images) {
+        Some(view) => views.push(view.clone()),
+        None => return false,
+      }
+    }
+    self.material_type(device, &draw.descriptor);
+    let uniform = (draw.descriptor.uniform_size > 0).then(|| {
+      let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(draw.descriptor.name),
+        size: draw.descriptor.uniform_size,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+      });
+      queue.write_buffer(&buffer, 0, &draw.uniform);
+      buffer
+    });
+
+    let mut entries = Vec::new();
+    if let Some(buffer) = uniform.as_ref() {
+      entries.push(wgpu::BindGroupEntry {
+        binding: 0,
+        resource: buffer.as_entire_binding(),
+      });
+    }
+    for (binding, view) in (1..).zip(views.iter()) {
+      entries.push(wgpu::BindGroupEntry {
+        binding,
+        resource: wgpu::BindingResource::TextureView(view),
+      });
+    }
+    if !views.is_empty() {
+      entries.push(wgpu::BindGroupEntry {
+        binding: views.len() as u32 + 1,
+        resource: wgpu::BindingResource::Sampler(&self.sampler),
+      });
+    }
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some(draw.descriptor.name),
+      layout: &self.types[&draw.descriptor.type_id].layout,
+      entries: &entries,
+    });
+    self.bindings.insert(
+      key,
+      MaterialBinding {
+        uniform,
+        textures: draw.textures.clone(),
+        bind_group,
+      },
+    );
+    true
+  }
+
+  fn upload_mesh(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, id: u64, mesh: &Mesh) {
+    if self.meshes.contains_key(&id) {
+      return;
+    }
+    let buffer = |label, contents: &[u8], usage| {
+      let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        // Copies have to be a multiple of 4 bytes
+        size: (contents.len() as u64).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+      });
+      queue.write_buffer(&buffer, 0, contents);
+      buffer
+    };
+    self.meshes.insert(
+      id,
+      GpuMesh {
+        vertices: buffer(
+          "p1 mesh vertices",
+          bytemuck::cast_slice(&mesh.vertices),
+          wgpu::BufferUsages::VERTEX,
+        ),
+        indices: buffer(
+          "p1 mesh indices",
+          bytemuck::cast_slice(&mesh.indices),
+          wgpu::BufferUsages::INDEX,
+        ),
+        count: mesh.indices.len() as u32,
+      },
+    );
+  }
+
+  // Drops what was made from an asset, it's made again the next time it's drawn
+  pub fn invalidate(&mut self, id: u64) {
+    self.meshes.remove(&id);
+    self
+      .bindings
+      .retain(|_, binding| !binding.textures.contains(&id));
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  pub fn prepare(
+    &mut self,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+    draws: &mut [ExtractedMaterial],
+    textures: &mut SpriteRenderer,
+    images: &Assets<Image>,
+    meshes: &Assets<Mesh>,
+  ) -> Vec<MaterialDraw> {
+    // Entities that lost their material don't need their bindings anymore
+    self.bindings.retain(|(type_id, entity), _| {
+      draws
+        .iter()
+        .any(|draw| draw.descriptor.type_id == *type_id && draw.entity == *entity)
+    });
+    self.meshes.retain(|id, _| meshes.get_by_id(*id).is_some());
+    if draws.is_empty() {
+      return Vec::new();
+    }
+
+    // Sorted so draws of the same pipeline follow each other
+    draws.sort_by_key(|draw| {
+      (
+        draw.layer,
+        draw.descriptor.type_id,
+        draw.geometry.layout() == VertexLayout::Mesh,
+      )
+    });
+
+    let mut prepared = Vec::new();
+    let mut instances = Vec::new();
+    for draw in draws.iter() {
+      if let Geometry::Mesh(id) = draw.geometry {
+        let Some(mesh) = meshes.get_by_id(id) else {
+          continue;
+        };
+        self.upload_mesh(device, queue, id, mesh);
+      }
+      if !self.bind(device, queue, draw, textures, images) {
+        continue;
+      }
+
+      let key = PipelineKey {
+        material: draw.descriptor.type_id,
+        vertex: draw.geometry.layout(),
+        format,
+      };
+      self.pipeline(device, &draw.descriptor, key);
+      prepared.push(MaterialDraw {
+        key,
+        binding: (draw.descriptor.type_id, draw.entity),
+        geometry: draw.geometry,
+        instance: instances.len() as u32,
+        layer: draw.layer,
+      });
+      instances.push(draw.instance);
+    }
+    if instances.is_empty() {
+      return prepared;
+    }
+
+    let bytes: &[u8] = bytemuck::cast_slice(&instances);
+    if self
+      .instances
+      .as_ref()
+      .is_none_or(|buffer| buffer.size() < bytes.len() as u64)
+    {
+      self.instances = Some(device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("p1 material instances"),
+        size: (bytes.len() as u64).next_power_of_two(),
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+      }));
+    }
+    queue.write_buffer(self.instances.as_ref().unwrap(), 0, bytes);
+    prepared
+  }
+
+  // Records draws into a pass the camera was already applied to
+  pub fn draw(&self, pass: &mut wgpu::RenderPass, draws: &[MaterialDraw]) {
+    let Some(instances) = self.instances.as_ref() else {
+      return;
+    };
+
+    for draw in draws {
+      let (Some(pipeline), Some(binding)) = (
+        self.pipelines.get(&draw.key),
+        self.bindings.get(&draw.binding),
+      ) else {
+        continue;
+      };
+      pass.set_pipeline(pipeline);
+      pass.set_bind_group(1, &binding.bind_group, &[]);
+      let instance = draw.instance..draw.instance + 1;
+      match draw.geometry {
+        Geometry::Sprite => {
+          pass.set_vertex_buffer(0, instances.slice(..));
+          pass.draw(0..4, instance);
+        }
+        Geometry::Mesh(id) => {
+          let Some(mesh) = self.meshes.get(&id) else {
+            continue;
+          };
+          pass.set_vertex_buffer(0, mesh.vertices.slice(..));
+          pass.set_vertex_buffer(1, instances.slice(..));
+          pass.set_index_buffer(mesh.indices.slice(..), wgpu::IndexFormat::Uint32);
+          pass.draw_indexed(0..mesh.count, 0, instance);
+        }
+      }
+    }
+  }
+}
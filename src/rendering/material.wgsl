@@ -0,0 +1,64 @@
+// Prepended to the source of every material
+
+struct Camera {
+  view_projection: mat4x4<f32>,
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  position: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct Instance {
+  @location(0) model_0: vec4<f32>,
+  @location(1) model_1: vec4<f32>,
+  @location(2) model_2: vec4<f32>,
+  @location(3) model_3: vec4<f32>,
+  @location(4) uv_rect: vec4<f32>,
+  @location(5) color: vec4<f32>,
+};
+
+struct MeshVertex {
+  @location(6) position: vec3<f32>,
+  @location(7) normal: vec3<f32>,
+  @location(8) uv: vec2<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+  @location(1) color: vec4<f32>,
+  @location(2) world_position: vec3<f32>,
+  @location(3) world_normal: vec3<f32>,
+};
+
+fn instance_model(instance: Instance) -> mat4x4<f32> {
+  return mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+}
+
+@vertex
+fn vs_sprite(@builtin(vertex_index) index: u32, instance: Instance) -> VertexOutput {
+  let corner = vec2<f32>(f32(index & 1u), f32((index >> 1u) & 1u));
+  let world = instance_model(instance) * vec4<f32>(corner, 0.0, 1.0);
+
+  var out: VertexOutput;
+  out.position = camera.view_projection * world;
+  out.uv = mix(instance.uv_rect.xy, instance.uv_rect.zw, vec2<f32>(corner.x, 1.0 - corner.y));
+  out.color = instance.color;
+  out.world_position = world.xyz;
+  out.world_normal = normalize((instance_model(instance) * vec4<f32>(0.0, 0.0, 1.0, 0.0)).xyz);
+  return out;
+}
+
+@vertex
+fn vs_mesh(vertex: MeshVertex, instance: Instance) -> VertexOutput {
+  let world = instance_model(instance) * vec4<f32>(vertex.position, 1.0);
+
+  var out: VertexOutput;
+  out.position = camera.view_projection * world;
+  out.uv = vertex.uv;
+  out.color = instance.color;
+  out.world_position = world.xyz;
+  out.world_normal = normalize((instance_model(instance) * vec4<f32>(vertex.normal, 0.0)).xyz);
+  return out;
+}
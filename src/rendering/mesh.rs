@@ -0,0 +1,59 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec2;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct MeshVertex {
+  pub position: [f32; 3],
+  pub normal: [f32; 3],
+  pub uv: [f32; 2],
+}
+
+impl MeshVertex {
+  // Instances take the locations before these
+  const ATTRIBUTES: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+    6 => Float32x3,
+    7 => Float32x3,
+    8 => Float32x2,
+  ];
+
+  pub(crate) fn layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+      array_stride: std::mem::size_of::<Self>() as u64,
+      step_mode: wgpu::VertexStepMode::Vertex,
+      attributes: &Self::ATTRIBUTES,
+    }
+  }
+}
+
+// Indexed triangle list, only drawn through a Material on the same entity
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh {
+  pub vertices: Vec<MeshVertex>,
+  pub indices: Vec<u32>,
+}
+
+impl Mesh {
+  pub fn new(vertices: Vec<MeshVertex>, indices: Vec<u32>) -> Self {
+    Self { vertices, indices }
+  }
+
+  // Facing +Z, centered on the origin
+  pub fn quad(size: Vec2) -> Self {
+    let half = size / 2.0;
+    let vertex = |x: f32, y: f32, u: f32, v: f32| MeshVertex {
+      position: [x, y, 0.0],
+      normal: [0.0, 0.0, 1.0],
+      uv: [u, v],
+    };
+    Self::new(
+      vec![
+        vertex(-half.x, -half.y, 0.0, 1.0),
+        vertex(half.x, -half.y, 1.0, 1.0),
+        vertex(half.x, half.y, 1.0, 0.0),
+        vertex(-half.x, half.y, 0.0, 0.0),
+      ],
+      vec![0, 1, 2, 0, 2, 3],
+    )
+  }
+}
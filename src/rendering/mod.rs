@@ -1 +1,541 @@
+mod camera;
+mod engine;
+mod golden;
+mod image;
+mod material;
+mod mesh;
+mod renderer;
+mod sprite;
+mod window;
 mod window_manager;
+
+pub use camera::{Camera, Camera2d, Camera3d, CameraUniform, CameraView, Projection, Viewport};
+pub use golden::{assert_golden, compare_images, golden_path, ImageDiff, Tolerance};
+pub use image::Image;
+pub use material::{Material, VertexLayout};
+pub use mesh::{Mesh, MeshVertex};
+pub use renderer::{
+  RenderCommand, RenderContext, RenderMode, RenderQueue, RenderTarget, Renderer, OFFSCREEN_FORMAT,
+};
+pub use sprite::{Rect, Sprite, SpriteAnchor};
+pub use window::{FullscreenMode, WindowConfig, WindowState};
+
+pub(crate) use material::{
+  ExtractedMaterial, Geometry, MaterialDescriptor, MaterialDraw, MaterialRenderer,
+};
+pub(crate) use renderer::UiFrame;
+pub(crate) use sprite::{
+  batch_sprites, ExtractedSprite, SpriteBatch, SpriteInstance, SpriteRenderer,
+};
+pub(crate) use window_manager::WindowHandler;
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    assert_golden, batch_sprites, compare_images, Camera2d, Camera3d, Image, Material, Mesh,
+    Projection, RenderQueue, RenderTarget, Renderer, Sprite, SpriteAnchor, Tolerance, Viewport,
+    WindowConfig, WindowHandler, WindowState,
+  };
+  use crate::asset::Assets;
+  use crate::ecs::Component;
+  use crate::event::builtin::{Exit, Focused, Resized, WindowClosed};
+  use crate::event::Clock;
+  use crate::p1::P1;
+  use crate::spatial::Transform;
+  use crate::testing::gpu_available;
+
+  use std::sync::atomic::{AtomicBool, Ordering};
+  use std::sync::{Arc, Mutex};
+
+  use glam::{Mat4, Vec2, Vec3};
+
+  use macros::Component;
+  use winit::dpi::PhysicalSize;
+  use winit::event::WindowEvent;
+  use winit::window::WindowId;
+
+  #[test]
+  fn window_events_route_to_entities() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let (first_id, second_id) = (WindowId::from(1), WindowId::from(2));
+    let first = engine.create_entity();
+    let second = engine.create_entity();
+    for (entity, id) in [(first, first_id), (second, second_id)] {
+      engine
+        .add_component(entity, WindowConfig::default())
+        .unwrap();
+      engine
+        .add_component(entity, WindowState::new(id, 1280, 720, 1.0))
+        .unwrap();
+    }
+
+    {
+      let mut handler = WindowHandler::new(&mut engine);
+      handler.track(first_id, first);
+      handler.track(second_id, second);
+
+      let resized = WindowEvent::Resized(PhysicalSize::new(800, 600));
+      assert!(!handler.handle_window_event(second_id, &resized).unwrap());
+      assert!(!handler
+        .handle_window_event(first_id, &WindowEvent::Focused(true))
+        .unwrap());
+      assert!(!handler
+        .handle_window_event(first_id, &WindowEvent::CloseRequested)
+        .unwrap());
+    }
+
+    let resized = engine.latest_event::<Resized>().unwrap();
+    assert_eq!(
+      (resized.window, resized.width, resized.height),
+      (second, 800, 600)
+    );
+    assert_eq!(
+      engine.with_component::<WindowState, _>(second, |state| (state.width, state.height)),
+      Some((800, 600))
+    );
+    assert_eq!(engine.latest_event::<Focused>().unwrap().window, first);
+    assert_eq!(engine.latest_event::<WindowClosed>().unwrap().window, first);
+    assert!(!engine.contains_entity(first));
+    assert!(engine.latest_event::<Exit>().is_none());
+
+    let mut handler = WindowHandler::new(&mut engine);
+    handler.track(second_id, second);
+    assert!(handler
+      .handle_window_event(second_id, &WindowEvent::CloseRequested)
+      .unwrap());
+    assert!(engine.latest_event::<Exit>().is_some());
+  }
+
+  #[test]
+  fn headless_render_drains_queue() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(64, 32).unwrap();
+
+    let ran = Arc::new(AtomicBool::new(false));
+    let flag = ran.clone();
+    {
+      let mut queue = engine.resource_mut::<RenderQueue>().unwrap();
+      queue.submit(RenderTarget::Offscreen, move |context| {
+        assert_eq!(context.size, (64, 32));
+        flag.store(true, Ordering::Relaxed);
+      });
+      queue.submit(RenderTarget::Window(0), |_| {});
+    }
+    engine.render(RenderTarget::Offscreen).unwrap();
+
+    let renderer = engine.resource::<Renderer>().unwrap();
+    assert_eq!(renderer.frames(), 1);
+    // The null backend has no device to hand to commands, so they are dropped instead
+    assert_eq!(ran.load(Ordering::Relaxed), !renderer.is_null());
+    assert_eq!(engine.resource::<RenderQueue>().unwrap().len(), 1);
+  }
+
+  #[test]
+  fn cameras_follow_transforms() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(800, 600).unwrap();
+    let window = engine.create_entity();
+    engine
+      .add_component(window, WindowState::new(WindowId::from(1), 400, 400, 1.0))
+      .unwrap();
+
+    let flat = engine.create_entity();
+    engine
+      .add_component(flat, Camera2d::new(RenderTarget::Offscreen))
+      .unwrap();
+    engine
+      .add_component(flat, Transform::from_xyz(100.0, 50.0, 0.0))
+      .unwrap();
+
+    let deep = engine.create_entity();
+    let mut camera = Camera3d::new(RenderTarget::Offscreen);
+    camera.viewport = Viewport::new(0.0, 0.0, 0.5, 1.0);
+    camera.order = -1;
+    engine.add_component(deep, camera).unwrap();
+    engine
+      .add_component(
+        deep,
+        Transform::from_xyz(0.0, 0.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+      )
+      .unwrap();
+
+    let windowed = engine.create_entity();
+    engine
+      .add_component(windowed, Camera2d::new(RenderTarget::Window(window)))
+      .unwrap();
+
+    engine.update().unwrap();
+
+    let clip = engine
+      .with_component::<Camera2d, _>(flat, |camera| {
+        camera
+          .view_projection()
+          .project_point3(Vec3::new(500.0, 50.0, 0.0))
+      })
+      .unwrap();
+    assert!((clip - Vec3::new(1.0, 0.0, clip.z)).length() < 1e-5);
+    let clip = engine
+      .with_component::<Camera2d, _>(windowed, |camera| {
+        camera
+          .view_projection()
+          .project_point3(Vec3::new(200.0, 0.0, 0.0))
+      })
+      .unwrap();
+    assert!((clip.x - 1.0).abs() < 1e-5);
+
+    // Half of an 800x600 target, so a square viewport
+    let clip = engine
+      .with_component::<Camera3d, _>(deep, |camera| {
+        camera.view_projection().project_point3(Vec3::ZERO)
+      })
+      .unwrap();
+    assert!(clip.x.abs() < 1e-5 && clip.y.abs() < 1e-5 && (0.0..1.0).contains(&clip.z));
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let record = seen.clone();
+    engine
+      .resource_mut::<RenderQueue>()
+      .unwrap()
+      .submit(RenderTarget::Offscreen, move |context| {
+        *record.lock().unwrap() = context
+          .cameras
+          .iter()
+          .map(|camera| (camera.entity, camera.viewport, camera.bind_group.is_some()))
+          .collect();
+      });
+    engine.render(RenderTarget::Offscreen).unwrap();
+
+    if gpu_available(&engine, "cameras_follow_transforms") {
+      assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+          (deep, (0.0, 0.0, 400.0, 600.0), true),
+          (flat, (0.0, 0.0, 800.0, 600.0), true)
+        ]
+      );
+    }
+  }
+
+  #[test]
+  fn sprites_batch_by_layer_and_texture() {
+    let mut images = Assets::new();
+    let red = images.add(Image::solid(4, 2, [255, 0, 0, 255]));
+    let blue = images.add(Image::solid(1, 1, [0, 0, 255, 255]));
+    let missing = Assets::<Image>::new().add(Image::solid(1, 1, [0; 4]));
+
+    let mut flipped = Sprite::new(red.clone());
+    flipped.flip_x = true;
+    let sprites = [
+      Sprite::new(blue.clone()).with_layer(1),
+      flipped,
+      Sprite::new(blue.clone()),
+      Sprite::new(red.clone()).with_size(Vec2::splat(8.0)),
+      Sprite::new(missing),
+    ];
+    let mut extracted: Vec<_> = sprites
+      .iter()
+      .filter_map(|sprite| sprite.extract(Mat4::IDENTITY, &images))
+      .collect();
+    assert_eq!(extracted.len(), 4);
+
+    let batches = batch_sprites(&mut extracted, &[]);
+    let runs: Vec<_> = batches
+      .iter()
+      .map(|batch| (batch.texture, batch.instances.clone()))
+      .collect();
+    let (red, blue) = (red.id(), blue.id());
+    // Neighbouring layers sharing a texture still end up in one draw
+    let expected = if red < blue {
+      vec![(red, 0..2), (blue, 2..4)]
+    } else {
+      vec![(blue, 0..1), (red, 1..3), (blue, 3..4)]
+    };
+    assert_eq!(runs, expected);
+
+    // Something drawn on layer 0 keeps the two blue layers apart
+    let layers: Vec<_> = batch_sprites(&mut extracted, &[0])
+      .iter()
+      .map(|batch| batch.layer)
+      .collect();
+    let expected = if red < blue {
+      vec![0, 0, 1]
+    } else {
+      vec![0, 0, 0, 1]
+    };
+    assert_eq!(layers, expected);
+  }
+
+  #[test]
+  fn headless_sprites_render() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(64, 64).unwrap();
+    let texture = engine
+      .resource_mut::<Assets<Image>>()
+      .unwrap()
+      .add(Image::solid(2, 2, [255, 255, 255, 255]));
+
+    let camera = engine.create_entity();
+    engine
+      .add_component(camera, Camera2d::new(RenderTarget::Offscreen))
+      .unwrap();
+    for i in 0..1000 {
+      let sprite = engine.create_entity();
+      engine
+        .add_component(sprite, Sprite::new(texture.clone()).with_layer(i % 3))
+        .unwrap();
+      engine
+        .add_component(
+          sprite,
+          Transform::from_xyz((i % 32) as f32, (i / 32) as f32, 0.0),
+        )
+        .unwrap();
+    }
+
+    engine.update().unwrap();
+    engine.render(RenderTarget::Offscreen).unwrap();
+    engine.render(RenderTarget::Offscreen).unwrap();
+    assert_eq!(engine.resource::<Renderer>().unwrap().frames(), 2);
+    if !gpu_available(&engine, "headless_sprites_render") {
+      return;
+    }
+
+    // The camera is centered on the origin, the grid of sprites fills most of the top right quarter
+    let image = engine.render_to_image().unwrap();
+    assert_eq!(image.pixel(48, 16), Some([255, 255, 255, 255]));
+    assert_eq!(image.pixel(40, 2), Some([255, 255, 255, 255]));
+    assert_eq!(image.pixel(16, 16).unwrap()[..3], [0, 0, 0]);
+    assert_eq!(image.pixel(48, 48).unwrap()[..3], [0, 0, 0]);
+    assert_eq!(image.pixel(48, 0).unwrap()[..3], [0, 0, 0]);
+  }
+
+  #[test]
+  fn image_comparison_tolerance() {
+    let expected = Image::solid(4, 4, [100, 100, 100, 255]);
+    let mut actual = expected.clone();
+    actual.data[0] = 102;
+    actual.data[5] = 90;
+
+    let diff = compare_images(&actual, &expected, 2).unwrap();
+    assert_eq!((diff.different_pixels, diff.max_difference), (1, 10));
+    assert!(!diff.within(Tolerance::EXACT));
+    assert!(diff.within(Tolerance {
+      channel: 2,
+      pixels: 0.1
+    }));
+    assert!(compare_images(&actual, &Image::solid(2, 2, [0; 4]), 0).is_none());
+  }
+
+  #[test]
+  fn golden_sprites_and_cameras() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(64, 64).unwrap();
+    if !gpu_available(&engine, "golden_sprites_and_cameras") {
+      return;
+    }
+    engine
+      .resource_mut::<RenderQueue>()
+      .unwrap()
+      .set_clear_color(wgpu::Color {
+        r: 0.02,
+        g: 0.02,
+        b: 0.05,
+        a: 1.0,
+      });
+
+    #[rustfmt::skip]
+    let checker = Image::new(2, 2, vec![
+      255, 0, 0, 255,  0, 255, 0, 255,
+      0, 0, 255, 255,  255, 255, 255, 255,
+    ]).unwrap();
+    let texture = engine.resource_mut::<Assets<Image>>().unwrap().add(checker);
+
+    let main = engine.create_entity();
+    engine
+      .add_component(main, Camera2d::new(RenderTarget::Offscreen))
+      .unwrap();
+    // Zoomed out inset in the top right corner, drawn after the main camera
+    let inset = engine.create_entity();
+    let mut camera = Camera2d::new(RenderTarget::Offscreen);
+    camera.viewport = Viewport::new(0.5, 0.0, 0.5, 0.5);
+    camera.order = 1;
+    camera.projection = Projection::Orthographic {
+      scale: 2.0,
+      near: -1000.0,
+      far: 1000.0,
+    };
+    engine.add_component(inset, camera).unwrap();
+
+    let big = engine.create_entity();
+    engine
+      .add_component(
+        big,
+        Sprite::new(texture.clone()).with_size(Vec2::splat(32.0)),
+      )
+      .unwrap();
+    engine
+      .add_component(big, Transform::from_xyz(-8.0, -8.0, 0.0))
+      .unwrap();
+
+    let mut small = Sprite::new(texture.clone())
+      .with_size(Vec2::splat(16.0))
+      .with_layer(1);
+    small.flip_x = true;
+    small.anchor = SpriteAnchor::BottomLeft;
+    small.color = wgpu::Color {
+      r: 1.0,
+      g: 1.0,
+      b: 1.0,
+      a: 0.5,
+    };
+    let small_entity = engine.create_entity();
+    engine.add_component(small_entity, small).unwrap();
+    engine
+      .add_component(small_entity, Transform::from_xyz(0.0, 0.0, 0.0))
+      .unwrap();
+
+    engine.update().unwrap();
+    let image = engine.render_to_image().unwrap();
+    assert_eq!(image.size(), (64, 64));
+    assert_golden("sprites_and_cameras", &image, Tolerance::default());
+  }
+
+  #[derive(Component, Clone)]
+  struct Tint([f32; 4]);
+
+  impl Material for Tint {
+    type Uniform = [f32; 4];
+
+    fn shader() -> &'static str {
+      "
+      @group(1) @binding(0) var<uniform> tint: vec4<f32>;
+
+      @fragment
+      fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+        return tint * in.color;
+      }
+      "
+    }
+
+    fn uniform(&self) -> [f32; 4] {
+      self.0
+    }
+  }
+
+  #[test]
+  fn materials_draw_sprites_and_meshes() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(64, 64).unwrap();
+    if !gpu_available(&engine, "materials_draw_sprites_and_meshes") {
+      return;
+    }
+    engine.register_material::<Tint>();
+    let texture = engine
+      .resource_mut::<Assets<Image>>()
+      .unwrap()
+      .add(Image::solid(1, 1, [255, 255, 255, 255]));
+    let quad = engine
+      .resource_mut::<Assets<Mesh>>()
+      .unwrap()
+      .add(Mesh::quad(Vec2::splat(16.0)));
+
+    let camera = engine.create_entity();
+    engine
+      .add_component(camera, Camera2d::new(RenderTarget::Offscreen))
+      .unwrap();
+
+    // The material replaces the white sprite
+    let sprite = engine.create_entity();
+    engine
+      .add_component(
+        sprite,
+        Sprite::new(texture.clone()).with_size(Vec2::splat(16.0)),
+      )
+      .unwrap();
+    engine
+      .add_component(sprite, Tint([1.0, 0.0, 0.0, 1.0]))
+      .unwrap();
+    engine
+      .add_component(sprite, Transform::from_xyz(-16.0, 0.0, 0.0))
+      .unwrap();
+
+    for (x, y) in [(16.0, 0.0), (16.0, 20.0)] {
+      let mesh = engine.create_entity();
+      engine.add_component(mesh, quad.clone()).unwrap();
+      engine
+        .add_component(mesh, Tint([0.0, 1.0, 0.0, 1.0]))
+        .unwrap();
+      engine
+        .add_component(mesh, Transform::from_xyz(x, y, 0.0))
+        .unwrap();
+    }
+
+    engine.update().unwrap();
+    engine.render_to_image().unwrap();
+    let image = engine.render_to_image().unwrap();
+    assert_eq!(image.pixel(16, 32), Some([255, 0, 0, 255]));
+    assert_eq!(image.pixel(48, 32), Some([0, 255, 0, 255]));
+    assert_eq!(image.pixel(48, 12), Some([0, 255, 0, 255]));
+    assert_eq!(image.pixel(32, 32), Some([0, 0, 0, 255]));
+    // One pipeline for sprites and one for meshes, however many entities and frames
+    assert_eq!(
+      engine.resource::<Renderer>().unwrap().material_pipelines(),
+      2
+    );
+  }
+
+  #[test]
+  fn materials_and_sprites_share_layers() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(64, 64).unwrap();
+    if !gpu_available(&engine, "materials_and_sprites_share_layers") {
+      return;
+    }
+    engine.register_material::<Tint>();
+    let white = engine
+      .resource_mut::<Assets<Image>>()
+      .unwrap()
+      .add(Image::solid(1, 1, [255, 255, 255, 255]));
+
+    let camera = engine.create_entity();
+    engine
+      .add_component(camera, Camera2d::new(RenderTarget::Offscreen))
+      .unwrap();
+
+    // On the left a plain sprite covers a material, on the right it is sandwiched between two
+    let stacks = [
+      (-16.0, vec![(10, None), (-10, Some([0.0, 0.0, 1.0, 1.0]))]),
+      (
+        16.0,
+        vec![
+          (10, Some([0.0, 1.0, 0.0, 1.0])),
+          (0, None),
+          (-10, Some([1.0, 0.0, 0.0, 1.0])),
+        ],
+      ),
+    ];
+    for (x, stack) in stacks {
+      for (layer, tint) in stack {
+        let entity = engine.create_entity();
+        engine
+          .add_component(
+            entity,
+            Sprite::new(white.clone())
+              .with_size(Vec2::splat(16.0))
+              .with_layer(layer),
+          )
+          .unwrap();
+        if let Some(tint) = tint {
+          engine.add_component(entity, Tint(tint)).unwrap();
+        }
+        engine
+          .add_component(entity, Transform::from_xyz(x, 0.0, 0.0))
+          .unwrap();
+      }
+    }
+
+    engine.update().unwrap();
+    let image = engine.render_to_image().unwrap();
+    assert_eq!(image.pixel(16, 32), Some([255, 255, 255, 255]));
+    assert_eq!(image.pixel(48, 32), Some([0, 255, 0, 255]));
+  }
+}
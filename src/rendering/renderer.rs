@@ -0,0 +1,739 @@
+use super::{
+  CameraUniform, CameraView, ExtractedMaterial, ExtractedSprite, Image, MaterialDraw,
+  MaterialRenderer, Mesh, SpriteBatch, SpriteRenderer,
+};
+use crate::asset::Assets;
+use crate::error::RenderError;
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc};
+
+use winit::window::Window;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RenderTarget {
+  // Entity of the window
+  Window(u32),
+  Offscreen,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+  Windowed,
+  // Renders into an offscreen texture, falls back to a software adapter then to the null backend
+  Headless { width: u32, height: u32 },
+}
+
+// Everything a render command needs to record its work for one target
+pub struct RenderContext<'a> {
+  pub device: &'a wgpu::Device,
+  pub queue: &'a wgpu::Queue,
+  pub encoder: &'a mut wgpu::CommandEncoder,
+  pub view: &'a wgpu::TextureView,
+  pub format: wgpu::TextureFormat,
+  pub size: (u32, u32),
+  pub target: RenderTarget,
+  // Cameras looking at this target, sorted by render order
+  pub cameras: &'a [CameraView],
+  pub camera_layout: &'a wgpu::BindGroupLayout,
+}
+
+pub type RenderCommand = Box<dyn FnOnce(&mut RenderContext) + Send + Sync>;
+
+// Resource systems submit into, the renderer drains it once per target every frame
+pub struct RenderQueue {
+  clear_color: wgpu::Color,
+  commands: Vec<(RenderTarget, RenderCommand)>,
+}
+
+impl RenderQueue {
+  pub fn new() -> Self {
+    Self {
+      clear_color: wgpu::Color::BLACK,
+      commands: Vec::new(),
+    }
+  }
+
+  pub fn clear_color(&self) -> wgpu::Color {
+    self.clear_color
+  }
+  pub fn set_clear_color(&mut self, color: wgpu::Color) {
+    self.clear_color = color;
+  }
+
+  // Commands run in submission order, after the target was cleared
+  pub fn submit(
+    &mut self,
+    target: RenderTarget,
+    command: impl FnOnce(&mut RenderContext) + Send + Sync + 'static,
+  ) {
+    self.commands.push((target, Box::new(command)));
+  }
+
+  pub fn len(&self) -> usize {
+    self.commands.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.commands.is_empty()
+  }
+
+  pub(crate) fn take(&mut self, target: RenderTarget) -> Vec<RenderCommand> {
+    let (taken, kept) = self.commands.drain(..).partition(|(t, _)| *t == target);
+    self.commands = kept;
+    taken.into_iter().map(|(_, command)| command).collect()
+  }
+}
+
+struct GpuContext {
+  instance: wgpu::Instance,
+  adapter: wgpu::Adapter,
+  device: wgpu::Device,
+  queue: wgpu::Queue,
+  camera_layout: wgpu::BindGroupLayout,
+}
+
+struct GpuCamera {
+  target: RenderTarget,
+  buffer: wgpu::Buffer,
+  bind_group: wgpu::BindGroup,
+}
+
+impl GpuCamera {
+  fn new(gpu: &GpuContext, target: RenderTarget) -> Self {
+    let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+      label: Some("p1 camera uniforms"),
+      size: std::mem::size_of::<CameraUniform>() as u64,
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+      mapped_at_creation: false,
+    });
+    let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("p1 camera"),
+      layout: &gpu.camera_layout,
+      entries: &[wgpu::BindGroupEntry {
+        binding: 0,
+        resource: buffer.as_entire_binding(),
+      }],
+    });
+    Self {
+      target,
+      buffer,
+      bind_group,
+    }
+  }
+
+  fn write(&self, gpu: &GpuContext, view: &mut CameraView) {
+    gpu
+      .queue
+      .write_buffer(&self.buffer, 0, bytemuck::bytes_of(&view.uniform()));
+    view.bind_group = Some(self.bind_group.clone());
+  }
+}
+
+// UI of a target, drawn over everything else in logical pixels
+pub(crate) struct UiFrame {
+  pub camera: CameraView,
+  pub sprites: Vec<ExtractedSprite>,
+}
+
+struct WindowSurface {
+  surface: wgpu::Surface<'static>,
+  config: wgpu::SurfaceConfiguration,
+}
+
+struct Offscreen {
+  texture: Option<wgpu::Texture>,
+  size: (u32, u32),
+}
+
+// The format offscreen targets are rendered in, also what read back pixels are in
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+pub struct Renderer {
+  // None is the null backend, which accepts everything and draws nothing
+  gpu: Option<GpuContext>,
+  surfaces: HashMap<u32, WindowSurface>,
+  offscreen: Option<Offscreen>,
+  // Uniform buffers of every camera that was rendered, by entity
+  cameras: HashMap<u32, GpuCamera>,
+  sprites: Option<SpriteRenderer>,
+  materials: Option<MaterialRenderer>,
+  // Separate from sprites so both keep their instances for the frame
+  ui: Option<SpriteRenderer>,
+  ui_cameras: HashMap<RenderTarget, GpuCamera>,
+  frames: u64,
+}
+
+impl Renderer {
+  pub fn new(mode: RenderMode) -> Result<Self, RenderError> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+      backends: wgpu::Backends::all(),
+      ..Default::default()
+    });
+
+    let request = |force_fallback_adapter| {
+      pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        force_fallback_adapter,
+        compatible_surface: None,
+      }))
+    };
+
+    let adapter = match mode {
+      RenderMode::Windowed => request(false).ok_or(RenderError::NoAdapter)?,
+      // Always the software one so headless frames come out the same on every machine
+      RenderMode::Headless { width, height } => match request(true) {
+        Some(adapter) => adapter,
+        None => return Ok(Self::null(width, height)),
+      },
+    };
+
+    let (device, queue) = pollster::block_on(adapter.request_device(
+      &wgpu::DeviceDescriptor {
+        label: Some("p1"),
+        required_features: wgpu::Features::empty(),
+        required_limits: wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+        memory_hints: wgpu::MemoryHints::default(),
+      },
+      None,
+    ))?;
+
+    let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("p1 camera"),
+      entries: &[wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+          ty: wgpu::BufferBindingType::Uniform,
+          has_dynamic_offset: false,
+          min_binding_size: None,
+        },
+        count: None,
+      }],
+    });
+
+    let sprites = SpriteRenderer::new(&device, &camera_layout);
+    let materials = MaterialRenderer::new(&device, &camera_layout);
+    let ui = SpriteRenderer::new(&device, &camera_layout);
+
+    let mut renderer = Self {
+      gpu: Some(GpuContext {
+        instance,
+        adapter,
+        device,
+        queue,
+        camera_layout,
+      }),
+      surfaces: HashMap::new(),
+      offscreen: None,
+      cameras: HashMap::new(),
+      sprites: Some(sprites),
+      materials: Some(materials),
+      ui: Some(ui),
+      ui_cameras: HashMap::new(),
+      frames: 0,
+    };
+    if let RenderMode::Headless { width, height } = mode {
+      renderer.resize_offscreen(width, height);
+    }
+    Ok(renderer)
+  }
+
+  pub fn null(width: u32, height: u32) -> Self {
+    Self {
+      gpu: None,
+      surfaces: HashMap::new(),
+      offscreen: Some(Offscreen {
+        texture: None,
+        size: (width, height),
+      }),
+      cameras: HashMap::new(),
+      sprites: None,
+      materials: None,
+      ui: None,
+      ui_cameras: HashMap::new(),
+      frames: 0,
+    }
+  }
+
+  pub fn is_null(&self) -> bool {
+    self.gpu.is_none()
+  }
+
+  pub fn device(&self) -> Option<&wgpu::Device> {
+    self.gpu.as_ref().map(|gpu| &gpu.device)
+  }
+  pub fn queue(&self) -> Option<&wgpu::Queue> {
+    self.gpu.as_ref().map(|gpu| &gpu.queue)
+  }
+
+  // Pipelines drawing through cameras put this layout at their camera group
+  pub fn camera_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+    self.gpu.as_ref().map(|gpu| &gpu.camera_layout)
+  }
+
+  // Size of a target in physical pixels, None for windows without a surface
+  pub fn target_size(&self, target: RenderTarget) -> Option<(u32, u32)> {
+    match target {
+      RenderTarget::Window(entity) => self
+        .surfaces
+        .get(&entity)
+        .map(|surface| (surface.config.width, surface.config.height)),
+      RenderTarget::Offscreen => self.offscreen_size(),
+    }
+  }
+
+  // Number of frames rendered across every target
+  pub fn frames(&self) -> u64 {
+    self.frames
+  }
+
+  pub fn attach_window(
+    &mut self,
+    entity: u32,
+    window: Arc<Window>,
+    vsync: bool,
+  ) -> Result<(), RenderError> {
+    let Some(gpu) = self.gpu.as_ref() else {
+      return Ok(());
+    };
+
+    let size = window.inner_size();
+    let surface = gpu.instance.create_surface(window)?;
+    let mut config = surface
+      .get_default_config(&gpu.adapter, size.width.max(1), size.height.max(1))
+      .ok_or(RenderError::UnsupportedSurface)?;
+    config.present_mode = if vsync {
+      wgpu::PresentMode::AutoVsync
+    } else {
+      wgpu::PresentMode::AutoNoVsync
+    };
+    surface.configure(&gpu.device, &config);
+
+    self
+      .surfaces
+      .insert(entity, WindowSurface { surface, config });
+    Ok(())
+  }
+
+  pub fn detach_window(&mut self, entity: u32) {
+    self.surfaces.remove(&entity);
+    self
+      .cameras
+      .retain(|_, camera| camera.target != RenderTarget::Window(entity));
+    self.ui_cameras.remove(&RenderTarget::Window(entity));
+  }
+
+  pub fn resize(&mut self, entity: u32, width: u32, height: u32) {
+    let (Some(gpu), Some(surface)) = (self.gpu.as_ref(), self.surfaces.get_mut(&entity)) else {
+      return;
+    };
+    // Minimized windows report a zero size, surfaces can't be configured with it
+    if width == 0 || height == 0 {
+      return;
+    }
+    surface.config.width = width;
+    surface.config.height = height;
+    surface.surface.configure(&gpu.device, &surface.config);
+  }
+
+  pub fn offscreen_size(&self) -> Option<(u32, u32)> {
+    self.offscreen.as_ref().map(|offscreen| offscreen.size)
+  }
+
+  pub fn resize_offscreen(&mut self, width: u32, height: u32) {
+    let texture = self.gpu.as_ref().map(|gpu| {
+      gpu.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("p1 offscreen target"),
+        size: wgpu::Extent3d {
+          width: width.max(1),
+          height: height.max(1),
+          depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+      })
+    });
+    self.offscreen = Some(Offscreen {
+      texture,
+      size: (width.max(1), height.max(1)),
+    });
+  }
+
+  // Writes the uniforms of the cameras and hands them their bind groups
+  fn upload_cameras(&mut self, target: RenderTarget, cameras: &mut [CameraView]) {
+    let Some(gpu) = self.gpu.as_ref() else {
+      return;
+    };
+
+    // Cameras that stopped looking at this target don't need their buffers anymore
+    self.cameras.retain(|entity, camera| {
+      camera.target != target || cameras.iter().any(|view| view.entity == *entity)
+    });
+
+    for view in cameras.iter_mut() {
+      let camera = self
+        .cameras
+        .entry(view.entity)
+        .or_insert_with(|| GpuCamera::new(gpu, target));
+      // A camera can move to another target while keeping its buffer
+      camera.target = target;
+      camera.write(gpu, view);
+    }
+  }
+
+  // Copies the offscreen target back to the CPU, blocks until the GPU is done with it
+  pub fn read_offscreen(&self) -> Result<Image, RenderError> {
+    let gpu = self.gpu.as_ref().ok_or(RenderError::NullBackend)?;
+    let offscreen = self
+      .offscreen
+      .as_ref()
+      .ok_or(RenderError::NoOffscreenTarget)?;
+    let texture = offscreen
+      .texture
+      .as_ref()
+      .ok_or(RenderError::NoOffscreenTarget)?;
+    let (width, height) = offscreen.size;
+
+    // Rows of a copy have to be aligned, the padding is stripped once mapped
+    let row = width * 4;
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_row = row.div_ceil(alignment) * alignment;
+    let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+      label: Some("p1 readback"),
+      size: padded_row as u64 * height as u64,
+      usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+      mapped_at_creation: false,
+    });
+
+    let mut encoder = gpu
+      .device
+      .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("p1 readback"),
+      });
+    encoder.copy_texture_to_buffer(
+      wgpu::TexelCopyTextureInfo {
+        texture,
+        mip_level: 0,
+        origin: wgpu::Origin3d::ZERO,
+        aspect: wgpu::TextureAspect::All,
+      },
+      wgpu::TexelCopyBufferInfo {
+        buffer: &buffer,
+        layout: wgpu::TexelCopyBufferLayout {
+          offset: 0,
+          bytes_per_row: Some(padded_row),
+          rows_per_image: Some(height),
+        },
+      },
+      wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+    );
+    gpu.queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+      let _ = sender.send(result);
+    });
+    gpu.device.poll(wgpu::Maintain::Wait);
+    // The callback always ran once the device was polled to completion
+    receiver.recv().unwrap()?;
+
+    let mapped = slice.get_mapped_range();
+    let data = mapped
+      .chunks_exact(padded_row as usize)
+      .flat_map(|padded| &padded[..row as usize])
+      .copied()
+      .collect();
+    drop(mapped);
+    buffer.unmap();
+
+    Ok(Image::new(width, height, data).unwrap())
+  }
+
+  // Lets an image that changed in place be uploaded again
+  pub fn invalidate_texture(&mut self, id: u64) {
+    if let Some(sprites) = self.sprites.as_mut() {
+      sprites.invalidate_texture(id);
+    }
+    if let Some(materials) = self.materials.as_mut() {
+      materials.invalidate(id);
+    }
+    if let Some(ui) = self.ui.as_mut() {
+      ui.invalidate_texture(id);
+    }
+  }
+
+  // Pipelines built for materials so far, each is built once per vertex layout and format
+  pub fn material_pipelines(&self) -> usize {
+    self
+      .materials
+      .as_ref()
+      .map_or(0, |materials| materials.pipeline_count())
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  pub(crate) fn render(
+    &mut self,
+    target: RenderTarget,
+    queue: &mut RenderQueue,
+    mut cameras: Vec<CameraView>,
+    mut sprites: Vec<ExtractedSprite>,
+    mut materials: Vec<ExtractedMaterial>,
+    ui: Option<UiFrame>,
+    images: &Assets<Image>,
+    meshes: &Assets<Mesh>,
+  ) -> Result<(), RenderError> {
+    let commands = queue.take(target);
+    cameras.sort_by_key(|camera| camera.order);
+    self.upload_cameras(target, &mut cameras);
+    let Some(gpu) = self.gpu.as_ref() else {
+      self.frames += 1;
+      return Ok(());
+    };
+
+    let (ui_camera, mut ui_sprites) = match ui {
+      Some(UiFrame {
+        mut camera,
+        sprites,
+      }) => {
+        let gpu_camera = self
+          .ui_cameras
+          .entry(target)
+          .or_insert_with(|| GpuCamera::new(gpu, target));
+        gpu_camera.write(gpu, &mut camera);
+        (vec![camera], sprites)
+      }
+      None => (Vec::new(), Vec::new()),
+    };
+
+    match target {
+      RenderTarget::Window(entity) => {
+        let surface = self
+          .surfaces
+          .get(&entity)
+          .ok_or(RenderError::TargetNotFound(entity))?;
+        let frame = match surface.surface.get_current_texture() {
+          Ok(frame) => frame,
+          // The surface needs to be set up again, this frame is dropped
+          Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+            surface.surface.configure(&gpu.device, &surface.config);
+            return Ok(());
+          }
+          Err(wgpu::SurfaceError::OutOfMemory) => return Err(RenderError::OutOfMemory),
+          Err(wgpu::SurfaceError::Timeout | wgpu::SurfaceError::Other) => return Ok(()),
+        };
+
+        let view = frame
+          .texture
+          .create_view(&wgpu::TextureViewDescriptor::default());
+        let size = (surface.config.width, surface.config.height);
+        let format = surface.config.format;
+        let layers = material_layers(&materials);
+        let sprite_renderer = self.sprites.as_mut().unwrap();
+        let batches = sprite_renderer.prepare(
+          &gpu.device,
+          &gpu.queue,
+          format,
+          &mut sprites,
+          &layers,
+          images,
+        );
+        let material_renderer = self.materials.as_mut().unwrap();
+        let draws = material_renderer.prepare(
+          &gpu.device,
+          &gpu.queue,
+          format,
+          &mut materials,
+          sprite_renderer,
+          images,
+          meshes,
+        );
+        let ui_renderer = self.ui.as_mut().unwrap();
+        let ui_batches = ui_renderer.prepare(
+          &gpu.device,
+          &gpu.queue,
+          format,
+          &mut ui_sprites,
+          &[],
+          images,
+        );
+        Self::draw(
+          gpu,
+          target,
+          &view,
+          format,
+          size,
+          queue,
+          commands,
+          &cameras,
+          (sprite_renderer, &batches),
+          (material_renderer, &draws),
+          (ui_renderer, &ui_camera, &ui_batches),
+        );
+        frame.present();
+      }
+      RenderTarget::Offscreen => {
+        let offscreen = self.offscreen.as_ref().ok_or(RenderError::NoOffscreenTarget)?;
+        let view = offscreen
+          .texture
+          .as_ref()
+          .ok_or(RenderError::NoOffscreenTarget)?
+          .create_view(&wgpu::TextureViewDescriptor::default());
+        let layers = material_layers(&materials);
+        let sprite_renderer = self.sprites.as_mut().unwrap();
+        let batches = sprite_renderer.prepare(
+          &gpu.device,
+          &gpu.queue,
+          OFFSCREEN_FORMAT,
+          &mut sprites,
+          &layers,
+          images,
+        );
+        let material_renderer = self.materials.as_mut().unwrap();
+        let draws = material_renderer.prepare(
+          &gpu.device,
+          &gpu.queue,
+          OFFSCREEN_FORMAT,
+          &mut materials,
+          sprite_renderer,
+          images,
+          meshes,
+        );
+        let ui_renderer = self.ui.as_mut().unwrap();
+        let ui_batches = ui_renderer.prepare(
+          &gpu.device,
+          &gpu.queue,
+          OFFSCREEN_FORMAT,
+          &mut ui_sprites,
+          &[],
+          images,
+        );
+        Self::draw(
+          gpu,
+          target,
+          &view,
+          OFFSCREEN_FORMAT,
+          offscreen.size,
+          queue,
+          commands,
+          &cameras,
+          (sprite_renderer, &batches),
+          (material_renderer, &draws),
+          (ui_renderer, &ui_camera, &ui_batches),
+        );
+      }
+    }
+
+    self.frames += 1;
+    Ok(())
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn draw(
+    gpu: &GpuContext,
+    target: RenderTarget,
+    view: &wgpu::TextureView,
+    format: wgpu::TextureFormat,
+    size: (u32, u32),
+    queue: &RenderQueue,
+    commands: Vec<RenderCommand>,
+    cameras: &[CameraView],
+    (sprite_renderer, batches): (&SpriteRenderer, &[SpriteBatch]),
+    (material_renderer, draws): (&MaterialRenderer, &[MaterialDraw]),
+    (ui_renderer, ui_camera, ui_batches): (&SpriteRenderer, &[CameraView], &[SpriteBatch]),
+  ) {
+    let mut encoder = gpu
+      .device
+      .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("p1 frame"),
+      });
+
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("p1 clear"),
+      color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+        view,
+        resolve_target: None,
+        ops: wgpu::Operations {
+          load: wgpu::LoadOp::Clear(queue.clear_color()),
+          store: wgpu::StoreOp::Store,
+        },
+      })],
+      depth_stencil_attachment: None,
+      timestamp_writes: None,
+      occlusion_query_set: None,
+    });
+
+    // Sprites and materials go under whatever the commands draw
+    if !batches.is_empty() || !draws.is_empty() {
+      let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("p1 sprites"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+          view,
+          resolve_target: None,
+          ops: wgpu::Operations {
+            load: wgpu::LoadOp::Load,
+            store: wgpu::StoreOp::Store,
+          },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+      });
+
+      for camera in cameras {
+        if camera.bind_group.is_none() || camera.viewport.2 < 1.0 || camera.viewport.3 < 1.0 {
+          continue;
+        }
+        camera.apply(&mut pass, 0);
+        // Both are sorted by layer, within a layer materials go over plain sprites
+        let (mut batches, mut draws) = (batches, draws);
+        while !batches.is_empty() || !draws.is_empty() {
+          let below = draws.first().map(|draw| draw.layer);
+          let count =
+            batches.partition_point(|batch| below.is_none_or(|layer| batch.layer <= layer));
+          sprite_renderer.draw_batches(&mut pass, format, &batches[..count]);
+          batches = &batches[count..];
+
+          let above = batches.first().map(|batch| batch.layer);
+          let count = draws.partition_point(|draw| above.is_none_or(|layer| draw.layer < layer));
+          material_renderer.draw(&mut pass, &draws[..count]);
+          draws = &draws[count..];
+        }
+      }
+    }
+
+    let mut context = RenderContext {
+      device: &gpu.device,
+      queue: &gpu.queue,
+      encoder: &mut encoder,
+      view,
+      format,
+      size,
+      target,
+      cameras,
+      camera_layout: &gpu.camera_layout,
+    };
+    for command in commands {
+      command(&mut context);
+    }
+    ui_renderer.draw(&mut encoder, view, format, ui_camera, ui_batches);
+
+    gpu.queue.submit([encoder.finish()]);
+  }
+}
+
+// Layers sprite batches must not span, so materials on them can be drawn in between
+fn material_layers(materials: &[ExtractedMaterial]) -> Vec<i32> {
+  let mut layers: Vec<_> = materials.iter().map(|material| material.layer).collect();
+  layers.sort_unstable();
+  layers.dedup();
+  layers
+}
@@ -0,0 +1,532 @@
+use super::{CameraView, Image};
+use crate::asset::{Assets, Handle};
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2};
+use macros::Component;
+
+use crate::ecs::Component;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+  pub min: Vec2,
+  pub max: Vec2,
+}
+
+impl Rect {
+  pub const UNIT: Self = Self {
+    min: Vec2::ZERO,
+    max: Vec2::ONE,
+  };
+
+  pub fn new(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Self {
+    Self {
+      min: Vec2::new(min_x, min_y),
+      max: Vec2::new(max_x, max_y),
+    }
+  }
+
+  pub fn size(&self) -> Vec2 {
+    self.max - self.min
+  }
+
+  // Min inclusive, max exclusive
+  pub fn contains(&self, point: Vec2) -> bool {
+    point.cmpge(self.min).all() && point.cmplt(self.max).all()
+  }
+}
+
+// Point of the sprite that sits on its transform
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SpriteAnchor {
+  #[default]
+  Center,
+  BottomLeft,
+  BottomCenter,
+  BottomRight,
+  CenterLeft,
+  CenterRight,
+  TopLeft,
+  TopCenter,
+  TopRight,
+  // From (-0.5, -0.5) at the bottom left to (0.5, 0.5) at the top right
+  Custom(Vec2),
+}
+
+impl SpriteAnchor {
+  pub fn as_vec(&self) -> Vec2 {
+    match *self {
+      SpriteAnchor::Center => Vec2::ZERO,
+      SpriteAnchor::BottomLeft => Vec2::new(-0.5, -0.5),
+      SpriteAnchor::BottomCenter => Vec2::new(0.0, -0.5),
+      SpriteAnchor::BottomRight => Vec2::new(0.5, -0.5),
+      SpriteAnchor::CenterLeft => Vec2::new(-0.5, 0.0),
+      SpriteAnchor::CenterRight => Vec2::new(0.5, 0.0),
+      SpriteAnchor::TopLeft => Vec2::new(-0.5, 0.5),
+      SpriteAnchor::TopCenter => Vec2::new(0.0, 0.5),
+      SpriteAnchor::TopRight => Vec2::new(0.5, 0.5),
+      SpriteAnchor::Custom(anchor) => anchor,
+    }
+  }
+}
+
+// Drawn by every camera of every target, placed by the GlobalTransform of its entity
+#[derive(Component, Clone, Debug)]
+pub struct Sprite {
+  pub texture: Handle<Image>,
+  // Multiplied with the texture
+  pub color: wgpu::Color,
+  // Part of the texture to draw, in UV coordinates with the origin at the top left
+  pub uv_rect: Rect,
+  pub flip_x: bool,
+  pub flip_y: bool,
+  pub anchor: SpriteAnchor,
+  // World units, defaults to the pixel size of the drawn part of the texture
+  pub custom_size: Option<Vec2>,
+  // Higher layers are drawn over lower ones
+  pub layer: i32,
+}
+
+impl Sprite {
+  pub fn new(texture: Handle<Image>) -> Self {
+    Self {
+      texture,
+      color: wgpu::Color::WHITE,
+      uv_rect: Rect::UNIT,
+      flip_x: false,
+      flip_y: false,
+      anchor: SpriteAnchor::Center,
+      custom_size: None,
+      layer: 0,
+    }
+  }
+
+  pub fn with_size(mut self, size: Vec2) -> Self {
+    self.custom_size = Some(size);
+    self
+  }
+  pub fn with_layer(mut self, layer: i32) -> Self {
+    self.layer = layer;
+    self
+  }
+
+  // None while the texture isn't loaded
+  pub(crate) fn extract(&self, transform: Mat4, images: &Assets<Image>) -> Option<ExtractedSprite> {
+    let image = images.get(&self.texture)?;
+    let size = self.custom_size.unwrap_or_else(|| {
+      Vec2::new(image.width as f32, image.height as f32) * self.uv_rect.size().abs()
+    });
+    let offset = -(self.anchor.as_vec() + 0.5) * size;
+    let model =
+      transform * Mat4::from_translation(offset.extend(0.0)) * Mat4::from_scale(size.extend(1.0));
+
+    let (mut min, mut max) = (self.uv_rect.min, self.uv_rect.max);
+    if self.flip_x {
+      std::mem::swap(&mut min.x, &mut max.x);
+    }
+    if self.flip_y {
+      std::mem::swap(&mut min.y, &mut max.y);
+    }
+
+    Some(ExtractedSprite {
+      texture: self.texture.id(),
+      layer: self.layer,
+      instance: SpriteInstance {
+        model: model.to_cols_array_2d(),
+        uv_rect: [min.x, min.y, max.x, max.y],
+        color: [
+          self.color.r as f32,
+          self.color.g as f32,
+          self.color.b as f32,
+          self.color.a as f32,
+        ],
+      },
+    })
+  }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct SpriteInstance {
+  model: [[f32; 4]; 4],
+  uv_rect: [f32; 4],
+  color: [f32; 4],
+}
+
+impl SpriteInstance {
+  pub fn new(model: Mat4, color: wgpu::Color) -> Self {
+    Self {
+      model: model.to_cols_array_2d(),
+      uv_rect: [0.0, 0.0, 1.0, 1.0],
+      color: [
+        color.r as f32,
+        color.g as f32,
+        color.b as f32,
+        color.a as f32,
+      ],
+    }
+  }
+
+  // Part of the texture drawn, origin at the top left
+  pub fn with_uv(mut self, uv: Rect) -> Self {
+    self.uv_rect = [uv.min.x, uv.min.y, uv.max.x, uv.max.y];
+    self
+  }
+
+  const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+    0 => Float32x4,
+    1 => Float32x4,
+    2 => Float32x4,
+    3 => Float32x4,
+    4 => Float32x4,
+    5 => Float32x4,
+  ];
+
+  pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+      array_stride: std::mem::size_of::<Self>() as u64,
+      step_mode: wgpu::VertexStepMode::Instance,
+      attributes: &Self::ATTRIBUTES,
+    }
+  }
+}
+
+// Everything the renderer needs from a sprite entity for one frame
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ExtractedSprite {
+  pub texture: u64,
+  pub layer: i32,
+  pub instance: SpriteInstance,
+}
+
+// A run of instances sharing a texture, drawn with a single call
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SpriteBatch {
+  pub texture: u64,
+  pub instances: Range<u32>,
+  // Layer of the last instance, the highest one in the batch
+  pub layer: i32,
+}
+
+// Sorts by layer then texture, the sort is stable so sprites keep their entity order within a batch.
+// A batch never spans one of the given layers, so whatever is drawn on it can go between batches
+pub(crate) fn batch_sprites(sprites: &mut [ExtractedSprite], splits: &[i32]) -> Vec<SpriteBatch> {
+  sprites.sort_by_key(|sprite| (sprite.layer, sprite.texture));
+  let mut splits = splits.to_vec();
+  splits.sort_unstable();
+
+  let mut batches: Vec<SpriteBatch> = Vec::new();
+  for (index, sprite) in sprites.iter().enumerate() {
+    let index = index as u32;
+    match batches.last_mut() {
+      Some(batch)
+        if batch.texture == sprite.texture
+          && splits
+            .get(splits.partition_point(|&split| split < batch.layer))
+            .is_none_or(|&split| split >= sprite.layer) =>
+      {
+        batch.instances.end = index + 1;
+        batch.layer = sprite.layer;
+      }
+      _ => batches.push(SpriteBatch {
+        texture: sprite.texture,
+        instances: index..index + 1,
+        layer: sprite.layer,
+      }),
+    }
+  }
+  batches
+}
+
+struct GpuTexture {
+  // Kept alive for the views
+  _texture: wgpu::Texture,
+  view: wgpu::TextureView,
+  bind_group: wgpu::BindGroup,
+}
+
+pub(crate) struct SpriteRenderer {
+  texture_layout: wgpu::BindGroupLayout,
+  pipeline_layout: wgpu::PipelineLayout,
+  shader: wgpu::ShaderModule,
+  sampler: wgpu::Sampler,
+  // Pipelines depend on the format of the target
+  pipelines: HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>,
+  textures: HashMap<u64, GpuTexture>,
+  instances: Option<wgpu::Buffer>,
+}
+
+impl SpriteRenderer {
+  pub fn new(device: &wgpu::Device, camera_layout: &wgpu::BindGroupLayout) -> Self {
+    let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("p1 sprite texture"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+          },
+          count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+          binding: 1,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+          count: None,
+        },
+      ],
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("p1 sprites"),
+      bind_group_layouts: &[camera_layout, &texture_layout],
+      push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(wgpu::include_wgsl!("sprite.wgsl"));
+    // Nearest keeps pixel art crisp, which is most of what 2D tools draw
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+      label: Some("p1 sprite sampler"),
+      mag_filter: wgpu::FilterMode::Nearest,
+      min_filter: wgpu::FilterMode::Nearest,
+      ..Default::default()
+    });
+
+    Self {
+      texture_layout,
+      pipeline_layout,
+      shader,
+      sampler,
+      pipelines: HashMap::new(),
+      textures: HashMap::new(),
+      instances: None,
+    }
+  }
+
+  fn pipeline(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+    if self.pipelines.contains_key(&format) {
+      return;
+    }
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+      label: Some("p1 sprites"),
+      layout: Some(&self.pipeline_layout),
+      vertex: wgpu::VertexState {
+        module: &self.shader,
+        entry_point: Some("vs_main"),
+        compilation_options: Default::default(),
+        buffers: &[SpriteInstance::layout()],
+      },
+      fragment: Some(wgpu::FragmentState {
+        module: &self.shader,
+        entry_point: Some("fs_main"),
+        compilation_options: Default::default(),
+        targets: &[Some(wgpu::ColorTargetState {
+          format,
+          blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+          write_mask: wgpu::ColorWrites::ALL,
+        })],
+      }),
+      primitive: wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleStrip,
+        ..Default::default()
+      },
+      depth_stencil: None,
+      multisample: wgpu::MultisampleState::default(),
+      multiview: None,
+      cache: None,
+    });
+    self.pipelines.insert(format, pipeline);
+  }
+
+  fn upload_texture(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, id: u64, image: &Image) {
+    let size = wgpu::Extent3d {
+      width: image.width.max(1),
+      height: image.height.max(1),
+      depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+      label: Some("p1 sprite texture"),
+      size,
+      mip_level_count: 1,
+      sample_count: 1,
+      dimension: wgpu::TextureDimension::D2,
+      format: wgpu::TextureFormat::Rgba8UnormSrgb,
+      usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+      view_formats: &[],
+    });
+    if image.width > 0 && image.height > 0 {
+      queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+          texture: &texture,
+          mip_level: 0,
+          origin: wgpu::Origin3d::ZERO,
+          aspect: wgpu::TextureAspect::All,
+        },
+        &image.data,
+        wgpu::TexelCopyBufferLayout {
+          offset: 0,
+          bytes_per_row: Some(image.width * 4),
+          rows_per_image: Some(image.height),
+        },
+        size,
+      );
+    }
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("p1 sprite texture"),
+      layout: &self.texture_layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: wgpu::BindingResource::TextureView(&view),
+        },
+        wgpu::BindGroupEntry {
+          binding: 1,
+          resource: wgpu::BindingResource::Sampler(&self.sampler),
+        },
+      ],
+    });
+    self.textures.insert(
+      id,
+      GpuTexture {
+        _texture: texture,
+        view,
+        bind_group,
+      },
+    );
+  }
+
+  // Textures are shared with materials, which sample them through their own bind groups
+  pub fn texture_view(
+    &mut self,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    id: u64,
+    images: &Assets<Image>,
+  ) -> Option<&wgpu::TextureView> {
+    if !self.textures.contains_key(&id) {
+      self.upload_texture(device, queue, id, images.get_by_id(id)?);
+    }
+    self.textures.get(&id).map(|texture| &texture.view)
+  }
+
+  // Drops the GPU copy of a texture, it's uploaded again the next time a sprite uses it
+  pub fn invalidate_texture(&mut self, id: u64) {
+    self.textures.remove(&id);
+  }
+
+  // Uploads what the sprites need and returns the batches to draw
+  pub fn prepare(
+    &mut self,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+    sprites: &mut [ExtractedSprite],
+    splits: &[i32],
+    images: &Assets<Image>,
+  ) -> Vec<SpriteBatch> {
+    self
+      .textures
+      .retain(|id, _| images.get_by_id(*id).is_some());
+    if sprites.is_empty() {
+      return Vec::new();
+    }
+
+    self.pipeline(device, format);
+    for sprite in sprites.iter() {
+      if self.textures.contains_key(&sprite.texture) {
+        continue;
+      }
+      if let Some(image) = images.get_by_id(sprite.texture) {
+        self.upload_texture(device, queue, sprite.texture, image);
+      }
+    }
+
+    let batches = batch_sprites(sprites, splits);
+    let instances: Vec<_> = sprites.iter().map(|sprite| sprite.instance).collect();
+    let bytes: &[u8] = bytemuck::cast_slice(&instances);
+    if self
+      .instances
+      .as_ref()
+      .is_none_or(|buffer| buffer.size() < bytes.len() as u64)
+    {
+      // Grows by powers of two so a slowly growing scene doesn't reallocate every frame
+      self.instances = Some(device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("p1 sprite instances"),
+        size: (bytes.len() as u64).next_power_of_two(),
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+      }));
+    }
+    queue.write_buffer(self.instances.as_ref().unwrap(), 0, bytes);
+    batches
+  }
+
+  // Every camera draws every batch, in camera order
+  pub fn draw(
+    &self,
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    format: wgpu::TextureFormat,
+    cameras: &[CameraView],
+    batches: &[SpriteBatch],
+  ) {
+    if batches.is_empty() {
+      return;
+    }
+
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("p1 sprites"),
+      color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+        view,
+        resolve_target: None,
+        ops: wgpu::Operations {
+          load: wgpu::LoadOp::Load,
+          store: wgpu::StoreOp::Store,
+        },
+      })],
+      depth_stencil_attachment: None,
+      timestamp_writes: None,
+      occlusion_query_set: None,
+    });
+
+    for camera in cameras {
+      if camera.bind_group.is_none() || camera.viewport.2 < 1.0 || camera.viewport.3 < 1.0 {
+        continue;
+      }
+      camera.apply(&mut pass, 0);
+      self.draw_batches(&mut pass, format, batches);
+    }
+  }
+
+  // Records batches into a pass the camera was already applied to
+  pub fn draw_batches(
+    &self,
+    pass: &mut wgpu::RenderPass,
+    format: wgpu::TextureFormat,
+    batches: &[SpriteBatch],
+  ) {
+    let (Some(pipeline), Some(instances)) = (self.pipelines.get(&format), self.instances.as_ref())
+    else {
+      return;
+    };
+    if batches.is_empty() {
+      return;
+    }
+
+    pass.set_pipeline(pipeline);
+    pass.set_vertex_buffer(0, instances.slice(..));
+    for batch in batches {
+      let Some(texture) = self.textures.get(&batch.texture) else {
+        continue;
+      };
+      pass.set_bind_group(1, &texture.bind_group, &[]);
+      pass.draw(0..4, batch.instances.clone());
+    }
+  }
+}
@@ -0,0 +1,45 @@
+struct Camera {
+  view_projection: mat4x4<f32>,
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  position: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(1) @binding(0) var sprite_texture: texture_2d<f32>;
+@group(1) @binding(1) var sprite_sampler: sampler;
+
+struct Instance {
+  @location(0) model_0: vec4<f32>,
+  @location(1) model_1: vec4<f32>,
+  @location(2) model_2: vec4<f32>,
+  @location(3) model_3: vec4<f32>,
+  // min.xy then max.xy, already flipped
+  @location(4) uv_rect: vec4<f32>,
+  @location(5) color: vec4<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+  @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: Instance) -> VertexOutput {
+  // Triangle strip over the unit quad, the model matrix places it
+  let corner = vec2<f32>(f32(index & 1u), f32((index >> 1u) & 1u));
+  let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+
+  var out: VertexOutput;
+  out.position = camera.view_projection * model * vec4<f32>(corner, 0.0, 1.0);
+  // Textures start at the top, quads at the bottom
+  out.uv = mix(instance.uv_rect.xy, instance.uv_rect.zw, vec2<f32>(corner.x, 1.0 - corner.y));
+  out.color = instance.color;
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  return textureSample(sprite_texture, sprite_sampler, in.uv) * in.color;
+}
@@ -0,0 +1,90 @@
+use macros::Component;
+use winit::dpi::PhysicalSize;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{Fullscreen, Window, WindowAttributes, WindowId};
+
+use crate::ecs::Component;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+  Windowed,
+  Borderless,
+  // Uses the first video mode of the primary monitor
+  Exclusive,
+}
+
+// Spawning an entity with this component opens a window for it once the event loop runs
+#[derive(Component, Clone, Debug)]
+pub struct WindowConfig {
+  pub title: String,
+  // Physical pixels
+  pub size: (u32, u32),
+  pub resizable: bool,
+  pub vsync: bool,
+  pub fullscreen: FullscreenMode,
+}
+
+impl WindowConfig {
+  pub fn new(title: &str, width: u32, height: u32) -> Self {
+    Self {
+      title: title.to_owned(),
+      size: (width, height),
+      resizable: true,
+      vsync: true,
+      fullscreen: FullscreenMode::Windowed,
+    }
+  }
+
+  pub(crate) fn attributes(&self, event_loop: &ActiveEventLoop) -> WindowAttributes {
+    let fullscreen = match self.fullscreen {
+      FullscreenMode::Windowed => None,
+      FullscreenMode::Borderless => Some(Fullscreen::Borderless(None)),
+      FullscreenMode::Exclusive => event_loop
+        .primary_monitor()
+        .and_then(|monitor| monitor.video_modes().next())
+        .map(Fullscreen::Exclusive),
+    };
+
+    Window::default_attributes()
+      .with_title(self.title.clone())
+      .with_inner_size(PhysicalSize::new(self.size.0, self.size.1))
+      .with_resizable(self.resizable)
+      .with_fullscreen(fullscreen)
+  }
+}
+
+impl Default for WindowConfig {
+  fn default() -> Self {
+    Self::new("P1", 1280, 720)
+  }
+}
+
+// Added by the engine once the window of an entity is open, kept up to date with window events
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WindowState {
+  pub id: WindowId,
+  pub width: u32,
+  pub height: u32,
+  pub scale_factor: f64,
+  pub focused: bool,
+}
+
+impl WindowState {
+  pub fn new(id: WindowId, width: u32, height: u32, scale_factor: f64) -> Self {
+    Self {
+      id,
+      width,
+      height,
+      scale_factor,
+      focused: false,
+    }
+  }
+
+  // Size in logical pixels, which is what UI should be laid out with
+  pub fn logical_size(&self) -> (f64, f64) {
+    (
+      self.width as f64 / self.scale_factor,
+      self.height as f64 / self.scale_factor,
+    )
+  }
+}
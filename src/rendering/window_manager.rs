@@ -1,26 +1,238 @@
-use crate::event::EventManager;
+use super::{RenderMode, RenderTarget, Renderer, WindowConfig, WindowState};
+use crate::error::{P1Error, WindowError};
+use crate::event::builtin::{Exit, Focused, Resized, Resume, ScaleFactorChanged, WindowClosed};
+use crate::event::InputEvent;
+use crate::p1::P1;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use parking_lot::RwLock;
 use winit::application::ApplicationHandler;
 use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::event_loop::ActiveEventLoop;
 use winit::window::{Window, WindowId};
 
-struct WindowHandler {
-  event_manager: Arc<RwLock<EventManager>>,
-  window: Option<Window>,
+// Bridges winit's callbacks to the engine, lives for as long as P1::run_windowed
+pub(crate) struct WindowHandler<'engine> {
+  engine: &'engine mut P1,
+  // Window events are routed to the entity owning the window
+  entities: HashMap<WindowId, u32>,
+  windows: HashMap<u32, Arc<Window>>,
+  // Callbacks can't return errors, the first one is kept and stops the event loop
+  error: Option<P1Error>,
+  exited: bool,
 }
 
-impl ApplicationHandler for WindowHandler {
+impl<'engine> WindowHandler<'engine> {
+  pub fn new(engine: &'engine mut P1) -> Self {
+    Self {
+      engine,
+      entities: HashMap::new(),
+      windows: HashMap::new(),
+      error: None,
+      exited: false,
+    }
+  }
+
+  pub fn into_result(self) -> Result<(), P1Error> {
+    match self.error {
+      Some(error) => Err(error),
+      None => Ok(()),
+    }
+  }
+
+  fn fail(&mut self, event_loop: &ActiveEventLoop, error: P1Error) {
+    self.error.get_or_insert(error);
+    event_loop.exit();
+  }
+
+  // The last window closing and the last WindowConfig going away can both happen in one iteration
+  fn exit(&mut self) -> Result<(), P1Error> {
+    if !self.exited {
+      self.exited = true;
+      self.engine.emit::<Exit>(Exit)?;
+    }
+    Ok(())
+  }
+
+  pub fn track(&mut self, window_id: WindowId, entity: u32) {
+    self.entities.insert(window_id, entity);
+  }
+
+  fn untrack(&mut self, entity: u32) {
+    self.entities.retain(|_, e| *e != entity);
+    self.windows.remove(&entity);
+    if let Some(mut renderer) = self.engine.resource_mut::<Renderer>() {
+      renderer.detach_window(entity);
+    }
+  }
+
+  // The renderer is only created with the first window so it can pick an adapter able to present
+  fn attach_surface(
+    &mut self,
+    entity: u32,
+    window: Arc<Window>,
+    vsync: bool,
+  ) -> Result<(), P1Error> {
+    if self.engine.resource::<Renderer>().is_none() {
+      self
+        .engine
+        .insert_resource(Renderer::new(RenderMode::Windowed)?);
+    }
+    self
+      .engine
+      .resource_mut::<Renderer>()
+      .unwrap()
+      .attach_window(entity, window, vsync)?;
+    Ok(())
+  }
+
+  // Opens windows for new WindowConfig entities and closes the ones whose entity went away
+  // Returns whether no windows are left
+  fn sync_windows(&mut self, event_loop: &ActiveEventLoop) -> Result<bool, P1Error> {
+    let configured = self.engine.entities_with::<WindowConfig>();
+
+    let stale: Vec<_> = self
+      .entities
+      .values()
+      .copied()
+      .filter(|entity| !configured.contains(entity))
+      .collect();
+    for entity in stale {
+      self.untrack(entity);
+    }
+
+    for entity in configured {
+      if self.windows.contains_key(&entity) {
+        continue;
+      }
+
+      let (attributes, vsync) = self
+        .engine
+        .with_component::<WindowConfig, _>(entity, |config| {
+          (config.attributes(event_loop), config.vsync)
+        })
+        .unwrap();
+      let window = event_loop
+        .create_window(attributes)
+        .map_err(WindowError::from)?;
+      let size = window.inner_size();
+      let state = WindowState::new(
+        window.id(),
+        size.width,
+        size.height,
+        window.scale_factor(),
+      );
+
+      // A resumed application gets new windows, the old state goes with the old window
+      self.engine.remove_component::<WindowState>(entity)?;
+      self.engine.add_component(entity, state)?;
+      self.track(window.id(), entity);
+      let window = Arc::new(window);
+      self.attach_surface(entity, window.clone(), vsync)?;
+      self.windows.insert(entity, window);
+    }
+
+    Ok(self.entities.is_empty())
+  }
+
+  // Returns whether the event loop should stop
+  pub fn handle_window_event(
+    &mut self,
+    window_id: WindowId,
+    event: &WindowEvent,
+  ) -> Result<bool, P1Error> {
+    let Some(&window) = self.entities.get(&window_id) else {
+      return Ok(false);
+    };
+
+    for input in InputEvent::from_window_event(event) {
+      self.engine.send_input(input);
+    }
+
+    match event {
+      WindowEvent::CloseRequested => {
+        self.untrack(window);
+        self.engine.despawn(window)?;
+        self.engine.emit::<WindowClosed>(WindowClosed { window })?;
+
+        if self.entities.is_empty() {
+          self.exit()?;
+          return Ok(true);
+        }
+      }
+      WindowEvent::Resized(size) => {
+        self
+          .engine
+          .with_component_mut::<WindowState, _>(window, |state| {
+            state.width = size.width;
+            state.height = size.height;
+          });
+        if let Some(mut renderer) = self.engine.resource_mut::<Renderer>() {
+          renderer.resize(window, size.width, size.height);
+        }
+        self.engine.update_anchors();
+        self.engine.emit::<Resized>(Resized {
+          window,
+          width: size.width,
+          height: size.height,
+        })?
+      }
+      WindowEvent::Focused(focused) => {
+        self
+          .engine
+          .with_component_mut::<WindowState, _>(window, |state| state.focused = *focused);
+        self.engine.emit::<Focused>(Focused {
+          window,
+          focused: *focused,
+        })?
+      }
+      WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+        self
+          .engine
+          .with_component_mut::<WindowState, _>(window, |state| {
+            state.scale_factor = *scale_factor
+          });
+        self.engine.update_anchors();
+        self
+          .engine
+          .emit::<ScaleFactorChanged>(ScaleFactorChanged {
+            window,
+            scale_factor: *scale_factor,
+          })?
+      }
+      WindowEvent::RedrawRequested => {
+        self.engine.render(RenderTarget::Window(window))?;
+      }
+      _ => {}
+    }
+
+    Ok(false)
+  }
+}
+
+impl ApplicationHandler for WindowHandler<'_> {
   fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-    self.window = Some(
-      event_loop
-        .create_window(Window::default_attributes())
-        .unwrap(),
-    );
-    // Do an event here
+    if self.engine.entities_with::<WindowConfig>().is_empty() {
+      let entity = self.engine.create_entity();
+      if let Err(e) = self.engine.add_component(entity, WindowConfig::default()) {
+        return self.fail(event_loop, e.into());
+      }
+    }
+
+    // Windows have to be recreated after a suspension on some platforms
+    let windows: Vec<_> = self.windows.keys().copied().collect();
+    for entity in windows {
+      self.untrack(entity);
+    }
+    self.entities.clear();
+    if let Err(e) = self.sync_windows(event_loop) {
+      return self.fail(event_loop, e);
+    }
+
+    if let Err(e) = self.engine.emit::<Resume>(Resume) {
+      self.fail(event_loop, e.into());
+    }
   }
 
   fn window_event(
@@ -29,23 +241,32 @@ impl ApplicationHandler for WindowHandler {
     window_id: WindowId,
     event: WindowEvent,
   ) {
-    match event {
-      WindowEvent::CloseRequested => {
+    match self.handle_window_event(window_id, &event) {
+      Ok(true) => event_loop.exit(),
+      Ok(false) => {}
+      Err(e) => self.fail(event_loop, e),
+    }
+  }
+
+  // Once winit is done delivering events for this iteration, the engine moves on to the next frame
+  fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+    if let Err(e) = self.engine.update() {
+      return self.fail(event_loop, e);
+    }
+
+    match self.sync_windows(event_loop) {
+      Ok(true) => {
+        if let Err(e) = self.exit() {
+          return self.fail(event_loop, e);
+        }
         event_loop.exit();
-        // Do an event here maybe?
-      }
-      WindowEvent::RedrawRequested => {
-        // TODO
-        // Cameras will need to handle events here as well
       }
-      WindowEvent::KeyboardInput {
-        device_id,
-        event,
-        is_synthetic,
-      } => {
-        // Do an event here
-      }
-      _ => {}
+      Ok(false) => {}
+      Err(e) => return self.fail(event_loop, e),
+    }
+
+    for window in self.windows.values() {
+      window.request_redraw();
     }
   }
 }
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use super::Prefab;
+use crate::asset::Handle;
+
+// One instantiation of a prefab, with the fields it overrides
+#[derive(Clone, Debug)]
+pub struct PrefabInstance {
+  pub(crate) id: u64,
+  pub(crate) prefab: Handle<Prefab>,
+  pub(crate) overrides: BTreeMap<String, Value>,
+}
+
+impl PrefabInstance {
+  // Also the id of the PrefabInstantiated event emitted once it's spawned
+  pub fn id(&self) -> u64 {
+    self.id
+  }
+
+  pub fn with(&mut self, field: &str, value: impl Into<Value>) -> &mut Self {
+    self.overrides.insert(field.to_owned(), value.into());
+    self
+  }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Command {
+  Instantiate(PrefabInstance),
+  Despawn(u32),
+}
+
+// Changes to the world that systems can ask for, applied by P1::update on the main thread in the order they were queued
+// Instances of prefabs that are still loading wait for them
+#[derive(Default)]
+pub struct Commands {
+  queue: Vec<Command>,
+  next_instance: u64,
+}
+
+impl Commands {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn instantiate(&mut self, prefab: Handle<Prefab>) -> &mut PrefabInstance {
+    self.queue.push(Command::Instantiate(PrefabInstance {
+      id: self.next_instance,
+      prefab,
+      overrides: BTreeMap::new(),
+    }));
+    self.next_instance += 1;
+    match self.queue.last_mut() {
+      Some(Command::Instantiate(instance)) => instance,
+      _ => unreachable!(),
+    }
+  }
+
+  pub fn despawn(&mut self, entity: u32) {
+    self.queue.push(Command::Despawn(entity));
+  }
+
+  pub fn len(&self) -> usize {
+    self.queue.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.queue.is_empty()
+  }
+
+  pub(crate) fn take(&mut self) -> Vec<Command> {
+    std::mem::take(&mut self.queue)
+  }
+
+  // Puts back what couldn't be applied yet, ahead of what was queued since
+  pub(crate) fn requeue(&mut self, mut commands: Vec<Command>) {
+    commands.append(&mut self.queue);
+    self.queue = commands;
+  }
+}
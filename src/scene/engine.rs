@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{Command, Commands, Prefab, Scene, SceneEntity, Snapshot};
+use crate::asset::{AssetServer, Assets, LoadState};
+use crate::ecs::Component;
+use crate::error::{DataError, P1Error, SceneError, SnapshotError};
+use crate::event::builtin::{PrefabFailed, PrefabInstantiated};
+use crate::p1::P1;
+use crate::spatial::Children;
+
+use bytemuck::Pod;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+// Scenes, prefabs and snapshots, read and written through the component registry
+impl P1 {
+  // Every entity with its serializable components, the others are left out
+  // The hierarchy is kept through the children of every entity
+  pub fn save_scene(&self) -> Result<String, SceneError> {
+    let components = self.component_manager.read();
+    let mut entities = Vec::new();
+    for entity in self.entity_manager.entities() {
+      let mut saved = SceneEntity {
+        id: entity,
+        ..Default::default()
+      };
+      for type_id in self.entity_manager.components(entity)? {
+        let Some(info) = self.registry.get(type_id) else {
+          continue;
+        };
+        if let Some(value) = info.read_json(&components, entity) {
+          saved.components.insert(info.name().to_owned(), value?);
+        }
+      }
+      if let Some(container) = components.get_container::<Children>() {
+        if let Some(children) = container.get::<Children>(&entity) {
+          saved.children = children.0.clone();
+        }
+      }
+      entities.push(saved);
+    }
+    Scene::new(entities).to_json()
+  }
+
+  // Spawns the entities of the scene next to the ones already there, under new ids
+  // Returns the new id of every entity of the scene
+  pub fn load_scene(&mut self, json: &str) -> Result<HashMap<u32, u32>, SceneError> {
+    self.spawn_scene(&Scene::from_json(json)?)
+  }
+
+  // Nothing is spawned unless every component can be read
+  // Entity ids held by components are remapped along with the entities
+  // The ones from outside the scene are kept
+  pub fn spawn_scene(&mut self, scene: &Scene) -> Result<HashMap<u32, u32>, SceneError> {
+    scene.validate()?;
+
+    let mut loaded = Vec::with_capacity(scene.entities.len());
+    for entity in &scene.entities {
+      let mut components = Vec::with_capacity(entity.components.len());
+      for (name, value) in &entity.components {
+        let info = *self
+          .registry
+          .by_name(name)?
+          .ok_or_else(|| SceneError::UnknownComponent {
+            entity: entity.id,
+            name: name.clone(),
+          })?;
+        let serde = info
+          .serde()
+          .ok_or_else(|| SceneError::NotSerializable(name.clone()))?;
+        let component =
+          (serde.deserialize)(value.clone()).map_err(|source| SceneError::Component {
+            entity: entity.id,
+            name: name.clone(),
+            source,
+          })?;
+        components.push((info, component));
+      }
+      loaded.push(components);
+    }
+
+    let ids: HashMap<u32, u32> = scene
+      .entities
+      .iter()
+      .map(|entity| (entity.id, self.create_entity()))
+      .collect();
+    for (entity, components) in scene.entities.iter().zip(loaded) {
+      for (info, mut component) in components {
+        info.map_entities(&mut component, &mut |id| {
+          ids.get(&id).copied().unwrap_or(id)
+        });
+        self.add_component_erased(ids[&entity.id], &info, component)?;
+      }
+    }
+    for entity in &scene.entities {
+      for child in &entity.children {
+        self.set_parent(ids[child], ids[&entity.id])?;
+      }
+    }
+    Ok(ids)
+  }
+
+  // Spawns one instance of the prefab with its fields overridden
+  // See Commands::instantiate to do it from systems
+  pub fn instantiate(
+    &mut self,
+    prefab: &Prefab,
+    overrides: &BTreeMap<String, Value>,
+  ) -> Result<HashMap<u32, u32>, SceneError> {
+    self.spawn_scene(&prefab.apply(overrides)?)
+  }
+
+  // Instances that fail are dropped with a PrefabFailed, the others are still spawned
+  pub(crate) fn apply_commands(&mut self) -> Result<(), P1Error> {
+    let Some(commands) = self
+      .resource_manager
+      .get_mut::<Commands>()
+      .map(|mut commands| commands.take())
+    else {
+      return Ok(());
+    };
+
+    let mut waiting = Vec::new();
+    for command in commands {
+      match command {
+        Command::Instantiate(instance) => {
+          let scene = {
+            let Some(prefabs) = self.resource_manager.get::<Assets<Prefab>>() else {
+              waiting.push(Command::Instantiate(instance));
+              continue;
+            };
+            match prefabs.get(&instance.prefab) {
+              Some(prefab) => prefab.apply(&instance.overrides),
+              None => {
+                // Dropped along with the prefab that failed to load
+                let failed = self
+                  .resource_manager
+                  .get::<AssetServer>()
+                  .and_then(|server| server.load_state(&instance.prefab))
+                  == Some(LoadState::Failed);
+                if failed {
+                  self
+                    .event_manager
+                    .write()
+                    .emit_with::<PrefabFailed>(PrefabFailed {
+                      id: instance.id,
+                      error: "The prefab failed to load.".to_owned(),
+                    })?;
+                } else {
+                  waiting.push(Command::Instantiate(instance));
+                }
+                continue;
+              }
+            }
+          };
+          let spawned = scene.and_then(|scene| {
+            let ids = self.spawn_scene(&scene)?;
+            Ok(
+              scene
+                .entities
+                .iter()
+                .map(|entity| ids[&entity.id])
+                .collect(),
+            )
+          });
+          let mut event_manager = self.event_manager.write();
+          match spawned {
+            Ok(entities) => event_manager.emit_with::<PrefabInstantiated>(PrefabInstantiated {
+              id: instance.id,
+              entities,
+            })?,
+            Err(error) => event_manager.emit_with::<PrefabFailed>(PrefabFailed {
+              id: instance.id,
+              error: error.to_string(),
+            })?,
+          }
+        }
+        Command::Despawn(entity) => {
+          if self.contains_entity(entity) {
+            self.despawn(entity)?;
+          }
+        }
+      }
+    }
+
+    if let Some(mut commands) = self.resource_manager.get_mut::<Commands>() {
+      commands.requeue(waiting);
+    }
+    Ok(())
+  }
+
+  // Components snapshots hold as their bytes, they have to be Pod
+  pub fn register_pod_component<C: Component + Pod>(&mut self) {
+    self.registry.register_pod::<C>();
+  }
+
+  // Components snapshots hold encoded with postcard, compact and stable across platforms
+  pub fn register_postcard_component<C: Component + Serialize + DeserializeOwned>(&mut self) {
+    self.registry.register_postcard::<C>();
+  }
+
+  // Every entity, with the components registered with a binary encoding
+  pub fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
+    Snapshot::write(
+      self.entity_manager.next_id(),
+      &self.entity_manager.entities(),
+      &self.registry,
+      &self.component_manager.read(),
+    )
+  }
+
+  // Entities that weren't there when the snapshot was taken are despawned
+  // The ones that were come back under their ids
+  // Components with a binary encoding are put back as they were, the others are left alone
+  // Taking a snapshot right after gives back the same bytes
+  pub fn restore_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+    let contents = snapshot.contents(&self.registry)?;
+
+    for entity in self.entity_manager.entities() {
+      if contents.entities.binary_search(&entity).is_err() && self.contains_entity(entity) {
+        self.despawn(entity)?;
+      }
+    }
+    for entity in &contents.entities {
+      self.entity_manager.insert_entity(*entity);
+    }
+    self.entity_manager.set_next_id(contents.next_entity);
+
+    for column in contents.columns {
+      let type_id = column.info.type_id();
+      let owners = self.entity_manager.get_archetype(&[type_id]);
+      for entity in owners {
+        if column
+          .components
+          .binary_search_by_key(&entity, |(owner, _)| *owner)
+          .is_err()
+        {
+          self.remove_component_erased(entity, type_id)?;
+        }
+      }
+      for (entity, component) in column.components {
+        // Replaced in place when the entity still has one, which leaves the archetypes as they are
+        let replaced = {
+          let components = self.component_manager.read();
+          let container = components.get_container_mut_from_id(&type_id);
+          match container {
+            Some(mut container) if container.contains_key(&entity) => {
+              container
+                .replace_erased(entity, component)
+                .map_err(DataError::from)?;
+              None
+            }
+            _ => Some(component),
+          }
+        };
+        if let Some(component) = replaced {
+          self.add_component_erased(entity, &column.info, component)?;
+        }
+      }
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,429 @@
+mod commands;
+mod engine;
+mod prefab;
+mod scene;
+mod snapshot;
+
+pub use commands::{Commands, PrefabInstance};
+pub use prefab::{Prefab, PrefabField, PrefabLoader};
+pub use scene::{Scene, SceneEntity, SCENE_VERSION};
+pub use snapshot::{Snapshot, SnapshotDelta, SNAPSHOT_VERSION};
+
+pub(crate) use commands::Command;
+
+#[cfg(test)]
+mod tests {
+  use super::{Commands, Prefab, Scene, Snapshot, SnapshotDelta, SCENE_VERSION};
+  use crate::asset::{AssetServer, Assets};
+  use crate::ecs::{Component, MapEntities};
+  use crate::error::{SceneError, SnapshotError};
+  use crate::event::builtin::{PrefabFailed, PrefabInstantiated};
+  use crate::event::Clock;
+  use crate::p1::P1;
+  use crate::spatial::{Children, Transform};
+  use crate::testing::{temp_dir, update_until};
+  use crate::ui::Button;
+
+  use std::collections::BTreeMap;
+  use std::fs;
+
+  use bytemuck::{Pod, Zeroable};
+  use glam::Vec3;
+  use macros::Component;
+  use serde::{Deserialize, Serialize};
+  use serde_json::json;
+
+  #[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+  struct Health(u32);
+
+  #[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+  struct Target(u32);
+
+  #[repr(C)]
+  #[derive(Component, Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+  struct Velocity {
+    x: f32,
+    y: f32,
+  }
+
+  impl MapEntities for Target {
+    fn map_entities(&mut self, map: &mut dyn FnMut(u32) -> u32) {
+      self.0 = map(self.0);
+    }
+  }
+
+  // The turret aims at its barrel, whose offset and health can be changed per instance
+  const TURRET: &str = r#"{
+    "version": 1,
+    "entities": [
+      {"id": 10, "children": [11], "components": {"Health": 5, "Target": 11}},
+      {"id": 11, "components": {
+        "Transform": {"translation": [1.0, 0.0, 0.0], "rotation": [0.0, 0.0, 0.0, 1.0], "scale": [1.0, 1.0, 1.0]}
+      }}
+    ],
+    "fields": {
+      "health": {"entity": 10, "component": "Health", "path": ""},
+      "offset": {"entity": 11, "component": "Transform", "path": "/translation/0"}
+    }
+  }"#;
+
+  fn engine_with_health() -> P1 {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.register_component::<Health>();
+    engine.register_component::<Target>();
+    engine
+  }
+
+  #[test]
+  fn scenes_round_trip_under_new_ids() {
+    let mut engine = engine_with_health();
+    let root = engine.create_entity();
+    engine
+      .add_component(root, Transform::from_xyz(1.0, 2.0, 3.0))
+      .unwrap();
+    engine.add_component(root, Health(7)).unwrap();
+    // Not serializable, left out of the scene
+    engine.add_component(root, Button).unwrap();
+    let first = engine.create_entity();
+    let second = engine.create_entity();
+    engine.set_parent(second, root).unwrap();
+    engine.set_parent(first, root).unwrap();
+    let json = engine.save_scene().unwrap();
+    let scene = Scene::from_json(&json).unwrap();
+    assert_eq!(scene.version, SCENE_VERSION);
+    assert_eq!(scene.entities.len(), 3);
+    assert_eq!(scene.entities[0].components.len(), 2);
+
+    // Loaded next to entities that already use the saved ids
+    let mut other = engine_with_health();
+    for _ in 0..5 {
+      other.create_entity();
+    }
+    let ids = other.load_scene(&json).unwrap();
+    assert_eq!(other.entity_count(), 8);
+    let new_root = ids[&root];
+    assert!(new_root >= 5);
+    assert_eq!(
+      other.with_component::<Transform, _>(new_root, |transform| transform.translation),
+      Some(Vec3::new(1.0, 2.0, 3.0))
+    );
+    assert_eq!(
+      other.with_component::<Health, _>(new_root, Clone::clone),
+      Some(Health(7))
+    );
+    assert!(!other.has_component::<Button>(new_root).unwrap());
+    // Children keep their order
+    let children = other.with_component::<Children, _>(new_root, |children| {
+      children.iter().copied().collect::<Vec<_>>()
+    });
+    assert_eq!(children, Some(vec![ids[&second], ids[&first]]));
+    assert_eq!(other.parent(ids[&first]), Some(new_root));
+
+    // Saving the loaded world gives back the same scene, up to the ids
+    let mut resaved = Scene::from_json(&other.save_scene().unwrap()).unwrap();
+    resaved.entities.retain(|entity| entity.id >= 5);
+    assert_eq!(resaved.entities[0].components, scene.entities[0].components);
+  }
+
+  #[test]
+  fn broken_scenes_spawn_nothing() {
+    let mut engine = engine_with_health();
+    let load = |engine: &mut P1, json: &str| engine.load_scene(json).unwrap_err();
+
+    assert!(matches!(
+      load(
+        &mut engine,
+        r#"{"version":1,"entities":[{"id":3,"components":{"Mana":5}}]}"#
+      ),
+      SceneError::UnknownComponent { entity: 3, name } if name == "Mana"
+    ));
+    assert!(matches!(
+      load(
+        &mut engine,
+        r#"{"version":1,"entities":[{"id":0,"components":{"Health":"full"}}]}"#
+      ),
+      SceneError::Component { entity: 0, name, .. } if name == "Health"
+    ));
+    assert!(matches!(
+      load(&mut engine, r#"{"version":2,"entities":{}}"#),
+      SceneError::UnsupportedVersion {
+        found: 2,
+        supported: 1
+      }
+    ));
+    assert!(matches!(
+      load(&mut engine, r#"{"entities":[]}"#),
+      SceneError::MissingVersion
+    ));
+    assert!(matches!(
+      load(
+        &mut engine,
+        r#"{"version":1,"entities":[{"id":0,"children":[1]},{"id":1,"children":[0]}]}"#
+      ),
+      SceneError::InvalidHierarchy(_)
+    ));
+    assert!(matches!(
+      load(
+        &mut engine,
+        r#"{"version":1,"entities":[{"id":0,"children":[4]}]}"#
+      ),
+      SceneError::MissingEntity {
+        entity: 0,
+        target: 4
+      }
+    ));
+    assert!(matches!(
+      load(
+        &mut engine,
+        r#"{"version":1,"entities":[{"id":0},{"id":0}]}"#
+      ),
+      SceneError::DuplicateEntity(0)
+    ));
+    assert_eq!(engine.entity_count(), 0);
+  }
+
+  #[test]
+  fn prefab_instances_override_fields_and_remap_references() {
+    let mut engine = engine_with_health();
+    assert!(engine
+      .component_registry()
+      .by_name("Target")
+      .unwrap()
+      .unwrap()
+      .maps_entities());
+    let turret = engine
+      .resource_mut::<Assets<Prefab>>()
+      .unwrap()
+      .add(Prefab::from_json(TURRET).unwrap());
+    {
+      let mut commands = engine.resource_mut::<Commands>().unwrap();
+      commands.instantiate(turret.clone());
+      let id = commands
+        .instantiate(turret)
+        .with("health", 9)
+        .with("offset", 4.0)
+        .id();
+      assert_eq!(id, 1);
+    }
+    engine.update().unwrap();
+    assert!(engine.resource::<Commands>().unwrap().is_empty());
+    assert_eq!(engine.entity_count(), 4);
+
+    let instantiated = engine.latest_event::<PrefabInstantiated>().unwrap();
+    assert_eq!(instantiated.id, 1);
+    let [root, barrel] = instantiated.entities[..] else {
+      panic!("{:?}", instantiated.entities);
+    };
+    assert_eq!(
+      engine.with_component::<Health, _>(root, Clone::clone),
+      Some(Health(9))
+    );
+    assert_eq!(
+      engine.with_component::<Target, _>(root, |target| target.0),
+      Some(barrel)
+    );
+    assert_eq!(engine.parent(barrel), Some(root));
+    assert_eq!(
+      engine.with_component::<Transform, _>(barrel, |transform| transform.translation),
+      Some(Vec3::new(4.0, 0.0, 0.0))
+    );
+    // The first instance kept the defaults and points at its own barrel
+    let roots = engine.entities_with::<Target>();
+    let first = roots
+      .iter()
+      .copied()
+      .find(|entity| *entity != root)
+      .unwrap();
+    assert_eq!(
+      engine.with_component::<Health, _>(first, Clone::clone),
+      Some(Health(5))
+    );
+    let first_barrel = engine.with_component::<Target, _>(first, |target| target.0);
+    assert_ne!(first_barrel, Some(barrel));
+    assert_eq!(
+      first_barrel.and_then(|barrel| engine.parent(barrel)),
+      Some(first)
+    );
+
+    let prefab = Prefab::from_json(TURRET).unwrap();
+    let overrides = BTreeMap::from([("armor".to_owned(), json!(1))]);
+    assert!(matches!(
+      engine.instantiate(&prefab, &overrides),
+      Err(SceneError::UnknownField(name)) if name == "armor"
+    ));
+    let overrides = BTreeMap::from([("health".to_owned(), json!("full"))]);
+    assert!(matches!(
+      engine.instantiate(&prefab, &overrides),
+      Err(SceneError::Component { entity: 10, .. })
+    ));
+    assert_eq!(engine.entity_count(), 4);
+    assert!(matches!(
+      Prefab::from_json(&TURRET.replace("/translation/0", "/translation/5")),
+      Err(SceneError::InvalidField(name)) if name == "offset"
+    ));
+  }
+
+  #[test]
+  fn bad_prefab_instances_are_dropped_alone() {
+    let mut engine = engine_with_health();
+    let turret = engine
+      .resource_mut::<Assets<Prefab>>()
+      .unwrap()
+      .add(Prefab::from_json(TURRET).unwrap());
+    {
+      let mut commands = engine.resource_mut::<Commands>().unwrap();
+      commands.instantiate(turret.clone()).with("health", "full");
+      commands.instantiate(turret).with("health", 3);
+    }
+    engine.update().unwrap();
+    assert!(engine.resource::<Commands>().unwrap().is_empty());
+
+    let failed = engine.latest_event::<PrefabFailed>().unwrap();
+    assert_eq!(failed.id, 0);
+    let instantiated = engine.latest_event::<PrefabInstantiated>().unwrap();
+    assert_eq!(instantiated.id, 1);
+    assert_eq!(engine.entity_count(), 2);
+    assert_eq!(
+      engine.with_component::<Health, _>(instantiated.entities[0], Clone::clone),
+      Some(Health(3))
+    );
+  }
+
+  #[test]
+  fn prefab_instances_wait_for_their_file() {
+    let dir = temp_dir("prefabs");
+    fs::write(dir.join("turret.prefab"), TURRET).unwrap();
+
+    let mut engine = engine_with_health();
+    engine.insert_resource(AssetServer::new(&dir));
+    let turret = engine
+      .resource_mut::<AssetServer>()
+      .unwrap()
+      .load::<Prefab>("turret.prefab")
+      .unwrap();
+    engine
+      .resource_mut::<Commands>()
+      .unwrap()
+      .instantiate(turret)
+      .with("health", 2);
+
+    update_until(&mut engine, |engine| engine.entity_count() > 0);
+    let roots = engine.entities_with::<Health>();
+    assert_eq!(roots.len(), 1);
+    assert_eq!(
+      engine.with_component::<Health, _>(roots[0], Clone::clone),
+      Some(Health(2))
+    );
+    assert!(engine.resource::<Commands>().unwrap().is_empty());
+  }
+
+  fn engine_with_snapshots() -> P1 {
+    let mut engine = engine_with_health();
+    engine.register_postcard_component::<Health>();
+    engine.register_pod_component::<Velocity>();
+    engine
+  }
+
+  #[test]
+  fn snapshots_restore_the_world_byte_for_byte() {
+    let mut engine = engine_with_snapshots();
+    let first = engine.create_entity();
+    engine.add_component(first, Health(3)).unwrap();
+    engine
+      .add_component(first, Velocity { x: 1.0, y: -1.0 })
+      .unwrap();
+    // Left out of snapshots, and left alone by restoring them
+    engine.add_component(first, Button).unwrap();
+    let second = engine.create_entity();
+    engine.add_component(second, Health(8)).unwrap();
+    let snapshot = engine.snapshot().unwrap();
+
+    engine.with_component_mut::<Health, _>(first, |health| health.0 = 0);
+    engine.remove_component::<Velocity>(first).unwrap();
+    engine
+      .add_component(second, Velocity { x: 5.0, y: 5.0 })
+      .unwrap();
+    engine.despawn(second).unwrap();
+    let third = engine.create_entity();
+    engine.add_component(third, Health(1)).unwrap();
+    engine.remove_component::<Button>(first).unwrap();
+    assert_ne!(engine.snapshot().unwrap(), snapshot);
+
+    engine.restore_snapshot(&snapshot).unwrap();
+    assert_eq!(engine.snapshot().unwrap(), snapshot);
+    assert_eq!(engine.entity_count(), 2);
+    assert!(!engine.contains_entity(third));
+    assert_eq!(
+      engine.with_component::<Health, _>(first, Clone::clone),
+      Some(Health(3))
+    );
+    assert_eq!(
+      engine.with_component::<Velocity, _>(first, |velocity| *velocity),
+      Some(Velocity { x: 1.0, y: -1.0 })
+    );
+    assert!(!engine.has_component::<Velocity>(second).unwrap());
+    assert!(!engine.has_component::<Button>(first).unwrap());
+    assert_eq!(engine.entities_with::<Health>(), vec![first, second]);
+    // Ids are handed out again from where they were
+    assert_eq!(engine.create_entity(), third);
+  }
+
+  #[test]
+  fn snapshot_deltas_rebuild_the_next_snapshot() {
+    let mut engine = engine_with_snapshots();
+    let entities: Vec<u32> = (0..50).map(|_| engine.create_entity()).collect();
+    for (index, entity) in entities.iter().enumerate() {
+      engine.add_component(*entity, Health(index as u32)).unwrap();
+      engine
+        .add_component(*entity, Velocity { x: 0.0, y: 1.0 })
+        .unwrap();
+    }
+    let base = engine.snapshot().unwrap();
+    engine.with_component_mut::<Velocity, _>(entities[20], |velocity| velocity.x = 2.0);
+    let next = engine.snapshot().unwrap();
+
+    let delta = next.delta_from(&base);
+    assert!(delta.len() < 32, "{}", delta.len());
+    assert_eq!(delta.apply(&base).unwrap(), next);
+    assert!(matches!(
+      delta.apply(&next),
+      Err(SnapshotError::DeltaMismatch)
+    ));
+    // Deltas also cover snapshots of another size
+    let despawned = {
+      engine.despawn(entities[49]).unwrap();
+      engine.snapshot().unwrap()
+    };
+    assert_eq!(despawned.delta_from(&next).apply(&next).unwrap(), despawned);
+    // A run skipping usize::MAX bytes is rejected instead of overflowing
+    let mut bytes = delta.as_bytes()[..16].to_vec();
+    bytes.extend_from_slice(&[0xff; 9]);
+    bytes.extend_from_slice(&[0x01, 0x01, 0x00]);
+    assert!(matches!(
+      SnapshotDelta::from_bytes(bytes).apply(&base),
+      Err(SnapshotError::DeltaOutOfRange)
+    ));
+
+    let mut bytes = next.clone().into_bytes();
+    bytes.truncate(bytes.len() - 3);
+    assert!(matches!(
+      Snapshot::from_bytes(bytes),
+      Err(SnapshotError::Truncated(_))
+    ));
+    assert!(matches!(
+      Snapshot::from_bytes(b"not a snapshot".to_vec()),
+      Err(SnapshotError::InvalidHeader)
+    ));
+    let mut other = engine_with_health();
+    assert!(matches!(
+      other.restore_snapshot(&next),
+      Err(SnapshotError::NotEncodable(name)) if name == "Health"
+    ));
+    let mut other = P1::with_clock(Clock::manual()).unwrap();
+    assert!(matches!(
+      other.restore_snapshot(&next),
+      Err(SnapshotError::UnknownComponent(_))
+    ));
+    assert_eq!(other.entity_count(), 0);
+  }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::Scene;
+use crate::asset::AssetLoader;
+use crate::error::{AssetError, SceneError};
+
+// Where an overridable field of a prefab is, path is a JSON pointer into the component
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefabField {
+  pub entity: u32,
+  pub component: String,
+  pub path: String,
+}
+
+// A scene meant to be spawned many times, laid out the same way with the overridable fields next to the entities
+// Fields are checked when the prefab is read so overriding them can only fail on the values
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prefab {
+  scene: Scene,
+  fields: BTreeMap<String, PrefabField>,
+}
+
+impl Prefab {
+  pub fn new(scene: Scene, fields: BTreeMap<String, PrefabField>) -> Result<Self, SceneError> {
+    scene.validate()?;
+    let prefab = Self { scene, fields };
+    for name in prefab.fields.keys() {
+      prefab.value(&prefab.scene, name)?;
+    }
+    Ok(prefab)
+  }
+
+  pub fn from_json(json: &str) -> Result<Self, SceneError> {
+    Self::from_value(serde_json::from_str(json)?)
+  }
+
+  pub fn from_value(mut value: Value) -> Result<Self, SceneError> {
+    let fields = match value
+      .as_object_mut()
+      .and_then(|prefab| prefab.remove("fields"))
+    {
+      Some(fields) => serde_json::from_value(fields)?,
+      None => BTreeMap::new(),
+    };
+    Self::new(Scene::from_value(value)?, fields)
+  }
+
+  pub fn scene(&self) -> &Scene {
+    &self.scene
+  }
+
+  pub fn fields(&self) -> &BTreeMap<String, PrefabField> {
+    &self.fields
+  }
+
+  // The scene of one instance, with the overridden fields replaced
+  pub fn apply(&self, overrides: &BTreeMap<String, Value>) -> Result<Scene, SceneError> {
+    let mut scene = self.scene.clone();
+    for (name, value) in overrides {
+      *self.value_mut(&mut scene, name)? = value.clone();
+    }
+    Ok(scene)
+  }
+
+  fn value<'scene>(&self, scene: &'scene Scene, name: &str) -> Result<&'scene Value, SceneError> {
+    let field = self
+      .fields
+      .get(name)
+      .ok_or_else(|| SceneError::UnknownField(name.to_owned()))?;
+    scene
+      .entities
+      .iter()
+      .find(|entity| entity.id == field.entity)
+      .and_then(|entity| entity.components.get(&field.component))
+      .and_then(|component| component.pointer(&field.path))
+      .ok_or_else(|| SceneError::InvalidField(name.to_owned()))
+  }
+
+  fn value_mut<'scene>(
+    &self,
+    scene: &'scene mut Scene,
+    name: &str,
+  ) -> Result<&'scene mut Value, SceneError> {
+    let field = self
+      .fields
+      .get(name)
+      .ok_or_else(|| SceneError::UnknownField(name.to_owned()))?;
+    scene
+      .entities
+      .iter_mut()
+      .find(|entity| entity.id == field.entity)
+      .and_then(|entity| entity.components.get_mut(&field.component))
+      .and_then(|component| component.pointer_mut(&field.path))
+      .ok_or_else(|| SceneError::InvalidField(name.to_owned()))
+  }
+}
+
+pub struct PrefabLoader;
+
+impl AssetLoader for PrefabLoader {
+  type Asset = Prefab;
+
+  fn extensions(&self) -> &[&str] {
+    &["prefab"]
+  }
+
+  fn load(&self, bytes: &[u8], _: &Path) -> Result<Prefab, AssetError> {
+    Ok(Prefab::from_value(serde_json::from_slice(bytes)?)?)
+  }
+}
@@ -0,0 +1,98 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::SceneError;
+
+// Bumped whenever the layout below changes in a way older scenes can't be read with
+pub const SCENE_VERSION: u32 = 1;
+
+// Entities as they were saved, the ids are only meaningful within the scene
+// Components are keyed by the name the registry has for them
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+  pub version: u32,
+  pub entities: Vec<SceneEntity>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneEntity {
+  pub id: u32,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub children: Vec<u32>,
+  #[serde(default)]
+  pub components: BTreeMap<String, Value>,
+}
+
+impl Scene {
+  pub fn new(entities: Vec<SceneEntity>) -> Self {
+    Self {
+      version: SCENE_VERSION,
+      entities,
+    }
+  }
+
+  pub fn from_json(json: &str) -> Result<Self, SceneError> {
+    Self::from_value(serde_json::from_str(json)?)
+  }
+
+  // The version is checked before anything else so scenes from other versions get a clear error
+  // Also reads the contents of scene files loaded through the asset server
+  pub fn from_value(value: Value) -> Result<Self, SceneError> {
+    let version = value
+      .get("version")
+      .and_then(Value::as_u64)
+      .ok_or(SceneError::MissingVersion)?;
+    if version != u64::from(SCENE_VERSION) {
+      return Err(SceneError::UnsupportedVersion {
+        found: version,
+        supported: SCENE_VERSION.into(),
+      });
+    }
+    Ok(serde_json::from_value(value)?)
+  }
+
+  pub fn to_json(&self) -> Result<String, SceneError> {
+    Ok(serde_json::to_string_pretty(self)?)
+  }
+
+  // Checks that the ids are unique and that the children form trees within the scene
+  pub(crate) fn validate(&self) -> Result<(), SceneError> {
+    let mut ids = HashSet::new();
+    for entity in &self.entities {
+      if !ids.insert(entity.id) {
+        return Err(SceneError::DuplicateEntity(entity.id));
+      }
+    }
+
+    let mut parents = HashMap::new();
+    for entity in &self.entities {
+      for child in &entity.children {
+        if !ids.contains(child) {
+          return Err(SceneError::MissingEntity {
+            entity: entity.id,
+            target: *child,
+          });
+        }
+        if parents.insert(*child, entity.id).is_some() {
+          return Err(SceneError::InvalidHierarchy(*child));
+        }
+      }
+    }
+
+    // Every chain of parents has to end on a root before going through all of them
+    for child in parents.keys() {
+      let mut ancestor = parents.get(child);
+      let mut depth = 0;
+      while let Some(parent) = ancestor {
+        depth += 1;
+        if parent == child || depth > parents.len() {
+          return Err(SceneError::InvalidHierarchy(*child));
+        }
+        ancestor = parents.get(parent);
+      }
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,323 @@
+use crate::ecs::{ComponentInfo, ComponentManager, ComponentRegistry};
+use crate::error::SnapshotError;
+use crate::utility::SyncBox;
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"P1SN";
+const DELTA_MAGIC: &[u8; 4] = b"P1DL";
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// The entities and the components that opted into a binary encoding, as they were when it was taken
+// Laid out as the header, the next entity id, the sorted entity ids, then a column per component type
+// sorted by name, holding the encoded component of every entity that had one, sorted by entity
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot(Vec<u8>);
+
+// Column of a snapshot after decoding
+pub(crate) struct SnapshotColumn {
+  pub info: ComponentInfo,
+  pub components: Vec<(u32, SyncBox)>,
+}
+
+pub(crate) struct SnapshotContents {
+  pub next_entity: u32,
+  pub entities: Vec<u32>,
+  pub columns: Vec<SnapshotColumn>,
+}
+
+impl Snapshot {
+  // Checks the whole layout, not the components
+  pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, SnapshotError> {
+    let snapshot = Self(bytes);
+    snapshot.read(|_, _, _| Ok(()))?;
+    Ok(snapshot)
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.0
+  }
+  pub fn into_bytes(self) -> Vec<u8> {
+    self.0
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub(crate) fn write(
+    next_entity: u32,
+    entities: &[u32],
+    registry: &ComponentRegistry,
+    components: &ComponentManager,
+  ) -> Result<Self, SnapshotError> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(SNAPSHOT_MAGIC);
+    write_u32(&mut bytes, SNAPSHOT_VERSION);
+    write_u32(&mut bytes, next_entity);
+    write_u32(&mut bytes, entities.len() as u32);
+    for entity in entities {
+      write_u32(&mut bytes, *entity);
+    }
+
+    let mut columns: Vec<_> = registry
+      .iter()
+      .filter_map(|info| Some((info, info.binary()?)))
+      .collect();
+    columns.sort_by_key(|(info, _)| info.name());
+    write_u32(&mut bytes, columns.len() as u32);
+    for (info, binary) in columns {
+      write_u32(&mut bytes, info.name().len() as u32);
+      bytes.extend_from_slice(info.name().as_bytes());
+
+      let container = components.get_container_from_id(&info.type_id());
+      let mut owners: Vec<u32> = container
+        .as_ref()
+        .map(|container| container.keys().copied().collect())
+        .unwrap_or_default();
+      owners.sort();
+      write_u32(&mut bytes, owners.len() as u32);
+      for entity in owners {
+        // Every owner came from the container
+        let component = container.as_ref().unwrap().get_erased(&entity).unwrap();
+        write_u32(&mut bytes, entity);
+        // Length written once the component is
+        let length = bytes.len();
+        write_u32(&mut bytes, 0);
+        (binary.encode)(component.as_any(), &mut bytes).map_err(|source| {
+          SnapshotError::Encode {
+            name: info.name(),
+            entity,
+            source,
+          }
+        })?;
+        let encoded = (bytes.len() - length - 4) as u32;
+        bytes[length..length + 4].copy_from_slice(&encoded.to_le_bytes());
+      }
+    }
+    Ok(Self(bytes))
+  }
+
+  // Decodes every component so a snapshot that can't be restored is found out before anything changes
+  pub(crate) fn contents(
+    &self,
+    registry: &ComponentRegistry,
+  ) -> Result<SnapshotContents, SnapshotError> {
+    let mut columns: Vec<SnapshotColumn> = Vec::new();
+    let (next_entity, entities) = self.read(|name, entity, bytes| {
+      let info = registry
+        .by_name(name)?
+        .ok_or_else(|| SnapshotError::UnknownComponent(name.to_owned()))?;
+      let binary = info
+        .binary()
+        .ok_or_else(|| SnapshotError::NotEncodable(name.to_owned()))?;
+      if columns
+        .last()
+        .is_none_or(|column| column.info.name() != name)
+      {
+        columns.push(SnapshotColumn {
+          info: *info,
+          components: Vec::new(),
+        });
+      }
+      let component = (binary.decode)(bytes).map_err(|source| SnapshotError::Decode {
+        name: name.to_owned(),
+        entity,
+        source,
+      })?;
+      columns
+        .last_mut()
+        .unwrap()
+        .components
+        .push((entity, component));
+      Ok(())
+    })?;
+
+    // Columns without any component still have to be emptied when restoring
+    for info in registry.iter().filter(|info| info.binary().is_some()) {
+      if !columns
+        .iter()
+        .any(|column| column.info.type_id() == info.type_id())
+      {
+        columns.push(SnapshotColumn {
+          info: *info,
+          components: Vec::new(),
+        });
+      }
+    }
+    Ok(SnapshotContents {
+      next_entity,
+      entities,
+      columns,
+    })
+  }
+
+  // Goes through the layout, handing every component to the closure with the name of its column
+  fn read(
+    &self,
+    mut component: impl FnMut(&str, u32, &[u8]) -> Result<(), SnapshotError>,
+  ) -> Result<(u32, Vec<u32>), SnapshotError> {
+    let mut reader = Reader(&self.0);
+    if reader.take(4, "the header")? != SNAPSHOT_MAGIC {
+      return Err(SnapshotError::InvalidHeader);
+    }
+    let version = reader.u32("the header")?;
+    if version != SNAPSHOT_VERSION {
+      return Err(SnapshotError::UnsupportedVersion {
+        found: version,
+        supported: SNAPSHOT_VERSION,
+      });
+    }
+
+    let next_entity = reader.u32("the header")?;
+    let count = reader.u32("the entities")?;
+    let entities = (0..count)
+      .map(|_| reader.u32("the entities"))
+      .collect::<Result<_, _>>()?;
+
+    for _ in 0..reader.u32("the columns")? {
+      let length = reader.u32("a column name")? as usize;
+      let name = std::str::from_utf8(reader.take(length, "a column name")?)
+        .map_err(|_| SnapshotError::InvalidHeader)?;
+      for _ in 0..reader.u32("a column")? {
+        let entity = reader.u32("a component")?;
+        let length = reader.u32("a component")? as usize;
+        component(name, entity, reader.take(length, "a component")?)?;
+      }
+    }
+    if !reader.0.is_empty() {
+      return Err(SnapshotError::InvalidHeader);
+    }
+    Ok((next_entity, entities))
+  }
+
+  // Only holds the bytes that changed since the base, so consecutive snapshots of a mostly idle world stay small
+  pub fn delta_from(&self, base: &Snapshot) -> SnapshotDelta {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(DELTA_MAGIC);
+    bytes.extend_from_slice(&fnv1a(&base.0).to_le_bytes());
+    write_u32(&mut bytes, self.0.len() as u32);
+
+    // Runs of unchanged bytes followed by runs of changed ones, xored with the base
+    let xored: Vec<u8> = self
+      .0
+      .iter()
+      .enumerate()
+      .map(|(index, byte)| byte ^ base.0.get(index).copied().unwrap_or(0))
+      .collect();
+    let mut index = 0;
+    while index < xored.len() {
+      let unchanged = xored[index..].iter().take_while(|byte| **byte == 0).count();
+      index += unchanged;
+      let changed = xored[index..].iter().take_while(|byte| **byte != 0).count();
+      write_varint(&mut bytes, unchanged);
+      write_varint(&mut bytes, changed);
+      bytes.extend_from_slice(&xored[index..index + changed]);
+      index += changed;
+    }
+    SnapshotDelta(bytes)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotDelta(Vec<u8>);
+
+impl SnapshotDelta {
+  pub fn from_bytes(bytes: Vec<u8>) -> Self {
+    Self(bytes)
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.0
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  // Rebuilds the snapshot the delta was made from, given the same base
+  pub fn apply(&self, base: &Snapshot) -> Result<Snapshot, SnapshotError> {
+    let mut reader = Reader(&self.0);
+    if reader.take(4, "the header")? != DELTA_MAGIC {
+      return Err(SnapshotError::InvalidHeader);
+    }
+    let hash = u64::from_le_bytes(reader.take(8, "the header")?.try_into().unwrap());
+    if hash != fnv1a(&base.0) {
+      return Err(SnapshotError::DeltaMismatch);
+    }
+
+    let length = reader.u32("the header")? as usize;
+    let mut bytes: Vec<u8> = (0..length)
+      .map(|index| base.0.get(index).copied().unwrap_or(0))
+      .collect();
+    let mut index = 0usize;
+    while !reader.0.is_empty() {
+      let start = index
+        .checked_add(reader.varint()?)
+        .ok_or(SnapshotError::DeltaOutOfRange)?;
+      let changed = reader.varint()?;
+      let xored = reader.take(changed, "a run of changed bytes")?;
+      index = start
+        .checked_add(changed)
+        .ok_or(SnapshotError::DeltaOutOfRange)?;
+      let target = bytes
+        .get_mut(start..index)
+        .ok_or(SnapshotError::DeltaOutOfRange)?;
+      for (byte, xor) in target.iter_mut().zip(xored) {
+        *byte ^= xor;
+      }
+    }
+    Snapshot::from_bytes(bytes)
+  }
+}
+
+struct Reader<'bytes>(&'bytes [u8]);
+
+impl<'bytes> Reader<'bytes> {
+  fn take(&mut self, length: usize, what: &'static str) -> Result<&'bytes [u8], SnapshotError> {
+    if self.0.len() < length {
+      return Err(SnapshotError::Truncated(what));
+    }
+    let (taken, rest) = self.0.split_at(length);
+    self.0 = rest;
+    Ok(taken)
+  }
+
+  fn u32(&mut self, what: &'static str) -> Result<u32, SnapshotError> {
+    Ok(u32::from_le_bytes(self.take(4, what)?.try_into().unwrap()))
+  }
+
+  fn varint(&mut self) -> Result<usize, SnapshotError> {
+    let mut value = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+      let byte = self.take(1, "a run length")?[0];
+      value |= ((byte & 0x7f) as usize) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+    }
+    Err(SnapshotError::InvalidHeader)
+  }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+  bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+  while value >= 0x80 {
+    bytes.push(value as u8 | 0x80);
+    value >>= 7;
+  }
+  bytes.push(value as u8);
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+    (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+  })
+}
@@ -0,0 +1,33 @@
+use macros::Component;
+
+use crate::ecs::Component;
+
+// Both sides are kept in sync by P1::set_parent and P1::remove_parent, they shouldn't be added by hand
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parent(pub(crate) u32);
+
+impl Parent {
+  pub fn get(&self) -> u32 {
+    self.0
+  }
+}
+
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Children(pub(crate) Vec<u32>);
+
+impl Children {
+  pub fn iter(&self) -> impl Iterator<Item = &u32> {
+    self.0.iter()
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn contains(&self, entity: u32) -> bool {
+    self.0.contains(&entity)
+  }
+}
@@ -0,0 +1,5 @@
+mod hierarchy;
+mod transform;
+
+pub use hierarchy::{Children, Parent};
+pub use transform::{GlobalTransform, Transform};
@@ -0,0 +1,126 @@
+use glam::{Mat3, Mat4, Quat, Vec3};
+use macros::Component;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::Component;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+  pub translation: Vec3,
+  pub rotation: Quat,
+  pub scale: Vec3,
+}
+
+impl Transform {
+  pub const IDENTITY: Self = Self {
+    translation: Vec3::ZERO,
+    rotation: Quat::IDENTITY,
+    scale: Vec3::ONE,
+  };
+
+  pub fn from_xyz(x: f32, y: f32, z: f32) -> Self {
+    Self::from_translation(Vec3::new(x, y, z))
+  }
+
+  pub fn from_translation(translation: Vec3) -> Self {
+    Self {
+      translation,
+      ..Self::IDENTITY
+    }
+  }
+
+  pub fn with_rotation(mut self, rotation: Quat) -> Self {
+    self.rotation = rotation;
+    self
+  }
+
+  pub fn with_scale(mut self, scale: Vec3) -> Self {
+    self.scale = scale;
+    self
+  }
+
+  // Turns the transform so its forward (-Z) points at target
+  pub fn looking_at(mut self, target: Vec3, up: Vec3) -> Self {
+    let back = (self.translation - target).normalize_or(Vec3::Z);
+    let right = up.cross(back).normalize_or(Vec3::X);
+    let up = back.cross(right);
+    self.rotation = Quat::from_mat3(&Mat3::from_cols(right, up, back));
+    self
+  }
+
+  pub fn forward(&self) -> Vec3 {
+    self.rotation * Vec3::NEG_Z
+  }
+  pub fn right(&self) -> Vec3 {
+    self.rotation * Vec3::X
+  }
+  pub fn up(&self) -> Vec3 {
+    self.rotation * Vec3::Y
+  }
+
+  pub fn compute_matrix(&self) -> Mat4 {
+    Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+  }
+}
+
+impl Default for Transform {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+// World space transform, computed by the engine from the Transform of the entity and its parents
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct GlobalTransform {
+  matrix: Mat4,
+  // What the matrix was computed from, a subtree is only recomputed once this goes stale
+  source: Option<(Transform, Option<u32>)>,
+}
+
+impl GlobalTransform {
+  pub const IDENTITY: Self = Self {
+    matrix: Mat4::IDENTITY,
+    source: None,
+  };
+
+  pub fn matrix(&self) -> Mat4 {
+    self.matrix
+  }
+
+  pub fn translation(&self) -> Vec3 {
+    self.matrix.w_axis.truncate()
+  }
+
+  pub fn compute_transform(&self) -> Transform {
+    let (scale, rotation, translation) = self.matrix.to_scale_rotation_translation();
+    Transform {
+      translation,
+      rotation,
+      scale,
+    }
+  }
+
+  pub(crate) fn is_stale(&self, local: &Transform, parent: Option<u32>) -> bool {
+    self.source != Some((*local, parent))
+  }
+
+  pub(crate) fn set(&mut self, matrix: Mat4, local: Transform, parent: Option<u32>) {
+    self.matrix = matrix;
+    self.source = Some((local, parent));
+  }
+}
+
+impl Default for GlobalTransform {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+impl From<Transform> for GlobalTransform {
+  fn from(value: Transform) -> Self {
+    Self {
+      matrix: value.compute_matrix(),
+      source: None,
+    }
+  }
+}
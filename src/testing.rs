@@ -0,0 +1,52 @@
+// Helpers shared by the tests of several modules
+use crate::asset::{Assets, Handle};
+use crate::p1::P1;
+use crate::rendering::Renderer;
+use crate::text::Font;
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+
+pub(crate) const DEJAVU_SANS: &[u8] = include_bytes!("../tests/fonts/DejaVuSans.ttf");
+
+pub(crate) fn dejavu_sans() -> Font {
+  Font::from_bytes(DEJAVU_SANS.to_vec()).unwrap()
+}
+
+pub(crate) fn add_font(engine: &mut P1) -> Handle<Font> {
+  engine
+    .resource_mut::<Assets<Font>>()
+    .unwrap()
+    .add(dejavu_sans())
+}
+
+// There's nothing to look at on the null backend, tests say they were skipped instead of passing
+pub(crate) fn gpu_available(engine: &P1, test: &str) -> bool {
+  let available = !engine.resource::<Renderer>().unwrap().is_null();
+  if !available {
+    eprintln!("{test} skipped, no adapter could be found to render with");
+  }
+  available
+}
+
+// Emptied first, every test passes its own name so they don't step on each other
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("p1-{name}-{}", std::process::id()));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+// Loads finish on other threads, updates pick them up
+pub(crate) fn update_until(engine: &mut P1, condition: impl Fn(&P1) -> bool) {
+  for _ in 0..500 {
+    engine.update().unwrap();
+    if condition(engine) {
+      return;
+    }
+    sleep(Duration::from_millis(10));
+  }
+  panic!("Condition was not met in time.");
+}
@@ -0,0 +1,189 @@
+use super::Font;
+use crate::asset::{Assets, Handle};
+use crate::rendering::{Image, Rect};
+
+use std::collections::HashMap;
+
+use ab_glyph::{Font as _, GlyphId, ScaleFont};
+use glam::Vec2;
+
+// Empty pixels kept around every glyph so filtering doesn't bleed the neighbours in
+const PADDING: u32 = 1;
+// What wgpu guarantees, the engine lowers it to the device's own limit
+const MAX_SIZE: u32 = 8192;
+
+// Where a rasterized glyph sits in the atlas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct AtlasGlyph {
+  // Pixels of the atlas
+  pub rect: Rect,
+  // From the pen position on the baseline to the top left of the glyph
+  pub offset: Vec2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct GlyphKey {
+  font: u64,
+  glyph: u16,
+  // Quarter pixels, so sizes that barely differ share their glyphs
+  size: u32,
+}
+
+// Every glyph drawn so far, rasterized into one image as white with the coverage in alpha
+// Filled shelf by shelf from the top, grows by doubling its height once it's full
+// Past the largest texture the device takes, it's cleared and filled again from the top
+pub struct GlyphAtlas {
+  image: Handle<Image>,
+  // Glyphs without an outline, like spaces, are None
+  glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+  cursor: (u32, u32),
+  shelf_height: u32,
+  max_size: u32,
+  // Bumped every time glyphs already handed out moved in the image
+  generation: u64,
+  // Set when the image changed since the renderer last saw it
+  changed: bool,
+}
+
+impl GlyphAtlas {
+  pub(crate) fn new(images: &mut Assets<Image>, width: u32, height: u32) -> Self {
+    Self {
+      image: images.add(Image::solid(width, height, [255, 255, 255, 0])),
+      glyphs: HashMap::new(),
+      cursor: (PADDING, PADDING),
+      shelf_height: 0,
+      max_size: MAX_SIZE.max(height),
+      generation: 0,
+      changed: false,
+    }
+  }
+
+  pub fn image(&self) -> &Handle<Image> {
+    &self.image
+  }
+
+  pub fn len(&self) -> usize {
+    self.glyphs.values().flatten().count()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub(crate) fn take_changed(&mut self) -> bool {
+    std::mem::take(&mut self.changed)
+  }
+
+  // Layouts built at an older generation point at glyphs that moved
+  pub(crate) fn generation(&self) -> u64 {
+    self.generation
+  }
+
+  pub(crate) fn set_max_size(&mut self, max_size: u32) {
+    self.max_size = max_size;
+  }
+
+  // Rasterizes the glyph the first time it's asked for
+  pub(crate) fn glyph(
+    &mut self,
+    images: &mut Assets<Image>,
+    font: &Handle<Font>,
+    face: &Font,
+    glyph: GlyphId,
+    size: f32,
+  ) -> Option<AtlasGlyph> {
+    let key = GlyphKey {
+      font: font.id(),
+      glyph: glyph.0,
+      size: (size * 4.0).round() as u32,
+    };
+    if let Some(entry) = self.glyphs.get(&key) {
+      return *entry;
+    }
+
+    let scaled = face.scaled(key.size as f32 / 4.0);
+    let entry = scaled
+      .outline_glyph(glyph.with_scale(scaled.scale()))
+      .and_then(|outline| {
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        if width == 0 || height == 0 {
+          return None;
+        }
+
+        let image = images.get_mut(&self.image)?;
+        // Glyphs wider than the atlas or taller than it can grow would never fit
+        if width + 2 * PADDING > image.width || height + 2 * PADDING > self.max_size {
+          return None;
+        }
+        let (x, y) = match self.allocate(image, width, height) {
+          Some(position) => position,
+          None => {
+            self.clear(image);
+            self.allocate(image, width, height)?
+          }
+        };
+        let atlas_width = image.width as usize;
+        outline.draw(|glyph_x, glyph_y, coverage| {
+          let start = ((y + glyph_y) as usize * atlas_width + (x + glyph_x) as usize) * 4;
+          image.data[start + 3] = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+        });
+        self.changed = true;
+
+        Some(AtlasGlyph {
+          rect: Rect::new(x as f32, y as f32, (x + width) as f32, (y + height) as f32),
+          offset: Vec2::new(bounds.min.x, bounds.min.y),
+        })
+      });
+    self.glyphs.insert(key, entry);
+    entry
+  }
+
+  // Top left corner of a free spot of that size, the image grows until there is one
+  // None once it would grow past the max size
+  fn allocate(&mut self, image: &mut Image, width: u32, height: u32) -> Option<(u32, u32)> {
+    let mut cursor = self.cursor;
+    let mut shelf_height = self.shelf_height;
+    if cursor.0 + width + PADDING > image.width {
+      cursor = (PADDING, cursor.1 + shelf_height + PADDING);
+      shelf_height = 0;
+    }
+    while cursor.1 + height + PADDING > image.height {
+      if image.height * 2 > self.max_size {
+        return None;
+      }
+      // Rows are stored from the top, so the glyphs already placed don't move, their UVs do
+      image
+        .data
+        .extend([255, 255, 255, 0].repeat(image.data.len() / 4));
+      image.height *= 2;
+      self.generation += 1;
+    }
+
+    self.cursor = (cursor.0 + width + PADDING, cursor.1);
+    self.shelf_height = shelf_height.max(height);
+    Some(cursor)
+  }
+
+  // Drops every glyph, they're rasterized again as they're asked for
+  fn clear(&mut self, image: &mut Image) {
+    for pixel in image.data.chunks_exact_mut(4) {
+      pixel[3] = 0;
+    }
+    self.glyphs.clear();
+    self.cursor = (PADDING, PADDING);
+    self.shelf_height = 0;
+    self.generation += 1;
+  }
+
+  // UV coordinates of a glyph in the image as it is now
+  pub(crate) fn uv(&self, images: &Assets<Image>, glyph: &AtlasGlyph) -> Rect {
+    let Some(image) = images.get(&self.image) else {
+      return Rect::UNIT;
+    };
+    let size = Vec2::new(image.width as f32, image.height as f32);
+    Rect {
+      min: glyph.rect.min / size,
+      max: glyph.rect.max / size,
+    }
+  }
+}
@@ -0,0 +1,118 @@
+use super::{Font, GlyphAtlas, Text, TextLayout};
+use crate::asset::Assets;
+use crate::error::DataError;
+use crate::p1::P1;
+use crate::rendering::{ExtractedSprite, Image, Renderer, SpriteInstance};
+use crate::spatial::GlobalTransform;
+use crate::ui::{Node, Style};
+
+use glam::{Mat4, Vec3};
+
+// Shaping of every Text and the glyph quads of the ones outside the UI
+impl P1 {
+  // Lays out every Text again, glyphs drawn for the first time are added to the atlas
+  pub(crate) fn update_text(&mut self) -> Result<(), DataError> {
+    let entities = self.entities_with::<Text>();
+    if entities.is_empty() {
+      return Ok(());
+    }
+    let max_size = self
+      .resource_manager
+      .get::<Renderer>()
+      .and_then(|renderer| renderer.device().map(|device| device.limits()))
+      .map(|limits| limits.max_texture_dimension_2d);
+    if let Some(max_size) = max_size {
+      let mut atlas = self.resource_manager.get_mut::<GlyphAtlas>().unwrap();
+      atlas.set_max_size(max_size);
+    }
+
+    // Only texts that changed are shaped again, the second pass catches the ones whose glyphs
+    // moved in the atlas while the others were added
+    for _ in 0..2 {
+      let layouts: Vec<_> = {
+        let fonts = self.resource_manager.get::<Assets<Font>>().unwrap();
+        let mut images = self.resource_manager.get_mut::<Assets<Image>>().unwrap();
+        let mut atlas = self.resource_manager.get_mut::<GlyphAtlas>().unwrap();
+        entities
+          .iter()
+          .filter_map(|&entity| {
+            let text = self.with_component::<Text, _>(entity, |text| text.clone())?;
+            // UI text wraps inside the padding of its node
+            let max_width = self
+              .with_component::<Node, _>(entity, |node| node.size().x)
+              .zip(self.with_component::<Style, _>(entity, |style| *style))
+              .map(|(width, style)| style.content_width(width));
+            let current = self.with_component::<TextLayout, _>(entity, |layout| {
+              layout.is_built_from(&text, max_width, &atlas)
+            });
+            if current == Some(true) {
+              return None;
+            }
+            let layout = match fonts.get(&text.font) {
+              Some(font) => TextLayout::new(&text, font, max_width, &mut atlas, &mut images),
+              None => TextLayout::default(),
+            };
+            Some((entity, layout))
+          })
+          .collect()
+      };
+      if layouts.is_empty() {
+        break;
+      }
+
+      self.sync_atlas();
+      for (entity, layout) in layouts {
+        if self.has_component::<TextLayout>(entity)? {
+          self.with_component_mut::<TextLayout, _>(entity, |current| *current = layout);
+        } else {
+          self.add_component(entity, layout)?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  // Glyphs added to the atlas have to reach the GPU again
+  pub(crate) fn sync_atlas(&self) {
+    let mut atlas = self.resource_manager.get_mut::<GlyphAtlas>().unwrap();
+    if !atlas.take_changed() {
+      return;
+    }
+    if let Some(mut renderer) = self.resource_manager.get_mut::<Renderer>() {
+      renderer.invalidate_texture(atlas.image().id());
+    }
+  }
+
+  // Text outside the UI, one quad per glyph on layer 0
+  pub(crate) fn extract_text(&self) -> Vec<ExtractedSprite> {
+    let atlas = self.resource_manager.get::<GlyphAtlas>().unwrap();
+    let mut sprites = Vec::new();
+    for entity in self.entities_with::<Text>() {
+      if self.has_component::<Style>(entity).unwrap_or(true) {
+        continue;
+      }
+      let Some((color, layout)) = self
+        .with_component::<Text, _>(entity, |text| text.color)
+        .zip(self.with_component::<TextLayout, _>(entity, |layout| layout.clone()))
+      else {
+        continue;
+      };
+      let transform = self
+        .with_component::<GlobalTransform, _>(entity, |transform| transform.matrix())
+        .unwrap_or(Mat4::IDENTITY);
+      for glyph in layout.glyphs() {
+        // The layout goes down, the world goes up
+        let size = glyph.rect.size();
+        let model = transform
+          * Mat4::from_translation(Vec3::new(glyph.rect.min.x, -glyph.rect.max.y, 0.0))
+          * Mat4::from_scale(Vec3::new(size.x, size.y, 1.0));
+        sprites.push(ExtractedSprite {
+          texture: atlas.image().id(),
+          layer: 0,
+          instance: SpriteInstance::new(model, color).with_uv(glyph.uv),
+        });
+      }
+    }
+    sprites
+  }
+}
@@ -0,0 +1,65 @@
+use crate::asset::AssetLoader;
+use crate::error::AssetError;
+
+use std::fmt;
+use std::path::Path;
+
+use ab_glyph::{Font as _, FontArc, GlyphId, PxScale, PxScaleFont, ScaleFont};
+
+// TrueType or OpenType font, cheap to clone
+#[derive(Clone)]
+pub struct Font {
+  font: FontArc,
+}
+
+impl Font {
+  pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+    Ok(Self {
+      font: FontArc::try_from_vec(bytes)?,
+    })
+  }
+
+  // Size is the em size in pixels, like CSS font sizes, where ab_glyph scales by line height
+  pub(crate) fn scaled(&self, size: f32) -> PxScaleFont<&FontArc> {
+    let units_per_em = self.font.units_per_em().unwrap_or(1.0);
+    self.font.as_scaled(PxScale::from(
+      size * self.font.height_unscaled() / units_per_em,
+    ))
+  }
+
+  pub(crate) fn glyph_id(&self, character: char) -> GlyphId {
+    self.font.glyph_id(character)
+  }
+
+  pub(crate) fn inner(&self) -> &FontArc {
+    &self.font
+  }
+
+  // Distance between two baselines
+  pub fn line_height(&self, size: f32) -> f32 {
+    let scaled = self.scaled(size);
+    scaled.height() + scaled.line_gap()
+  }
+}
+
+impl fmt::Debug for Font {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Font")
+      .field("glyphs", &self.font.glyph_count())
+      .finish()
+  }
+}
+
+pub struct FontLoader;
+
+impl AssetLoader for FontLoader {
+  type Asset = Font;
+
+  fn extensions(&self) -> &[&str] {
+    &["ttf", "otf"]
+  }
+
+  fn load(&self, bytes: &[u8], _: &Path) -> Result<Font, AssetError> {
+    Font::from_bytes(bytes.to_vec())
+  }
+}
@@ -0,0 +1,262 @@
+mod atlas;
+mod engine;
+mod font;
+mod shaping;
+mod text;
+
+pub use atlas::GlyphAtlas;
+pub use font::{Font, FontLoader};
+pub use shaping::{shape_text, ShapedGlyph, ShapedText};
+pub use text::{PositionedGlyph, Text, TextLayout};
+
+#[cfg(test)]
+mod tests {
+  use super::{shape_text, Font, GlyphAtlas, Text, TextLayout};
+  use crate::asset::{AssetServer, Assets, Handle, LoadState};
+  use crate::event::Clock;
+  use crate::p1::P1;
+  use crate::rendering::{Camera2d, Image, RenderTarget};
+  use crate::spatial::Transform;
+  use crate::testing::{add_font, dejavu_sans, gpu_available, temp_dir, update_until, DEJAVU_SANS};
+  use crate::ui::{Node, Style, Val};
+
+  use std::fs;
+
+  use glam::Vec3;
+
+  #[test]
+  fn pairs_are_kerned() {
+    let font = dejavu_sans();
+    let pair = shape_text(&font, "AV", 32.0, None);
+    let apart =
+      shape_text(&font, "A", 32.0, None).size.x + shape_text(&font, "V", 32.0, None).size.x;
+    assert!(pair.size.x < apart - 1.0);
+    assert!(pair.glyphs[1].position.x < shape_text(&font, "A", 32.0, None).size.x);
+
+    assert!(matches!(
+      Font::from_bytes(b"not a font".to_vec()),
+      Err(crate::error::AssetError::Font(_))
+    ));
+  }
+
+  #[test]
+  fn lines_wrap_between_words() {
+    let font = dejavu_sans();
+    let line_height = font.line_height(16.0);
+    let single = shape_text(&font, "hello world", 16.0, None);
+    assert_eq!(single.lines, 1);
+    assert_eq!(single.size.y, line_height);
+
+    let hello = shape_text(&font, "hello", 16.0, None).size.x;
+    let world = shape_text(&font, "world", 16.0, None).size.x;
+    let wrapped = shape_text(&font, "hello world", 16.0, Some(hello.max(world) + 2.0));
+    assert_eq!(wrapped.lines, 2);
+    assert_eq!(wrapped.size.y, 2.0 * line_height);
+    // The trailing space stays on the first line and doesn't widen it
+    assert_eq!(wrapped.size.x, hello.max(world));
+    assert_eq!(wrapped.glyphs[6].position.x, 0.0);
+    assert_eq!(
+      wrapped.glyphs[6].position.y,
+      wrapped.glyphs[0].position.y + line_height
+    );
+
+    // Words wider than the line are broken between characters, newlines always break
+    let broken = shape_text(&font, "hello", 16.0, Some(hello / 2.0));
+    assert!(broken.lines >= 2);
+    assert!(broken.size.x <= hello / 2.0);
+    let forced = shape_text(&font, "a\n\nb", 16.0, None);
+    assert_eq!(forced.lines, 3);
+    assert_eq!(forced.glyphs.last().unwrap().position.x, 0.0);
+  }
+
+  #[test]
+  fn glyphs_fill_the_atlas() {
+    let font = dejavu_sans();
+    let mut images = Assets::<Image>::new();
+    let mut atlas = GlyphAtlas::new(&mut images, 64, 16);
+    let handle = Handle::weak(u64::MAX);
+    let text = Text::new("abc abc", handle).with_size(24.0);
+
+    let layout = TextLayout::new(&text, &font, None, &mut atlas, &mut images);
+    // The space has no outline, repeated letters share their glyph
+    assert_eq!(layout.glyphs().len(), 6);
+    assert_eq!(atlas.len(), 3);
+    assert!(atlas.take_changed());
+    assert!(!atlas.take_changed());
+    assert_eq!(layout.glyphs()[0].uv, layout.glyphs()[3].uv);
+
+    // Too small for 24px glyphs, it grew to fit them
+    let image = images.get(atlas.image()).unwrap();
+    assert!(image.height > 16);
+    let covered = image
+      .data
+      .chunks_exact(4)
+      .filter(|pixel| pixel[3] > 0)
+      .count();
+    assert!(covered > 0);
+    for glyph in layout.glyphs() {
+      assert!(glyph.uv.max.y <= 1.0 && glyph.uv.max.x <= 1.0);
+      assert!(glyph.rect.max.y <= layout.size().y);
+    }
+
+    TextLayout::new(&text, &font, None, &mut atlas, &mut images);
+    assert!(!atlas.take_changed());
+  }
+
+  #[test]
+  fn full_atlases_are_cleared() {
+    let font = dejavu_sans();
+    let mut images = Assets::<Image>::new();
+    let mut atlas = GlyphAtlas::new(&mut images, 64, 16);
+    atlas.set_max_size(64);
+    let handle = Handle::weak(u64::MAX);
+
+    // Every size gets its own glyphs, as with an animated size
+    let mut generation = atlas.generation();
+    let mut cleared = false;
+    for size in 16..48 {
+      let text = Text::new("W", handle.clone()).with_size(size as f32);
+      let layout = TextLayout::new(&text, &font, None, &mut atlas, &mut images);
+      assert_eq!(layout.glyphs().len(), 1);
+      assert!(images.get(atlas.image()).unwrap().height <= 64);
+
+      if atlas.generation() != generation {
+        generation = atlas.generation();
+        cleared |= atlas.len() == 1;
+      }
+    }
+    assert!(cleared);
+    // Taller than the atlas can ever be
+    let text = Text::new("W", handle).with_size(96.0);
+    let layout = TextLayout::new(&text, &font, None, &mut atlas, &mut images);
+    assert!(layout.glyphs().is_empty());
+  }
+
+  #[test]
+  fn text_is_shaped_again_once_it_changed() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let font = add_font(&mut engine);
+    let entity = engine.create_entity();
+    engine.add_component(entity, Text::new("P1", font)).unwrap();
+    engine.update().unwrap();
+
+    // Left alone while the text stays the same
+    engine.with_component_mut::<TextLayout, _>(entity, |layout| layout.glyphs.clear());
+    engine.update().unwrap();
+    let glyphs = engine.with_component::<TextLayout, _>(entity, |layout| layout.glyphs().len());
+    assert_eq!(glyphs, Some(0));
+
+    engine.with_component_mut::<Text, _>(entity, |text| text.font_size = 20.0);
+    engine.update().unwrap();
+    let glyphs = engine.with_component::<TextLayout, _>(entity, |layout| layout.glyphs().len());
+    assert_eq!(glyphs, Some(2));
+  }
+
+  #[test]
+  fn fonts_load_through_the_server() {
+    let dir = temp_dir("fonts");
+    fs::write(dir.join("dejavu.ttf"), DEJAVU_SANS).unwrap();
+
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.insert_resource(AssetServer::new(&dir));
+    let handle = engine
+      .resource_mut::<AssetServer>()
+      .unwrap()
+      .load::<Font>("dejavu.ttf")
+      .unwrap();
+    let entity = engine.create_entity();
+    engine
+      .add_component(entity, Text::new("P1", handle.clone()))
+      .unwrap();
+
+    update_until(&mut engine, |engine| {
+      let server = engine.resource::<AssetServer>().unwrap();
+      server.load_state(&handle) == Some(LoadState::Loaded)
+    });
+    assert!(engine.resource::<Assets<Font>>().unwrap().contains(&handle));
+    engine.update().unwrap();
+    let lines = engine.with_component::<TextLayout, _>(entity, |layout| layout.glyphs().len());
+    assert_eq!(lines, Some(2));
+  }
+
+  #[test]
+  fn ui_text_wraps_inside_its_node() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let font = add_font(&mut engine);
+
+    // Auto sized nodes take the size of their text on one line
+    let label = engine.create_entity();
+    engine.add_component(label, Style::default()).unwrap();
+    engine
+      .add_component(label, Text::new("hello world", font.clone()))
+      .unwrap();
+    // Narrower than the text, it wraps
+    let narrow = engine.create_entity();
+    engine
+      .add_component(narrow, Style::default().with_size(Val::Px(50.0), Val::Auto))
+      .unwrap();
+    engine
+      .add_component(narrow, Text::new("hello world", font))
+      .unwrap();
+    engine.update().unwrap();
+
+    let measured = shape_text(&dejavu_sans(), "hello world", 16.0, None);
+    let node = engine
+      .with_component::<Node, _>(label, |node| node.size())
+      .unwrap();
+    assert_eq!(node, measured.size);
+    let lines = engine.with_component::<TextLayout, _>(label, |layout| layout.lines());
+    assert_eq!(lines, Some(1));
+
+    let layout = engine
+      .with_component::<TextLayout, _>(narrow, |layout| layout.clone())
+      .unwrap();
+    assert_eq!(layout.lines(), 2);
+    assert!(layout.size().x <= 50.0);
+    let node = engine
+      .with_component::<Node, _>(narrow, |node| node.size())
+      .unwrap();
+    assert_eq!(node.y, layout.size().y);
+  }
+
+  #[test]
+  fn text_renders_in_the_ui_and_the_world() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(64, 64).unwrap();
+    if !gpu_available(&engine, "text_renders_in_the_ui_and_the_world") {
+      return;
+    }
+    let font = add_font(&mut engine);
+
+    let label = engine.create_entity();
+    engine.add_component(label, Style::default()).unwrap();
+    engine
+      .add_component(label, Text::new("H", font.clone()).with_size(32.0))
+      .unwrap();
+    engine.update().unwrap();
+    let image = engine.render_to_image().unwrap();
+    let lit = |image: &Image, x0: u32, x1: u32, y0: u32, y1: u32| {
+      (y0..y1).any(|y| (x0..x1).any(|x| image.pixel(x, y).unwrap()[0] > 128))
+    };
+    assert!(lit(&image, 0, 32, 0, 32));
+    assert!(!lit(&image, 32, 64, 32, 64));
+
+    // World text starts at its transform and goes down, y pointing up
+    engine.despawn(label).unwrap();
+    let camera = engine.create_entity();
+    engine
+      .add_component(camera, Camera2d::new(RenderTarget::Offscreen))
+      .unwrap();
+    let text = engine.create_entity();
+    engine
+      .add_component(text, Text::new("H", font).with_size(32.0))
+      .unwrap();
+    engine
+      .add_component(text, Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)))
+      .unwrap();
+    engine.update().unwrap();
+    let image = engine.render_to_image().unwrap();
+    assert!(lit(&image, 32, 64, 32, 64));
+    assert!(!lit(&image, 0, 32, 0, 32));
+  }
+}
@@ -0,0 +1,90 @@
+use super::Font;
+
+use ab_glyph::{GlyphId, ScaleFont};
+use glam::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+  pub id: GlyphId,
+  // Pen position on the baseline, from the top left of the text
+  pub position: Vec2,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShapedText {
+  pub glyphs: Vec<ShapedGlyph>,
+  pub size: Vec2,
+  pub lines: usize,
+}
+
+// Left to right, one glyph per character with kerning between neighbours
+// Lines break on newlines, and between words when they get wider than max_width
+// A single word wider than max_width is broken between characters
+pub fn shape_text(font: &Font, text: &str, size: f32, max_width: Option<f32>) -> ShapedText {
+  let scaled = font.scaled(size);
+  let line_height = font.line_height(size);
+
+  let mut shaped = ShapedText::default();
+  let mut line = 0;
+  let mut width = 0.0f32;
+  for paragraph in text.split('\n') {
+    let mut pen = 0.0f32;
+    // Up to the last character that isn't whitespace, trailing spaces don't make a line wider
+    let mut line_width = 0.0f32;
+    let mut previous: Option<GlyphId> = None;
+
+    for word in paragraph.split_inclusive(char::is_whitespace) {
+      let ids: Vec<_> = word
+        .chars()
+        .map(|character| (character, font.glyph_id(character)))
+        .collect();
+      let mut word_width = 0.0;
+      let mut last = previous;
+      for (character, id) in ids.iter() {
+        if character.is_whitespace() {
+          break;
+        }
+        if let Some(last) = last {
+          word_width += scaled.kern(last, *id);
+        }
+        word_width += scaled.h_advance(*id);
+        last = Some(*id);
+      }
+      if max_width.is_some_and(|max_width| pen > 0.0 && pen + word_width > max_width) {
+        width = width.max(line_width);
+        line += 1;
+        (pen, line_width, previous) = (0.0, 0.0, None);
+      }
+
+      for (character, id) in ids {
+        if let Some(previous) = previous {
+          pen += scaled.kern(previous, id);
+        }
+        let advance = scaled.h_advance(id);
+        if !character.is_whitespace()
+          && max_width.is_some_and(|max_width| pen > 0.0 && pen + advance > max_width)
+        {
+          width = width.max(line_width);
+          line += 1;
+          pen = 0.0;
+        }
+        shaped.glyphs.push(ShapedGlyph {
+          id,
+          position: Vec2::new(pen, scaled.ascent() + line as f32 * line_height),
+        });
+        pen += advance;
+        if !character.is_whitespace() {
+          line_width = pen;
+        }
+        previous = Some(id);
+      }
+    }
+
+    width = width.max(line_width);
+    line += 1;
+  }
+
+  shaped.lines = line;
+  shaped.size = Vec2::new(width, line as f32 * line_height);
+  shaped
+}
@@ -0,0 +1,145 @@
+use super::{shape_text, Font, GlyphAtlas};
+use crate::asset::{Assets, Handle};
+use crate::ecs::Component;
+use crate::rendering::{Image, Rect};
+
+use glam::Vec2;
+use macros::Component;
+
+// Drawn in the node's rectangle and wrapped to its width on UI nodes, TextInputs keep theirs in sync with what was typed
+// Elsewhere it's drawn in the world from the GlobalTransform, the first line starting at the origin and going down
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct Text {
+  pub value: String,
+  pub font: Handle<Font>,
+  // Em size, logical pixels in the UI and world units elsewhere
+  pub font_size: f32,
+  pub color: wgpu::Color,
+}
+
+impl Text {
+  pub fn new(value: impl Into<String>, font: Handle<Font>) -> Self {
+    Self {
+      value: value.into(),
+      font,
+      font_size: 16.0,
+      color: wgpu::Color::WHITE,
+    }
+  }
+
+  pub fn with_size(mut self, font_size: f32) -> Self {
+    self.font_size = font_size;
+    self
+  }
+  pub fn with_color(mut self, color: wgpu::Color) -> Self {
+    self.color = color;
+    self
+  }
+}
+
+// Part of the glyph atlas drawn over a rectangle of the text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+  // From the top left of the text, y going down
+  pub rect: Rect,
+  pub uv: Rect,
+}
+
+// Where the glyphs of a Text ended up, kept up to date by the engine every update
+// Empty while the font isn't loaded
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct TextLayout {
+  pub(crate) glyphs: Vec<PositionedGlyph>,
+  pub(crate) size: Vec2,
+  pub(crate) lines: usize,
+  source: Option<LayoutSource>,
+}
+
+// What a layout was built from, it's only built again once one of these changed
+#[derive(Clone, Debug, PartialEq)]
+struct LayoutSource {
+  value: String,
+  font: u64,
+  font_size: f32,
+  max_width: Option<f32>,
+  atlas: u64,
+}
+
+impl LayoutSource {
+  fn new(text: &Text, max_width: Option<f32>, atlas: &GlyphAtlas) -> Self {
+    Self {
+      value: text.value.clone(),
+      font: text.font.id(),
+      font_size: text.font_size,
+      max_width,
+      atlas: atlas.generation(),
+    }
+  }
+}
+
+impl TextLayout {
+  // Glyphs missing from the atlas are rasterized into it
+  pub(crate) fn new(
+    text: &Text,
+    font: &Font,
+    max_width: Option<f32>,
+    atlas: &mut GlyphAtlas,
+    images: &mut Assets<Image>,
+  ) -> Self {
+    // Taken first, if glyphs move while this one is built it's built again
+    let source = LayoutSource::new(text, max_width, atlas);
+    let shaped = shape_text(font, &text.value, text.font_size, max_width);
+    let glyphs = shaped
+      .glyphs
+      .iter()
+      .filter_map(|glyph| {
+        let entry = atlas.glyph(images, &text.font, font, glyph.id, text.font_size)?;
+        let min = glyph.position + entry.offset;
+        Some((min, entry))
+      })
+      .collect::<Vec<_>>()
+      .into_iter()
+      .map(|(min, entry)| PositionedGlyph {
+        rect: Rect {
+          min,
+          max: min + entry.rect.size(),
+        },
+        // After every glyph is in, the atlas may have grown along the way
+        uv: atlas.uv(images, &entry),
+      })
+      .collect();
+    Self {
+      glyphs,
+      size: shaped.size,
+      lines: shaped.lines,
+      source: Some(source),
+    }
+  }
+
+  // Layouts of fonts that weren't loaded yet are never up to date
+  pub(crate) fn is_built_from(
+    &self,
+    text: &Text,
+    max_width: Option<f32>,
+    atlas: &GlyphAtlas,
+  ) -> bool {
+    self.source.as_ref().is_some_and(|source| {
+      source.value == text.value
+        && source.font == text.font.id()
+        && source.font_size == text.font_size
+        && source.max_width == max_width
+        && source.atlas == atlas.generation()
+    })
+  }
+
+  pub fn glyphs(&self) -> &[PositionedGlyph] {
+    &self.glyphs
+  }
+  // Of the lines, trailing spaces left out
+  pub fn size(&self) -> Vec2 {
+    self.size
+  }
+  pub fn lines(&self) -> usize {
+    self.lines
+  }
+}
@@ -1,12 +1,9 @@
-use glfw::Window;
+use crate::ecs::Component;
+use crate::rendering::WindowState;
 
-// Might wanna massively rework this once I revisit it
-
-pub struct Anchor<'a> {
-  win: &'a Window,
-  pos: (f32, f32)
-}
+use macros::Component;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AnchorPosition {
   TopLeft,
   Top,
@@ -16,60 +13,83 @@ pub enum AnchorPosition {
   Right,
   BottomLeft,
   Bottom,
-  BottomRight
+  BottomRight,
 }
 
-impl<'a> Anchor<'a> {
-  pub fn new(win: &'a Window, pos: AnchorPosition) -> Self {
-    let (x, y);
-
-    match pos {
-      AnchorPosition::TopLeft => {
-        (x, y) = (0.0, 0.0)
-      }
-      AnchorPosition::Top => {
-        (x, y) = (0.5, 0.0)
-      }
-      AnchorPosition::TopRight => {
-        (x, y) = (1.0, 0.0)
-      }
-      AnchorPosition::Left => {
-        (x, y) = (0.0, 0.5)
-      }
-      AnchorPosition::Center => {
-        (x, y) = (0.5, 0.5)
-      }
-      AnchorPosition::Right => {
-        (x, y) = (1.0, 0.5)
-      }
-      AnchorPosition::BottomLeft => {
-        (x, y) = (0.0, 1.0)
-      }
-      AnchorPosition::Bottom => {
-        (x, y) = (0.5, 1.0)
-      }
-      AnchorPosition::BottomRight => {
-        (x, y) = (1.0, 1.0)
-      }
+impl AnchorPosition {
+  // Fraction of the window size, from the top left corner
+  pub fn fraction(&self) -> (f32, f32) {
+    match self {
+      AnchorPosition::TopLeft => (0.0, 0.0),
+      AnchorPosition::Top => (0.5, 0.0),
+      AnchorPosition::TopRight => (1.0, 0.0),
+      AnchorPosition::Left => (0.0, 0.5),
+      AnchorPosition::Center => (0.5, 0.5),
+      AnchorPosition::Right => (1.0, 0.5),
+      AnchorPosition::BottomLeft => (0.0, 1.0),
+      AnchorPosition::Bottom => (0.5, 1.0),
+      AnchorPosition::BottomRight => (1.0, 1.0),
     }
+  }
+}
+
+// Point of a window that follows its size, recomputed by the engine whenever the window is resized
+// or moves to a screen with another scale factor
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Anchor {
+  window: u32,
+  pos: (f32, f32),
+  // Physical size and scale factor of the window the position was computed with
+  size: (u32, u32),
+  scale_factor: f64,
+}
 
-    Anchor { win, pos: (x, y) }
+impl Anchor {
+  pub fn new(window: u32, pos: AnchorPosition) -> Self {
+    let (x, y) = pos.fraction();
+    Self::new_custom(window, x, y)
   }
 
-  pub fn new_custom(win: &'a Window, tx: f32, ty: f32) -> Self {
-    let (mut x, mut y) = (tx, ty);
+  pub fn new_custom(window: u32, x: f32, y: f32) -> Self {
+    Anchor {
+      window,
+      pos: (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)),
+      size: (0, 0),
+      scale_factor: 1.0,
+    }
+  }
 
-    if x < 0.0 { x = 0.0; }
-    else if x > 1.0 { x = 1.0; }
-    if y < 0.0 { y = 0.0; }
-    else if y > 1.0 { y = 1.0; }
+  pub fn window(&self) -> u32 {
+    self.window
+  }
 
-    Anchor { win, pos: (x, y) }
+  pub fn fraction(&self) -> (f32, f32) {
+    self.pos
   }
 
+  // Physical pixels, what the renderer works in
   pub fn position(&self) -> (i32, i32) {
-    let (x, y) = self.win.get_size();
-    (f32::floor(x as f32 * self.pos.0) as i32,
-    f32::floor(y as f32 * self.pos.1) as i32)
+    (
+      f32::floor(self.size.0 as f32 * self.pos.0) as i32,
+      f32::floor(self.size.1 as f32 * self.pos.1) as i32,
+    )
+  }
+
+  // Logical pixels, what UI is laid out with
+  pub fn logical_position(&self) -> (f32, f32) {
+    let scale_factor = self.scale_factor as f32;
+    (
+      self.size.0 as f32 / scale_factor * self.pos.0,
+      self.size.1 as f32 / scale_factor * self.pos.1,
+    )
+  }
+
+  pub(crate) fn is_stale(&self, state: &WindowState) -> bool {
+    self.size != (state.width, state.height) || self.scale_factor != state.scale_factor
+  }
+
+  pub(crate) fn update(&mut self, state: &WindowState) {
+    self.size = (state.width, state.height);
+    self.scale_factor = state.scale_factor;
   }
 }
@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+
+use super::{
+  Anchor, BackgroundColor, Button, Checkbox, Interaction, InteractionColors, LayoutTree, Node,
+  Slider, Style, TextInput, UiImage,
+};
+use crate::asset::Assets;
+use crate::debug::DebugOverlay;
+use crate::error::{DataError, P1Error};
+use crate::event::builtin::{
+  ButtonClicked, CheckboxToggled, SliderChanged, TextChanged, TextSubmitted,
+};
+use crate::event::InputState;
+use crate::p1::P1;
+use crate::rendering::{
+  CameraView, ExtractedSprite, Image, Rect, RenderTarget, SpriteInstance, UiFrame, WindowState,
+};
+use crate::spatial::Children;
+use crate::text::{shape_text, Font, GlyphAtlas, Text, TextLayout};
+
+use glam::{Mat4, Vec2, Vec3};
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+// Layout, widgets and drawing of the UI trees, P1::update and P1::render go through here
+impl P1 {
+  // Called on Resized and ScaleFactorChanged, and every update for anchors added since
+  // Anchors of a window that is gone keep their last position
+  pub(crate) fn update_anchors(&mut self) {
+    for entity in self.entities_with::<Anchor>() {
+      let Some(window) = self.with_component::<Anchor, _>(entity, |anchor| anchor.window()) else {
+        continue;
+      };
+      let Some(state) = self.with_component::<WindowState, _>(window, |state| *state) else {
+        continue;
+      };
+      self.with_component_mut::<Anchor, _>(entity, |anchor| {
+        if anchor.is_stale(&state) {
+          anchor.update(&state);
+        }
+      });
+    }
+  }
+
+  // Lays out every UI tree from scratch
+  // The trees are small enough that tracking changes isn't worth it
+  pub(crate) fn layout_ui(&mut self) -> Result<(), DataError> {
+    let entities = self.entities_with::<Style>();
+    // Nodes whose Style was removed
+    for entity in self.entities_with::<Node>() {
+      if entities.binary_search(&entity).is_err() {
+        self.remove_component::<Node>(entity)?;
+      }
+    }
+    if entities.is_empty() {
+      return Ok(());
+    }
+
+    let mut tree = LayoutTree::new();
+    let indices: HashMap<u32, usize> = entities
+      .iter()
+      .map(|entity| {
+        let style = self
+          .with_component::<Style, _>(*entity, |style| *style)
+          .unwrap_or_default();
+        (*entity, tree.add(style))
+      })
+      .collect();
+
+    // Text is measured on one line, or wrapped to the width of its node when that's set in pixels
+    // Percentages aren't known this early, those nodes are as tall as their text on one line
+    {
+      let fonts = self.resource_manager.get::<Assets<Font>>().unwrap();
+      for entity in entities.iter() {
+        let style = self
+          .with_component::<Style, _>(*entity, |style| *style)
+          .unwrap_or_default();
+        let max_width = style
+          .width
+          .resolve(None)
+          .map(|width| style.content_width(width));
+        let size = self
+          .with_component::<Text, _>(*entity, |text| {
+            let font = fonts.get(&text.font)?;
+            Some(shape_text(font, &text.value, text.font_size, max_width).size)
+          })
+          .flatten();
+        if let Some(size) = size {
+          tree.set_content_size(indices[entity], size);
+        }
+      }
+    }
+    for entity in entities.iter() {
+      let children = self
+        .with_component::<Children, _>(*entity, |children| children.0.clone())
+        .unwrap_or_default();
+      for child in children {
+        if let Some(child) = indices.get(&child) {
+          tree.add_child(indices[entity], *child);
+        }
+      }
+    }
+
+    for entity in entities.iter() {
+      if self
+        .parent(*entity)
+        .is_some_and(|parent| indices.contains_key(&parent))
+      {
+        continue;
+      }
+      let anchor = self.with_component::<Anchor, _>(*entity, |anchor| *anchor);
+      let container = anchor
+        .and_then(|anchor| {
+          self.with_component::<WindowState, _>(anchor.window(), |state| state.logical_size())
+        })
+        .map(|(width, height)| Rect::new(0.0, 0.0, width as f32, height as f32));
+      let fraction = anchor.map_or((0.0, 0.0), |anchor| anchor.fraction());
+      tree.compute(indices[entity], container, fraction);
+    }
+
+    for entity in entities {
+      let node = Node::new(tree.rect(indices[&entity]));
+      if self
+        .with_component_mut::<Node, _>(entity, |current| *current = node)
+        .is_none()
+      {
+        self.add_component(entity, node)?;
+      }
+    }
+    Ok(())
+  }
+
+  // Every UI node from the roots down, siblings in order, with the window of their root's Anchor
+  fn ui_nodes(&self) -> Vec<(u32, Option<u32>)> {
+    let entities = self.entities_with::<Style>();
+    let is_node = |entity: u32| entities.binary_search(&entity).is_ok();
+
+    let mut nodes = Vec::with_capacity(entities.len());
+    let mut stack: Vec<_> = entities
+      .iter()
+      .rev()
+      .filter(|entity| self.parent(**entity).is_none_or(|parent| !is_node(parent)))
+      .map(|root| {
+        let window = self.with_component::<Anchor, _>(*root, |anchor| anchor.window());
+        (*root, window)
+      })
+      .collect();
+    while let Some((entity, window)) = stack.pop() {
+      nodes.push((entity, window));
+      let children = self
+        .with_component::<Children, _>(entity, |children| children.0.clone())
+        .unwrap_or_default();
+      stack.extend(
+        children
+          .into_iter()
+          .rev()
+          .filter(|child| is_node(*child))
+          .map(|child| (child, window)),
+      );
+    }
+    nodes
+  }
+
+  // Hover, press and focus of the nodes with an Interaction, and what widgets do with them
+  pub(crate) fn update_interactions(&mut self) -> Result<(), P1Error> {
+    let mut widgets = self.entities_with::<Button>();
+    widgets.extend(self.entities_with::<Checkbox>());
+    widgets.extend(self.entities_with::<Slider>());
+    widgets.extend(self.entities_with::<TextInput>());
+    for entity in widgets {
+      if !self.has_component::<Interaction>(entity)? {
+        self.add_component(entity, Interaction::default())?;
+      }
+    }
+
+    let (cursor, just_pressed, just_released, text, enter) = {
+      let input = self.resource_manager.get::<InputState>().unwrap();
+      (
+        input.cursor(),
+        input.mouse_buttons().just_pressed(MouseButton::Left),
+        input.mouse_buttons().just_released(MouseButton::Left),
+        input.text().to_owned(),
+        input.just_pressed(KeyCode::Enter) || input.just_pressed(KeyCode::NumpadEnter),
+      )
+    };
+
+    // The cursor is in physical pixels, nodes in logical ones
+    let mut interactive = Vec::new();
+    for (entity, window) in self.ui_nodes() {
+      let Some(interaction) =
+        self.with_component::<Interaction, _>(entity, |interaction| *interaction)
+      else {
+        continue;
+      };
+      let Some(rect) = self.with_component::<Node, _>(entity, |node| node.rect()) else {
+        continue;
+      };
+      let scale_factor = window
+        .and_then(|window| {
+          self.with_component::<WindowState, _>(window, |state| state.scale_factor as f32)
+        })
+        .unwrap_or(1.0);
+      let cursor = cursor.map(|(x, y)| Vec2::new(x as f32, y as f32) / scale_factor);
+      interactive.push((entity, interaction, rect, cursor));
+    }
+
+    // Later nodes are drawn over earlier ones
+    let hovered = interactive
+      .iter()
+      .rev()
+      .find(|(_, _, rect, cursor)| cursor.is_some_and(|cursor| rect.contains(cursor)))
+      .map(|(entity, ..)| *entity);
+    let was_pressed = interactive
+      .iter()
+      .find(|(_, interaction, ..)| interaction.pressed)
+      .map(|(entity, ..)| *entity);
+    let was_focused = interactive
+      .iter()
+      .find(|(_, interaction, ..)| interaction.focused)
+      .map(|(entity, ..)| *entity);
+    // A press and its release can land in the same frame
+    let pressed_on = if just_pressed { hovered } else { was_pressed };
+    let pressed = pressed_on.filter(|_| !just_released);
+    let focused = if just_pressed { hovered } else { was_focused };
+    let clicked = pressed_on.filter(|entity| just_released && hovered == Some(*entity));
+
+    for (entity, ..) in interactive.iter() {
+      let interaction = Interaction {
+        hovered: hovered == Some(*entity),
+        pressed: pressed == Some(*entity),
+        focused: focused == Some(*entity),
+      };
+      self.with_component_mut::<Interaction, _>(*entity, |current| *current = interaction);
+      if let Some(color) =
+        self.with_component::<InteractionColors, _>(*entity, |colors| colors.color(&interaction))
+      {
+        if self
+          .with_component_mut::<BackgroundColor, _>(*entity, |background| background.0 = color)
+          .is_none()
+        {
+          self.add_component(*entity, BackgroundColor(color))?;
+        }
+      }
+    }
+
+    if let Some(entity) = clicked {
+      if self.has_component::<Button>(entity)? {
+        self.emit::<ButtonClicked>(ButtonClicked { entity })?;
+      }
+      let toggled = self.with_component_mut::<Checkbox, _>(entity, |checkbox| {
+        checkbox.checked = !checkbox.checked;
+        checkbox.checked
+      });
+      if let Some(checked) = toggled {
+        self.emit::<CheckboxToggled>(CheckboxToggled { entity, checked })?;
+      }
+    }
+
+    let dragged = interactive
+      .iter()
+      .find(|(entity, ..)| pressed_on == Some(*entity))
+      .and_then(|(entity, _, rect, cursor)| cursor.map(|cursor| (*entity, *rect, cursor.x)));
+    if let Some((entity, rect, x)) = dragged {
+      let changed = self
+        .with_component_mut::<Slider, _>(entity, |slider| {
+          let fraction = ((x - rect.min.x) / rect.size().x.max(1.0)).clamp(0.0, 1.0);
+          let value = slider.min + (slider.max - slider.min) * fraction;
+          let changed = value != slider.value;
+          slider.value = value;
+          changed.then_some(value)
+        })
+        .flatten();
+      if let Some(value) = changed {
+        self.emit::<SliderChanged>(SliderChanged { entity, value })?;
+      }
+    }
+
+    if let Some(entity) = focused {
+      let edited = self.with_component_mut::<TextInput, _>(entity, |input| {
+        let before = input.value.clone();
+        input.type_text(&text);
+        (input.value != before, input.value.clone())
+      });
+      if let Some((changed, value)) = edited {
+        if changed {
+          self.with_component_mut::<Text, _>(entity, |text| text.value = value.clone());
+          self.emit::<TextChanged>(TextChanged {
+            entity,
+            value: value.clone(),
+          })?;
+        }
+        if enter {
+          self.emit::<TextSubmitted>(TextSubmitted { entity, value })?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  // Quads of the UI trees drawn on that target, in tree order so children cover their parents
+  pub(crate) fn extract_ui(
+    &self,
+    target: RenderTarget,
+    size: (u32, u32),
+    images: &Assets<Image>,
+  ) -> Option<UiFrame> {
+    let nodes: Vec<_> = self
+      .ui_nodes()
+      .into_iter()
+      .filter(|(_, window)| window.is_none_or(|window| target == RenderTarget::Window(window)))
+      .collect();
+    let overlay = self
+      .resource_manager
+      .get::<DebugOverlay>()
+      .filter(|overlay| overlay.enabled);
+    if (nodes.is_empty() && overlay.is_none()) || size.0 == 0 || size.1 == 0 {
+      return None;
+    }
+
+    let atlas = self.resource_manager.get::<GlyphAtlas>().unwrap();
+    let mut sprites = Vec::new();
+    let mut quad = |texture: u64, rect: Rect, uv: Rect, color: wgpu::Color| {
+      // Flipped vertically, UI goes down from the top where sprites go up
+      let model = Mat4::from_translation(Vec3::new(rect.min.x, rect.max.y, 0.0))
+        * Mat4::from_scale(Vec3::new(rect.size().x, -rect.size().y, 1.0));
+      sprites.push(ExtractedSprite {
+        texture,
+        layer: sprites.len() as i32,
+        instance: SpriteInstance::new(model, color).with_uv(uv),
+      });
+    };
+    for (entity, _) in nodes {
+      let Some(rect) = self.with_component::<Node, _>(entity, |node| node.rect()) else {
+        continue;
+      };
+      if let Some(BackgroundColor(color)) =
+        self.with_component::<BackgroundColor, _>(entity, |background| *background)
+      {
+        quad(self.white.id(), rect, Rect::UNIT, color);
+      }
+      if let Some((texture, color)) =
+        self.with_component::<UiImage, _>(entity, |image| (image.texture.id(), image.color))
+      {
+        if images.get_by_id(texture).is_some() {
+          quad(texture, rect, Rect::UNIT, color);
+        }
+      }
+      if let Some(slider) = self.with_component::<Slider, _>(entity, |slider| *slider) {
+        let width = rect.size().y / 2.0;
+        let x = rect.min.x + (rect.size().x - width) * slider.fraction();
+        let handle = Rect::new(x, rect.min.y, x + width, rect.max.y);
+        quad(self.white.id(), handle, Rect::UNIT, slider.handle_color);
+      }
+      if let Some(checkbox) = self
+        .with_component::<Checkbox, _>(entity, |checkbox| *checkbox)
+        .filter(|checkbox| checkbox.checked)
+      {
+        let inset = rect.size() / 4.0;
+        let mark = Rect {
+          min: rect.min + inset,
+          max: rect.max - inset,
+        };
+        quad(self.white.id(), mark, Rect::UNIT, checkbox.color);
+      }
+      // Over everything else of the node, from the top left of its padding
+      let text = self
+        .with_component::<Text, _>(entity, |text| text.color)
+        .zip(self.with_component::<TextLayout, _>(entity, |layout| layout.clone()));
+      if let Some((color, layout)) = text {
+        let padding = self
+          .with_component::<Style, _>(entity, |style| style.padding)
+          .unwrap_or_default();
+        let origin = rect.min
+          + Vec2::new(
+            padding.left.resolve(None).unwrap_or(0.0),
+            padding.top.resolve(None).unwrap_or(0.0),
+          );
+        for glyph in layout.glyphs() {
+          let rect = Rect {
+            min: origin + glyph.rect.min,
+            max: origin + glyph.rect.max,
+          };
+          quad(atlas.image().id(), rect, glyph.uv, color);
+        }
+      }
+    }
+    // Over the whole UI, in the top left corner of every target
+    if let Some(overlay) = overlay {
+      let margin = Vec2::splat(4.0);
+      let background = Rect {
+        min: Vec2::ZERO,
+        max: overlay.layout.size() + 2.0 * margin,
+      };
+      let color = wgpu::Color {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 0.75,
+      };
+      quad(self.white.id(), background, Rect::UNIT, color);
+      for glyph in overlay.layout.glyphs() {
+        let rect = Rect {
+          min: margin + glyph.rect.min,
+          max: margin + glyph.rect.max,
+        };
+        quad(atlas.image().id(), rect, glyph.uv, wgpu::Color::WHITE);
+      }
+    }
+
+    let scale_factor = match target {
+      RenderTarget::Window(window) => self
+        .with_component::<WindowState, _>(window, |state| state.scale_factor as f32)
+        .unwrap_or(1.0),
+      RenderTarget::Offscreen => 1.0,
+    };
+    let (width, height) = (size.0 as f32, size.1 as f32);
+    Some(UiFrame {
+      camera: CameraView {
+        entity: u32::MAX,
+        order: i32::MAX,
+        viewport: (0.0, 0.0, width, height),
+        view: Mat4::IDENTITY,
+        projection: Mat4::orthographic_rh(
+          0.0,
+          width / scale_factor,
+          height / scale_factor,
+          0.0,
+          -1.0,
+          1.0,
+        ),
+        position: Vec3::ZERO,
+        bind_group: None,
+      },
+      sprites,
+    })
+  }
+}
@@ -0,0 +1,330 @@
+use super::{AlignItems, JustifyContent, Style, UiRect};
+use crate::rendering::Rect;
+
+use glam::Vec2;
+
+// Main axis first, then cross axis
+fn axes(size: Vec2, row: bool) -> (f32, f32) {
+  if row {
+    (size.x, size.y)
+  } else {
+    (size.y, size.x)
+  }
+}
+
+fn from_axes(main: f32, cross: f32, row: bool) -> Vec2 {
+  if row {
+    Vec2::new(main, cross)
+  } else {
+    Vec2::new(cross, main)
+  }
+}
+
+// Left and top, then right and bottom
+fn resolve_rect(rect: &UiRect, parent: Option<Vec2>) -> (Vec2, Vec2) {
+  let x = parent.map(|parent| parent.x);
+  let y = parent.map(|parent| parent.y);
+  (
+    Vec2::new(
+      rect.left.resolve(x).unwrap_or(0.0),
+      rect.top.resolve(y).unwrap_or(0.0),
+    ),
+    Vec2::new(
+      rect.right.resolve(x).unwrap_or(0.0),
+      rect.bottom.resolve(y).unwrap_or(0.0),
+    ),
+  )
+}
+
+// Max first so that min wins when they conflict
+fn clamp(style: &Style, size: Vec2, parent: Option<Vec2>) -> Vec2 {
+  let x = parent.map(|parent| parent.x);
+  let y = parent.map(|parent| parent.y);
+  let mut size = size;
+  if let Some(max) = style.max_width.resolve(x) {
+    size.x = size.x.min(max);
+  }
+  if let Some(max) = style.max_height.resolve(y) {
+    size.y = size.y.min(max);
+  }
+  if let Some(min) = style.min_width.resolve(x) {
+    size.x = size.x.max(min);
+  }
+  if let Some(min) = style.min_height.resolve(y) {
+    size.y = size.y.max(min);
+  }
+  size.max(Vec2::ZERO)
+}
+
+struct LayoutNode {
+  style: Style,
+  children: Vec<usize>,
+  // Size of what the node holds itself, like its text, taken as if it were one more child
+  content: Option<Vec2>,
+  rect: Rect,
+}
+
+// Single line flexbox over a tree of styles, positions are logical pixels from the top left with y going down
+// The engine builds one from the UI entities every update, it can also be used on its own
+#[derive(Default)]
+pub struct LayoutTree {
+  nodes: Vec<LayoutNode>,
+}
+
+impl LayoutTree {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add(&mut self, style: Style) -> usize {
+    self.nodes.push(LayoutNode {
+      style,
+      children: Vec::new(),
+      content: None,
+      rect: Rect::new(0.0, 0.0, 0.0, 0.0),
+    });
+    self.nodes.len() - 1
+  }
+
+  pub fn add_child(&mut self, parent: usize, child: usize) {
+    self.nodes[parent].children.push(child);
+  }
+
+  pub fn set_content_size(&mut self, node: usize, size: Vec2) {
+    self.nodes[node].content = Some(size);
+  }
+
+  pub fn rect(&self, node: usize) -> Rect {
+    self.nodes[node].rect
+  }
+
+  // Lays out the tree under root, placed within the container so that the point at fraction of the root
+  // (margins included) sits on the same point of the container
+  pub fn compute(&mut self, root: usize, container: Option<Rect>, fraction: (f32, f32)) {
+    let style = self.nodes[root].style;
+    let parent = container.map(|container| container.size());
+    let size = Vec2::new(
+      style
+        .width
+        .resolve(parent.map(|parent| parent.x))
+        .unwrap_or_else(|| self.intrinsic(root, parent).x),
+      style
+        .height
+        .resolve(parent.map(|parent| parent.y))
+        .unwrap_or_else(|| self.intrinsic(root, parent).y),
+    );
+    let size = clamp(&style, size, parent);
+
+    let (margin_start, margin_end) = resolve_rect(&style.margin, parent);
+    let outer = size + margin_start + margin_end;
+    let container = container.unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0));
+    let position = container.min + Vec2::from(fraction) * (container.size() - outer) + margin_start;
+
+    self.place(root, position, size, parent);
+  }
+
+  // Size a node takes when nothing stretches or squeezes it
+  fn intrinsic(&self, node: usize, parent: Option<Vec2>) -> Vec2 {
+    let style = &self.nodes[node].style;
+    let width = style.width.resolve(parent.map(|parent| parent.x));
+    let height = style.height.resolve(parent.map(|parent| parent.y));
+    if let (Some(width), Some(height)) = (width, height) {
+      return clamp(style, Vec2::new(width, height), parent);
+    }
+
+    let row = style.flex_direction.is_row();
+    let (padding_start, padding_end) = resolve_rect(&style.padding, parent);
+    let children = &self.nodes[node].children;
+    let (mut main, mut cross) = self.nodes[node]
+      .content
+      .map_or((0.0, 0.0), |content| axes(content, row));
+    for child in children {
+      let child_style = &self.nodes[*child].style;
+      let (margin_start, margin_end) = resolve_rect(&child_style.margin, None);
+      let outer = self.intrinsic(*child, None) + margin_start + margin_end;
+      let (child_main, child_cross) = axes(outer, row);
+      main += child_main;
+      cross = cross.max(child_cross);
+    }
+    let items = children.len() + self.nodes[node].content.is_some() as usize;
+    main += style.gap * items.saturating_sub(1) as f32;
+
+    let content = from_axes(main, cross, row) + padding_start + padding_end;
+    clamp(
+      style,
+      Vec2::new(width.unwrap_or(content.x), height.unwrap_or(content.y)),
+      parent,
+    )
+  }
+
+  fn place(&mut self, node: usize, position: Vec2, size: Vec2, parent: Option<Vec2>) {
+    self.nodes[node].rect = Rect {
+      min: position,
+      max: position + size,
+    };
+
+    let style = self.nodes[node].style;
+    let children = self.nodes[node].children.clone();
+    if children.is_empty() {
+      return;
+    }
+
+    let (padding_start, padding_end) = resolve_rect(&style.padding, parent);
+    let content_position = position + padding_start;
+    let content = (size - padding_start - padding_end).max(Vec2::ZERO);
+    let row = style.flex_direction.is_row();
+    let (content_main, content_cross) = axes(content, row);
+
+    // Base sizes along the main axis, margins resolved against the content box
+    let mut mains = Vec::with_capacity(children.len());
+    let mut margins = Vec::with_capacity(children.len());
+    for child in children.iter() {
+      let child_style = &self.nodes[*child].style;
+      let margin = resolve_rect(&child_style.margin, Some(content));
+      let explicit = if row {
+        child_style.width.resolve(Some(content.x))
+      } else {
+        child_style.height.resolve(Some(content.y))
+      };
+      let base = explicit.unwrap_or_else(|| axes(self.intrinsic(*child, Some(content)), row).0);
+      let base = axes(
+        clamp(child_style, from_axes(base, 0.0, row), Some(content)),
+        row,
+      )
+      .0;
+      mains.push(base);
+      margins.push(margin);
+    }
+
+    let gaps = style.gap * (children.len() - 1) as f32;
+    let margins_main: f32 = margins
+      .iter()
+      .map(|(start, end)| axes(*start + *end, row).0)
+      .sum();
+    let free = content_main - mains.iter().sum::<f32>() - margins_main - gaps;
+
+    // Children stopped by their min or max are frozen there and the rest is shared again by the others
+    let growing = free > 0.0;
+    let factors: Vec<_> = children
+      .iter()
+      .zip(mains.iter())
+      .map(|(child, base)| {
+        let child_style = &self.nodes[*child].style;
+        if growing {
+          child_style.flex_grow
+        } else {
+          // Shrinking is scaled by the base size so small children don't vanish first
+          child_style.flex_shrink * base
+        }
+      })
+      .collect();
+    let bases = mains.clone();
+    let mut frozen: Vec<_> = factors.iter().map(|factor| *factor <= 0.0).collect();
+    while free != 0.0 && frozen.contains(&false) {
+      let left = content_main
+        - mains
+          .iter()
+          .zip(bases.iter())
+          .zip(frozen.iter())
+          .map(|((main, base), frozen)| if *frozen { *main } else { *base })
+          .sum::<f32>()
+        - margins_main
+        - gaps;
+      let total: f32 = factors
+        .iter()
+        .zip(frozen.iter())
+        .filter(|(_, frozen)| !**frozen)
+        .map(|(factor, _)| factor)
+        .sum();
+
+      let mut violation = 0.0;
+      let mut targets = vec![0.0; children.len()];
+      for (index, child) in children.iter().enumerate() {
+        if frozen[index] {
+          continue;
+        }
+        targets[index] = bases[index] + left * factors[index] / total;
+        let child_style = &self.nodes[*child].style;
+        mains[index] = axes(
+          clamp(
+            child_style,
+            from_axes(targets[index], 0.0, row),
+            Some(content),
+          ),
+          row,
+        )
+        .0;
+        violation += mains[index] - targets[index];
+      }
+
+      // Too much left over freezes the children held at their min, too little the ones at their max
+      for index in 0..children.len() {
+        let (main, target) = (mains[index], targets[index]);
+        if !frozen[index]
+          && (violation == 0.0
+            || (violation > 0.0 && main > target)
+            || (violation < 0.0 && main < target))
+        {
+          frozen[index] = true;
+        }
+      }
+    }
+
+    let remaining = content_main - mains.iter().sum::<f32>() - margins_main - gaps;
+    let count = children.len() as f32;
+    let (mut cursor, between) = match style.justify_content {
+      _ if remaining <= 0.0 => (0.0, 0.0),
+      JustifyContent::Start => (0.0, 0.0),
+      JustifyContent::End => (remaining, 0.0),
+      JustifyContent::Center => (remaining / 2.0, 0.0),
+      JustifyContent::SpaceBetween if children.len() > 1 => (0.0, remaining / (count - 1.0)),
+      JustifyContent::SpaceBetween => (0.0, 0.0),
+      JustifyContent::SpaceAround => (remaining / count / 2.0, remaining / count),
+      JustifyContent::SpaceEvenly => (remaining / (count + 1.0), remaining / (count + 1.0)),
+    };
+
+    for ((child, main), (margin_start, margin_end)) in children.iter().zip(mains).zip(margins) {
+      let child_style = self.nodes[*child].style;
+      let (margin_main_start, margin_cross_start) = axes(margin_start, row);
+      let (margin_main_end, margin_cross_end) = axes(margin_end, row);
+
+      let align = child_style.align_self.unwrap_or(style.align_items);
+      let explicit = if row {
+        child_style.height.resolve(Some(content.y))
+      } else {
+        child_style.width.resolve(Some(content.x))
+      };
+      let cross = match (explicit, align) {
+        (Some(cross), _) => cross,
+        (None, AlignItems::Stretch) => content_cross - margin_cross_start - margin_cross_end,
+        (None, _) => axes(self.intrinsic(*child, Some(content)), row).1,
+      };
+      let cross = axes(
+        clamp(&child_style, from_axes(main, cross, row), Some(content)),
+        row,
+      )
+      .1;
+      let cross_position = match align {
+        AlignItems::Start | AlignItems::Stretch => margin_cross_start,
+        AlignItems::End => content_cross - cross - margin_cross_end,
+        AlignItems::Center => {
+          (content_cross - cross - margin_cross_start - margin_cross_end) / 2.0 + margin_cross_start
+        }
+      };
+
+      let mut main_position = cursor + margin_main_start;
+      cursor = main_position + main + margin_main_end + style.gap + between;
+      // Reversed directions are the same layout mirrored along the main axis
+      if style.flex_direction.is_reverse() {
+        main_position = content_main - main_position - main;
+      }
+
+      self.place(
+        *child,
+        content_position + from_axes(main_position, cross_position, row),
+        from_axes(main, cross, row),
+        Some(content),
+      );
+    }
+  }
+}
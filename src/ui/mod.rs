@@ -1,2 +1,371 @@
 mod anchor;
-pub use anchor::Anchor;
+mod engine;
+mod layout;
+mod node;
+mod style;
+mod widget;
+
+pub use anchor::{Anchor, AnchorPosition};
+pub use layout::LayoutTree;
+pub use node::Node;
+pub use style::{AlignItems, FlexDirection, JustifyContent, Style, UiRect, Val};
+pub use widget::{
+  BackgroundColor, Button, Checkbox, Interaction, InteractionColors, Slider, TextInput, UiImage,
+};
+
+pub(crate) use widget::type_text;
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    AlignItems, Anchor, AnchorPosition, BackgroundColor, Button, Checkbox, FlexDirection,
+    Interaction, InteractionColors, JustifyContent, LayoutTree, Node, Slider, Style, TextInput,
+    UiRect, Val,
+  };
+  use crate::asset::Handle;
+  use crate::event::builtin::{
+    ButtonClicked, CheckboxToggled, SliderChanged, TextChanged, TextSubmitted,
+  };
+  use crate::event::{Clock, InputEvent, BACKSPACE};
+  use crate::p1::P1;
+  use crate::rendering::{Rect, RenderTarget, Renderer, WindowConfig, WindowHandler, WindowState};
+  use crate::testing::gpu_available;
+  use crate::text::Text;
+
+  use winit::dpi::PhysicalSize;
+  use winit::event::{MouseButton, WindowEvent};
+  use winit::keyboard::KeyCode;
+  use winit::window::WindowId;
+
+  #[test]
+  fn anchors_follow_window_size() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let id = WindowId::from(1);
+    let window = engine.create_entity();
+    engine
+      .add_component(window, WindowConfig::default())
+      .unwrap();
+    engine
+      .add_component(window, WindowState::new(id, 1280, 720, 2.0))
+      .unwrap();
+
+    let corner = engine.create_entity();
+    engine
+      .add_component(corner, Anchor::new(window, AnchorPosition::BottomRight))
+      .unwrap();
+    let custom = engine.create_entity();
+    engine
+      .add_component(custom, Anchor::new_custom(window, 0.25, 1.5))
+      .unwrap();
+
+    engine.update().unwrap();
+    let position = |engine: &P1, entity| {
+      engine
+        .with_component::<Anchor, _>(entity, |anchor| {
+          (anchor.position(), anchor.logical_position())
+        })
+        .unwrap()
+    };
+    assert_eq!(position(&engine, corner), ((1280, 720), (640.0, 360.0)));
+    assert_eq!(position(&engine, custom), ((320, 720), (160.0, 360.0)));
+
+    // Recomputed before anything listening to the events runs
+    let mut handler = WindowHandler::new(&mut engine);
+    handler.track(id, window);
+    let resized = WindowEvent::Resized(PhysicalSize::new(800, 600));
+    handler.handle_window_event(id, &resized).unwrap();
+    assert_eq!(position(&engine, corner), ((800, 600), (400.0, 300.0)));
+    assert_eq!(position(&engine, custom), ((200, 600), (100.0, 300.0)));
+  }
+
+  fn px(width: f32, height: f32) -> Style {
+    Style::default().with_size(Val::Px(width), Val::Px(height))
+  }
+
+  #[test]
+  fn rows_grow_and_stretch() {
+    let mut tree = LayoutTree::new();
+    let mut style = px(300.0, 100.0);
+    style.padding = UiRect::all(Val::Px(10.0));
+    style.gap = 10.0;
+    let root = tree.add(style);
+    let fixed = tree.add(Style {
+      width: Val::Px(50.0),
+      ..Default::default()
+    });
+    let one = tree.add(Style::default().with_grow(1.0));
+    let two = tree.add(Style::default().with_grow(2.0));
+    for child in [fixed, one, two] {
+      tree.add_child(root, child);
+    }
+
+    tree.compute(root, None, (0.0, 0.0));
+    assert_eq!(tree.rect(root), Rect::new(0.0, 0.0, 300.0, 100.0));
+    assert_eq!(tree.rect(fixed), Rect::new(10.0, 10.0, 60.0, 90.0));
+    assert_eq!(tree.rect(one), Rect::new(70.0, 10.0, 140.0, 90.0));
+    assert_eq!(tree.rect(two), Rect::new(150.0, 10.0, 290.0, 90.0));
+  }
+
+  #[test]
+  fn columns_center() {
+    let mut tree = LayoutTree::new();
+    let mut style = px(100.0, 200.0).with_direction(FlexDirection::Column);
+    style.justify_content = JustifyContent::Center;
+    style.align_items = AlignItems::Center;
+    style.gap = 10.0;
+    let root = tree.add(style);
+    let first = tree.add(px(40.0, 20.0));
+    let second = tree.add(px(60.0, 30.0));
+    tree.add_child(root, first);
+    tree.add_child(root, second);
+
+    tree.compute(root, None, (0.0, 0.0));
+    assert_eq!(tree.rect(first), Rect::new(30.0, 70.0, 70.0, 90.0));
+    assert_eq!(tree.rect(second), Rect::new(20.0, 100.0, 80.0, 130.0));
+  }
+
+  #[test]
+  fn reversed_rows_space_between() {
+    let mut tree = LayoutTree::new();
+    let mut style = px(200.0, 50.0).with_direction(FlexDirection::RowReverse);
+    style.justify_content = JustifyContent::SpaceBetween;
+    style.align_items = AlignItems::Start;
+    let root = tree.add(style);
+    let mut first = px(20.0, 10.0);
+    first.margin.left = Val::Px(5.0);
+    let first = tree.add(first);
+    let second = tree.add(px(30.0, 10.0));
+    tree.add_child(root, first);
+    tree.add_child(root, second);
+
+    // The first child ends up on the right, its left margin keeps it off its neighbour
+    tree.compute(root, None, (0.0, 0.0));
+    assert_eq!(tree.rect(first), Rect::new(175.0, 0.0, 195.0, 10.0));
+    assert_eq!(tree.rect(second), Rect::new(0.0, 0.0, 30.0, 10.0));
+  }
+
+  #[test]
+  fn flexing_respects_min_and_max_sizes() {
+    let mut tree = LayoutTree::new();
+    let root = tree.add(px(100.0, 20.0));
+    let first = tree.add(px(80.0, 20.0));
+    let mut second = px(80.0, 20.0);
+    second.min_width = Val::Px(70.0);
+    let second = tree.add(second);
+    tree.add_child(root, first);
+    tree.add_child(root, second);
+
+    // The second stops at its min, the first takes the rest of the shrinking so both still fit
+    tree.compute(root, None, (0.0, 0.0));
+    assert_eq!(tree.rect(first), Rect::new(0.0, 0.0, 30.0, 20.0));
+    assert_eq!(tree.rect(second), Rect::new(30.0, 0.0, 100.0, 20.0));
+
+    // Growing the same way, the child held at its max leaves the rest to the other
+    let root = tree.add(px(100.0, 20.0));
+    let mut first = px(0.0, 20.0).with_grow(1.0);
+    first.max_width = Val::Px(20.0);
+    let first = tree.add(first);
+    let second = tree.add(px(0.0, 20.0).with_grow(1.0));
+    tree.add_child(root, first);
+    tree.add_child(root, second);
+    tree.compute(root, None, (0.0, 0.0));
+    assert_eq!(tree.rect(first), Rect::new(0.0, 0.0, 20.0, 20.0));
+    assert_eq!(tree.rect(second), Rect::new(20.0, 0.0, 100.0, 20.0));
+  }
+
+  #[test]
+  fn anchored_roots_fill_their_window() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let window = engine.create_entity();
+    engine
+      .add_component(window, WindowState::new(WindowId::from(1), 800, 600, 2.0))
+      .unwrap();
+
+    let root = engine.create_entity();
+    let mut style = Style {
+      width: Val::Percent(50.0),
+      ..Default::default()
+    };
+    style.margin = UiRect::all(Val::Px(10.0));
+    engine.add_component(root, style).unwrap();
+    engine
+      .add_component(root, Anchor::new(window, AnchorPosition::BottomRight))
+      .unwrap();
+    let child = engine.create_entity();
+    engine.add_component(child, px(100.0, 40.0)).unwrap();
+    engine.set_parent(child, root).unwrap();
+
+    // 400 by 300 logical pixels, the root is as tall as its child
+    engine.update().unwrap();
+    let rect = |entity| {
+      engine
+        .with_component::<Node, _>(entity, |node| node.rect())
+        .unwrap()
+    };
+    assert_eq!(rect(root), Rect::new(190.0, 250.0, 390.0, 290.0));
+    assert_eq!(rect(child), Rect::new(190.0, 250.0, 290.0, 290.0));
+
+    // Without its style the child isn't part of the layout anymore
+    engine.remove_component::<Style>(child).unwrap();
+    engine.update().unwrap();
+    assert!(!engine.has_component::<Node>(child).unwrap());
+  }
+
+  // Button, checkbox, slider and text input stacked from the top left
+  fn widgets(engine: &mut P1) -> [u32; 4] {
+    let root = engine.create_entity();
+    engine
+      .add_component(root, Style::default().with_direction(FlexDirection::Column))
+      .unwrap();
+    let button = engine.create_entity();
+    engine.add_component(button, px(100.0, 30.0)).unwrap();
+    engine.add_component(button, Button).unwrap();
+    let checkbox = engine.create_entity();
+    engine.add_component(checkbox, px(20.0, 20.0)).unwrap();
+    engine
+      .add_component(checkbox, Checkbox::new(false))
+      .unwrap();
+    let slider = engine.create_entity();
+    engine.add_component(slider, px(100.0, 20.0)).unwrap();
+    engine
+      .add_component(slider, Slider::new(0.0, 100.0, 0.0))
+      .unwrap();
+    let input = engine.create_entity();
+    engine.add_component(input, px(100.0, 30.0)).unwrap();
+    engine.add_component(input, TextInput::new("")).unwrap();
+    // Never loaded, the text is kept in sync all the same
+    engine
+      .add_component(input, Text::new("", Handle::weak(u64::MAX)))
+      .unwrap();
+    for child in [button, checkbox, slider, input] {
+      engine.set_parent(child, root).unwrap();
+    }
+    [button, checkbox, slider, input]
+  }
+
+  fn click(engine: &mut P1, x: f64, y: f64) {
+    engine.send_input(InputEvent::CursorMoved(x, y));
+    engine.send_input(InputEvent::MousePressed(MouseButton::Left));
+    engine.send_input(InputEvent::MouseReleased(MouseButton::Left));
+    engine.update().unwrap();
+  }
+
+  #[test]
+  fn widgets_react_to_input() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    let [button, checkbox, slider, input] = widgets(&mut engine);
+    let colors = InteractionColors {
+      normal: wgpu::Color::BLACK,
+      hovered: wgpu::Color::BLUE,
+      pressed: wgpu::Color::RED,
+      focused: wgpu::Color::GREEN,
+    };
+    engine.add_component(button, colors).unwrap();
+    engine.update().unwrap();
+    let interaction = |engine: &P1, entity| {
+      engine
+        .with_component::<Interaction, _>(entity, |interaction| *interaction)
+        .unwrap()
+    };
+    let background = |engine: &P1, entity| {
+      engine
+        .with_component::<BackgroundColor, _>(entity, |background| background.0)
+        .unwrap()
+    };
+    assert_eq!(background(&engine, button), wgpu::Color::BLACK);
+
+    // Released somewhere else, not a click
+    engine.send_input(InputEvent::CursorMoved(10.0, 10.0));
+    engine.send_input(InputEvent::MousePressed(MouseButton::Left));
+    engine.update().unwrap();
+    engine.send_input(InputEvent::CursorMoved(150.0, 10.0));
+    engine.send_input(InputEvent::MouseReleased(MouseButton::Left));
+    engine.update().unwrap();
+    assert!(engine.latest_event::<ButtonClicked>().is_none());
+    assert!(!interaction(&engine, button).hovered());
+
+    engine.send_input(InputEvent::CursorMoved(10.0, 10.0));
+    engine.send_input(InputEvent::MousePressed(MouseButton::Left));
+    engine.update().unwrap();
+    let pressed = interaction(&engine, button);
+    assert!(pressed.hovered() && pressed.pressed() && pressed.focused());
+    assert_eq!(background(&engine, button), wgpu::Color::RED);
+    assert!(engine.latest_event::<ButtonClicked>().is_none());
+
+    engine.send_input(InputEvent::MouseReleased(MouseButton::Left));
+    engine.update().unwrap();
+    assert_eq!(
+      engine.latest_event::<ButtonClicked>().unwrap().entity,
+      button
+    );
+    assert!(!interaction(&engine, button).pressed());
+    assert_eq!(background(&engine, button), wgpu::Color::BLUE);
+
+    click(&mut engine, 5.0, 35.0);
+    let toggled = engine.latest_event::<CheckboxToggled>().unwrap();
+    assert_eq!((toggled.entity, toggled.checked), (checkbox, true));
+    assert!(!interaction(&engine, button).focused());
+
+    click(&mut engine, 75.0, 60.0);
+    assert_eq!(engine.latest_event::<SliderChanged>().unwrap().value, 75.0);
+    assert_eq!(
+      engine.with_component::<Slider, _>(slider, |slider| slider.value),
+      Some(75.0)
+    );
+
+    click(&mut engine, 10.0, 80.0);
+    assert!(interaction(&engine, input).focused());
+    for character in ['h', 'i', '\r'] {
+      engine.send_input(InputEvent::Character(character));
+    }
+    engine.update().unwrap();
+    assert_eq!(engine.latest_event::<TextChanged>().unwrap().value, "hi");
+    // Held down, backspace repeats, and typing applies in order within the frame
+    for character in [BACKSPACE, BACKSPACE, 'y', 'o', BACKSPACE] {
+      engine.send_input(InputEvent::Character(character));
+    }
+    engine.send_input(InputEvent::KeyPressed(KeyCode::Enter));
+    engine.update().unwrap();
+    assert_eq!(engine.latest_event::<TextSubmitted>().unwrap().value, "y");
+    assert_eq!(
+      engine.with_component::<Text, _>(input, |text| text.value.clone()),
+      Some("y".to_owned())
+    );
+  }
+
+  #[test]
+  fn widgets_render_over_the_scene() {
+    let mut engine = P1::with_clock(Clock::manual()).unwrap();
+    engine.enable_headless_rendering(128, 128).unwrap();
+    if !gpu_available(&engine, "widgets_render_over_the_scene") {
+      return;
+    }
+    let [button, checkbox, slider, _] = widgets(&mut engine);
+    engine
+      .add_component(button, BackgroundColor(wgpu::Color::RED))
+      .unwrap();
+    engine
+      .add_component(checkbox, BackgroundColor(wgpu::Color::BLUE))
+      .unwrap();
+    engine
+      .add_component(slider, BackgroundColor(wgpu::Color::BLUE))
+      .unwrap();
+    engine
+      .with_component_mut::<Checkbox, _>(checkbox, |checkbox| checkbox.checked = true)
+      .unwrap();
+    engine
+      .with_component_mut::<Slider, _>(slider, |slider| slider.value = 100.0)
+      .unwrap();
+
+    engine.update().unwrap();
+    let image = engine.render_to_image().unwrap();
+    assert_eq!(image.pixel(50, 15), Some([255, 0, 0, 255]));
+    // Mark in the middle of the checkbox, its background around it
+    assert_eq!(image.pixel(10, 40), Some([255, 255, 255, 255]));
+    assert_eq!(image.pixel(2, 32), Some([0, 0, 255, 255]));
+    // Handle at the right end of the slider
+    assert_eq!(image.pixel(95, 60), Some([255, 255, 255, 255]));
+    assert_eq!(image.pixel(5, 60), Some([0, 0, 255, 255]));
+    assert_eq!(image.pixel(120, 120), Some([0, 0, 0, 255]));
+  }
+}
@@ -0,0 +1,28 @@
+use crate::ecs::Component;
+use crate::rendering::Rect;
+
+use glam::Vec2;
+use macros::Component;
+
+// Where the layout put a UI node, in logical pixels from the top left of its window
+// Added to every entity with a Style by the engine
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Node {
+  rect: Rect,
+}
+
+impl Node {
+  pub(crate) fn new(rect: Rect) -> Self {
+    Self { rect }
+  }
+
+  pub fn rect(&self) -> Rect {
+    self.rect
+  }
+  pub fn position(&self) -> Vec2 {
+    self.rect.min
+  }
+  pub fn size(&self) -> Vec2 {
+    self.rect.size()
+  }
+}
@@ -0,0 +1,165 @@
+use crate::ecs::Component;
+
+use macros::Component;
+
+// UI lengths are in logical pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Val {
+  #[default]
+  Auto,
+  Px(f32),
+  // Of the parent's content box, along the same axis
+  Percent(f32),
+}
+
+impl Val {
+  // None for Auto, and for percentages of something that isn't known yet
+  pub fn resolve(&self, parent: Option<f32>) -> Option<f32> {
+    match *self {
+      Val::Auto => None,
+      Val::Px(px) => Some(px),
+      Val::Percent(percent) => parent.map(|parent| parent * percent / 100.0),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UiRect {
+  pub left: Val,
+  pub right: Val,
+  pub top: Val,
+  pub bottom: Val,
+}
+
+impl UiRect {
+  pub fn all(val: Val) -> Self {
+    Self {
+      left: val,
+      right: val,
+      top: val,
+      bottom: val,
+    }
+  }
+
+  pub fn axes(horizontal: Val, vertical: Val) -> Self {
+    Self {
+      left: horizontal,
+      right: horizontal,
+      top: vertical,
+      bottom: vertical,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlexDirection {
+  #[default]
+  Row,
+  Column,
+  RowReverse,
+  ColumnReverse,
+}
+
+impl FlexDirection {
+  pub fn is_row(&self) -> bool {
+    matches!(self, FlexDirection::Row | FlexDirection::RowReverse)
+  }
+  pub fn is_reverse(&self) -> bool {
+    matches!(
+      self,
+      FlexDirection::RowReverse | FlexDirection::ColumnReverse
+    )
+  }
+}
+
+// Placement along the main axis
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JustifyContent {
+  #[default]
+  Start,
+  End,
+  Center,
+  SpaceBetween,
+  SpaceAround,
+  SpaceEvenly,
+}
+
+// Placement along the cross axis
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlignItems {
+  Start,
+  End,
+  Center,
+  #[default]
+  Stretch,
+}
+
+// Makes an entity a UI node, laid out by the engine every update
+// Children are the entity's Children that have a Style too, in the order they were parented
+// Roots are placed in the window of their Anchor, its point of the root lands on the same point of the window
+// Roots without one sit at the origin and their percentages count as Auto
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+  pub width: Val,
+  pub height: Val,
+  pub min_width: Val,
+  pub min_height: Val,
+  pub max_width: Val,
+  pub max_height: Val,
+  pub margin: UiRect,
+  pub padding: UiRect,
+  pub flex_direction: FlexDirection,
+  pub justify_content: JustifyContent,
+  pub align_items: AlignItems,
+  // Overrides the parent's align_items
+  pub align_self: Option<AlignItems>,
+  pub flex_grow: f32,
+  pub flex_shrink: f32,
+  // Space between children along the main axis
+  pub gap: f32,
+}
+
+impl Style {
+  pub fn with_size(mut self, width: Val, height: Val) -> Self {
+    self.width = width;
+    self.height = height;
+    self
+  }
+  pub fn with_direction(mut self, flex_direction: FlexDirection) -> Self {
+    self.flex_direction = flex_direction;
+    self
+  }
+  pub fn with_grow(mut self, flex_grow: f32) -> Self {
+    self.flex_grow = flex_grow;
+    self
+  }
+
+  // What's left of a width inside the padding, percentages of the padding count as nothing
+  pub fn content_width(&self, width: f32) -> f32 {
+    let left = self.padding.left.resolve(None).unwrap_or(0.0);
+    let right = self.padding.right.resolve(None).unwrap_or(0.0);
+    (width - left - right).max(0.0)
+  }
+}
+
+impl Default for Style {
+  fn default() -> Self {
+    Self {
+      width: Val::Auto,
+      height: Val::Auto,
+      min_width: Val::Auto,
+      min_height: Val::Auto,
+      max_width: Val::Auto,
+      max_height: Val::Auto,
+      margin: UiRect::default(),
+      padding: UiRect::default(),
+      flex_direction: FlexDirection::Row,
+      justify_content: JustifyContent::Start,
+      align_items: AlignItems::Stretch,
+      align_self: None,
+      flex_grow: 0.0,
+      flex_shrink: 1.0,
+      gap: 0.0,
+    }
+  }
+}
@@ -0,0 +1,155 @@
+use crate::asset::Handle;
+use crate::ecs::Component;
+use crate::event::BACKSPACE;
+use crate::rendering::Image;
+
+use macros::Component;
+
+// Fills the node's rectangle
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct BackgroundColor(pub wgpu::Color);
+
+// Stretches an image over the node's rectangle, tinted by color
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct UiImage {
+  pub texture: Handle<Image>,
+  pub color: wgpu::Color,
+}
+
+impl UiImage {
+  pub fn new(texture: Handle<Image>) -> Self {
+    Self {
+      texture,
+      color: wgpu::Color::WHITE,
+    }
+  }
+}
+
+// What the cursor and keyboard are doing to a node, kept up to date by the engine
+// Added to every widget, other nodes get one by adding it themselves
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Interaction {
+  pub(crate) hovered: bool,
+  pub(crate) pressed: bool,
+  pub(crate) focused: bool,
+}
+
+impl Interaction {
+  // Only the topmost node under the cursor is hovered
+  pub fn hovered(&self) -> bool {
+    self.hovered
+  }
+  // From the press on the node until the button is released, wherever the cursor went
+  pub fn pressed(&self) -> bool {
+    self.pressed
+  }
+  // The last node pressed, until another node or nothing is
+  pub fn focused(&self) -> bool {
+    self.focused
+  }
+}
+
+// Replaces the BackgroundColor depending on the Interaction, pressed over hovered over focused
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct InteractionColors {
+  pub normal: wgpu::Color,
+  pub hovered: wgpu::Color,
+  pub pressed: wgpu::Color,
+  pub focused: wgpu::Color,
+}
+
+impl InteractionColors {
+  pub fn color(&self, interaction: &Interaction) -> wgpu::Color {
+    if interaction.pressed {
+      self.pressed
+    } else if interaction.hovered {
+      self.hovered
+    } else if interaction.focused {
+      self.focused
+    } else {
+      self.normal
+    }
+  }
+}
+
+// Emits ButtonClicked when released over the node it was pressed on
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Button;
+
+// Toggled by clicks, a mark of color is drawn in the middle while checked
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Checkbox {
+  pub checked: bool,
+  pub color: wgpu::Color,
+}
+
+impl Checkbox {
+  pub fn new(checked: bool) -> Self {
+    Self {
+      checked,
+      color: wgpu::Color::WHITE,
+    }
+  }
+}
+
+// Horizontal, follows the cursor while pressed
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Slider {
+  pub value: f32,
+  pub min: f32,
+  pub max: f32,
+  pub handle_color: wgpu::Color,
+}
+
+impl Slider {
+  pub fn new(min: f32, max: f32, value: f32) -> Self {
+    Self {
+      value: value.clamp(min, max),
+      min,
+      max,
+      handle_color: wgpu::Color::WHITE,
+    }
+  }
+
+  // Where the value sits between min and max
+  pub fn fraction(&self) -> f32 {
+    if self.max > self.min {
+      ((self.value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    } else {
+      0.0
+    }
+  }
+}
+
+// Takes typed text while focused, backspace removes the last character and enter submits
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct TextInput {
+  pub value: String,
+  pub max_length: Option<usize>,
+}
+
+impl TextInput {
+  pub fn new(value: impl Into<String>) -> Self {
+    Self {
+      value: value.into(),
+      max_length: None,
+    }
+  }
+
+  pub(crate) fn type_text(&mut self, typed: &str) {
+    type_text(&mut self.value, typed, self.max_length);
+  }
+}
+
+// Characters and backspaces in the order they were typed, other control characters are left out
+pub(crate) fn type_text(value: &mut String, typed: &str, max_length: Option<usize>) {
+  for character in typed.chars() {
+    if character == BACKSPACE {
+      value.pop();
+    } else if !character.is_control()
+      && max_length.is_none_or(|max_length| value.chars().count() < max_length)
+    {
+      value.push(character);
+    }
+  }
+}
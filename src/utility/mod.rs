@@ -8,7 +8,7 @@ pub(super) fn create_whitespace_cstring_with_len(len: usize) -> CString {
 
 use std::any::{Any, TypeId};
 use std::collections::{
-  hash_map::{Values, ValuesMut},
+  hash_map::{Keys, Values, ValuesMut},
   HashMap,
 };
 use std::hash::Hash;
@@ -28,6 +28,24 @@ impl SyncBox {
   pub fn cast_mut<T: Send + Sync + Any>(&mut self) -> Option<&mut T> {
     self.0.downcast_mut::<T>()
   }
+
+  pub fn as_any(&self) -> &dyn Any {
+    &*self.0
+  }
+  pub fn as_any_mut(&mut self) -> &mut dyn Any {
+    &mut *self.0
+  }
+
+  // Of the boxed value, not of the box
+  pub fn inner_type_id(&self) -> TypeId {
+    (*self.0).type_id()
+  }
+}
+
+impl std::fmt::Debug for SyncBox {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("SyncBox").field(&self.inner_type_id()).finish()
+  }
 }
 
 // This is safe because the compiler will enforce the safety restriction thanks to <T: Send + Sync + Any>
@@ -39,6 +57,7 @@ unsafe impl Sync for SyncBox {}
 
 pub struct ErasedMapContainer<K: Eq + Hash> {
   type_id: TypeId,
+  type_name: &'static str,
   // Using HashMap here instead of DashMap because downcasting gets the inner value
   // Without regards to the lock, thus eliminating any potential thread safety we could've gotten there
   // To make this thread safe, I must ensure that the ErasedMapContainer is only ever accessed from a RwLock or the likes
@@ -49,6 +68,7 @@ impl<K: Eq + Hash> ErasedMapContainer<K> {
   pub fn new<T: Send + Sync + Any>() -> Self {
     Self {
       type_id: TypeId::of::<T>(),
+      type_name: std::any::type_name::<T>(),
       ptrs: HashMap::new(),
     }
   }
@@ -63,19 +83,61 @@ impl<K: Eq + Hash> ErasedMapContainer<K> {
     data: T,
   ) -> Result<(), UtilityContainerError> {
     if !self.is::<T>() {
-      return Err(UtilityContainerError::MismatchedTypeId(
-        self.type_id,
-        TypeId::of::<T>(),
-      ));
+      return Err(UtilityContainerError::MismatchedType {
+        expected: self.type_name,
+        found: std::any::type_name::<T>(),
+      });
+    }
+    self.insert_erased(key, SyncBox::new(data))
+  }
+
+  // Same as insert for values that were already boxed, checked against the container's type all the same
+  pub fn insert_erased(&mut self, key: K, data: SyncBox) -> Result<(), UtilityContainerError> {
+    if data.inner_type_id() != self.type_id {
+      return Err(UtilityContainerError::MismatchedType {
+        expected: self.type_name,
+        found: "a value of another type",
+      });
     } else if self.ptrs.contains_key(&key) {
-      return Err(UtilityContainerError::EntryOccupied);
+      return Err(UtilityContainerError::EntryOccupied(self.type_name));
     }
 
-    self.ptrs.insert(key, SyncBox::new(data));
+    self.ptrs.insert(key, data);
 
     Ok(())
   }
 
+  // Returns the value that was replaced
+  pub fn replace_erased(
+    &mut self,
+    key: K,
+    data: SyncBox,
+  ) -> Result<Option<SyncBox>, UtilityContainerError> {
+    if data.inner_type_id() != self.type_id {
+      return Err(UtilityContainerError::MismatchedType {
+        expected: self.type_name,
+        found: "a value of another type",
+      });
+    }
+    Ok(self.ptrs.insert(key, data))
+  }
+
+  pub fn type_name(&self) -> &'static str {
+    self.type_name
+  }
+
+  pub fn remove(&mut self, key: &K) -> Option<SyncBox> {
+    self.ptrs.remove(key)
+  }
+
+  pub fn contains_key(&self, key: &K) -> bool {
+    self.ptrs.contains_key(key)
+  }
+
+  pub fn get_erased(&self, key: &K) -> Option<&SyncBox> {
+    self.ptrs.get(key)
+  }
+
   pub fn get<T: Send + Sync + Any>(&self, key: &K) -> Option<&T> {
     self.ptrs.get(key).and_then(|ptr| ptr.cast_ref::<T>())
   }
@@ -84,6 +146,10 @@ impl<K: Eq + Hash> ErasedMapContainer<K> {
     self.ptrs.get_mut(key).and_then(|ptr| ptr.cast_mut::<T>())
   }
 
+  pub fn keys(&self) -> Keys<'_, K, SyncBox> {
+    self.ptrs.keys()
+  }
+
   pub fn iter(&self) -> Values<'_, K, SyncBox> {
     self.ptrs.values()
   }
@@ -91,4 +157,18 @@ impl<K: Eq + Hash> ErasedMapContainer<K> {
   pub fn iter_mut(&mut self) -> ValuesMut<'_, K, SyncBox> {
     self.ptrs.values_mut()
   }
+
+  pub fn entries<T: Send + Sync + Any>(&self) -> impl Iterator<Item = (&K, &T)> {
+    self
+      .ptrs
+      .iter()
+      .filter_map(|(key, ptr)| ptr.cast_ref::<T>().map(|data| (key, data)))
+  }
+
+  pub fn entries_mut<T: Send + Sync + Any>(&mut self) -> impl Iterator<Item = (&K, &mut T)> {
+    self
+      .ptrs
+      .iter_mut()
+      .filter_map(|(key, ptr)| ptr.cast_mut::<T>().map(|data| (key, data)))
+  }
 }